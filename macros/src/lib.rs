@@ -0,0 +1,89 @@
+//! Embeds odeir models in Rust code at compile time.
+
+use std::path::PathBuf;
+
+use odeir::{transformations::rust::render_rust, Model};
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{
+    parse::{Parse, ParseStream},
+    parse_macro_input, Ident, LitStr, Token, Visibility,
+};
+
+/// `[pub] mod name = "path/to/model.json"`
+struct ModelInput {
+    visibility: Visibility,
+    name: Ident,
+    path: LitStr,
+}
+
+impl Parse for ModelInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let visibility = input.parse()?;
+        input.parse::<Token![mod]>()?;
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        let path = input.parse()?;
+
+        Ok(Self {
+            visibility,
+            name,
+            path,
+        })
+    }
+}
+
+/// Expands to a module with the code from
+/// [`render_rust`](odeir::transformations::rust::render_rust) for an ODE
+/// model, read from a JSON file relative to the crate's root.
+///
+/// ```ignore
+/// odeir_macros::model!(pub mod predator_prey = "models/predator-prey.json");
+///
+/// let trajectory = predator_prey::simulate(&predator_prey::Params::default());
+/// ```
+#[proc_macro]
+pub fn model(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as ModelInput);
+
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(
+    ModelInput {
+        visibility,
+        name,
+        path,
+    }: ModelInput,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let error = |message: String| syn::Error::new(path.span(), message);
+
+    let root = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let full_path = PathBuf::from(root).join(path.value());
+
+    let json = std::fs::read_to_string(&full_path)
+        .map_err(|e| error(format!("couldn't read {}: {e}", full_path.display())))?;
+    let model: Model = serde_json::from_str(&json)
+        .map_err(|e| error(format!("invalid model in {}: {e}", full_path.display())))?;
+    let Model::ODE(model) = model else {
+        return Err(error("only ODE models can be embedded".into()));
+    };
+
+    let code: proc_macro2::TokenStream = render_rust(&model, &[])
+        .map_err(|e| error(format!("couldn't render {}: {e}", full_path.display())))?
+        .parse()
+        .map_err(|e| error(format!("generated code doesn't parse: {e}")))?;
+    let full_path = full_path.to_string_lossy();
+
+    Ok(quote! {
+        #visibility mod #name {
+            #code
+
+            // Rebuilds whenever the model changes
+            const _: &str = include_str!(#full_path);
+        }
+    })
+}
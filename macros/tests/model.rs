@@ -0,0 +1,67 @@
+use odeir::{models::ode::OdeModel, Model};
+
+odeir_macros::model!(mod predator_prey = "tests/fixtures/predator-prey.json");
+
+use predator_prey::{Params, State};
+
+fn predator_prey() -> OdeModel {
+    let json = include_str!("fixtures/predator-prey.json");
+    match serde_json::from_str(json).unwrap() {
+        Model::ODE(model) => model,
+        _ => unreachable!(),
+    }
+}
+
+#[test]
+fn defaults_come_from_the_model() {
+    assert_eq!(State::NAMES, ["predators", "prey"]);
+    assert_eq!(
+        State::default(),
+        State {
+            predators: 5.0,
+            prey: 10.0
+        }
+    );
+    assert_eq!(Params::NAMES, ["alpha", "beta", "delta", "gamma"]);
+    assert_eq!(Params::default().beta, 0.4);
+    assert_eq!(predator_prey::DELTA_TIME, 0.01);
+}
+
+#[test]
+fn rhs_matches_evaluation() {
+    let model = predator_prey();
+    let state = State {
+        predators: 3.0,
+        prey: 7.0,
+    };
+    let params = Params {
+        beta: 0.5,
+        ..Default::default()
+    };
+
+    let bindings = |name: &str| match name {
+        "predators" => Some(state.predators),
+        "prey" => Some(state.prey),
+        "beta" => Some(params.beta),
+        _ => None,
+    };
+    let derivative = predator_prey::rhs(0.0, &state, &params);
+
+    assert_eq!(
+        derivative.predators,
+        model.evaluate("dpredators", &bindings).unwrap()
+    );
+    assert_eq!(derivative.prey, model.evaluate("dprey", &bindings).unwrap());
+}
+
+#[test]
+fn simulation_covers_the_time_span() {
+    let trajectory = predator_prey::simulate(&Params::default());
+
+    assert_eq!(trajectory.len(), 2001);
+    assert_eq!(trajectory[0], (0.0, State::default()));
+    assert!((trajectory.last().unwrap().0 - 20.0).abs() < 1e-9);
+    assert!(trajectory
+        .iter()
+        .all(|(_, state)| state.prey > 0.0 && state.predators > 0.0));
+}
@@ -0,0 +1,13 @@
+//! Readers for model formats from other tools, producing the same models as
+//! the editor's JSON.
+
+pub mod antimony;
+mod expression;
+pub mod sbml;
+#[cfg(test)]
+mod test_models;
+pub mod txt;
+pub mod xpp;
+
+pub use expression::ExpressionError;
+pub(crate) use expression::{insert_rate, Expression};
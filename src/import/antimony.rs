@@ -0,0 +1,407 @@
+//! Import of reaction networks in a practical subset of Antimony, the
+//! notation of Tellurium:
+//!
+//! ```text
+//! model decay()
+//!   J0: S1 + 2 S2 -> S3; k1*S1*S2   // a labelled reaction and its rate law
+//!   S3 ->; k2*S3                    // degradation
+//!   $E -> S1; k3*E                  // E is a boundary species, left fixed
+//!   S1 = 10; S2 = 5; E = 1          // initial values
+//!   k1 = 0.1; k2 = 0.05; k3 = k1/2  // parameters
+//!   total := S1 + S2 + S3           // assignment rules
+//!   S3' = -S3/100                   // rate rules
+//! end
+//! ```
+//!
+//! Each rate law becomes a composite named after its reaction's label, and
+//! each species the reactions change gets an equation summing them, signed
+//! and scaled by the net number of the species each reaction makes.
+
+use std::fmt;
+
+use super::{insert_rate, Expression, ExpressionError};
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::Argument;
+use crate::Map;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AntimonyError {
+    /// Starting from 1.
+    pub line: usize,
+    pub kind: AntimonyErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AntimonyErrorKind {
+    /// A reaction without `; rate law`.
+    MissingRateLaw,
+    /// A reactant or product which isn't like `2 S1`.
+    InvalidSpecies(String),
+    InvalidName(String),
+    /// A species initialized with something other than a number.
+    InvalidInitialValue(String),
+    /// A statement outside the supported subset, like an event.
+    Unsupported(String),
+    Expression(ExpressionError),
+}
+
+impl fmt::Display for AntimonyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            AntimonyErrorKind::MissingRateLaw => write!(f, "the reaction has no rate law"),
+            AntimonyErrorKind::InvalidSpecies(species) => {
+                write!(f, "`{species}` is not a species")
+            }
+            AntimonyErrorKind::InvalidName(name) => write!(f, "`{name}` is not a valid name"),
+            AntimonyErrorKind::InvalidInitialValue(species) => {
+                write!(f, "the initial value of `{species}` is not a number")
+            }
+            AntimonyErrorKind::Unsupported(statement) => {
+                write!(f, "`{statement}` is not supported")
+            }
+            AntimonyErrorKind::Expression(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for AntimonyError {}
+
+/// Something the import had to guess.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AntimonyWarning {
+    /// A species or parameter which is used but never given a value, which
+    /// is taken to be 0 like Antimony does.
+    MissingValue { name: String },
+}
+
+impl fmt::Display for AntimonyWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingValue { name } => write!(f, "`{name}` has no value, using 0"),
+        }
+    }
+}
+
+/// Declarations which only say what a name is, which the model works out
+/// on its own.
+const DECLARATIONS: [&str; 5] = ["species", "compartment", "const", "var", "formula"];
+
+const UNSUPPORTED: [&str; 5] = ["at", "function", "import", "unit", "delete"];
+
+struct Reaction<'a> {
+    label: String,
+    /// Net number of each species made, negative if used up.
+    stoichiometries: Vec<(&'a str, f64)>,
+    rate: Expression,
+}
+
+/// Reads a reaction network, with default metadata.
+pub fn import_antimony(text: &str) -> Result<(OdeModel, Vec<AntimonyWarning>), AntimonyError> {
+    let mut name = String::new();
+    let mut reactions: Vec<Reaction> = Vec::new();
+    let mut boundary: Vec<&str> = Vec::new();
+    let mut values: Vec<(&str, Expression, usize)> = Vec::new();
+    let mut rules: Vec<(&str, Expression)> = Vec::new();
+    let mut rates: Vec<(&str, Expression)> = Vec::new();
+
+    for (line, content) in text.lines().enumerate() {
+        let line = line + 1;
+        let error = |kind| AntimonyError { line, kind };
+        let parse = |text: &str| {
+            Expression::parse(text).map_err(|err| error(AntimonyErrorKind::Expression(err)))
+        };
+        let content = content.split("//").next().unwrap_or_default();
+        let content = content.split('#').next().unwrap_or_default().trim();
+
+        if let Some(header) = content.strip_prefix("model ") {
+            name = header
+                .trim()
+                .trim_start_matches('*')
+                .split('(')
+                .next()
+                .unwrap_or_default()
+                .trim()
+                .to_owned();
+            continue;
+        }
+        if content == "end" {
+            continue;
+        }
+
+        let mut statements = content.split(';').map(str::trim);
+        while let Some(statement) = statements.next() {
+            if statement.is_empty() {
+                continue;
+            }
+
+            let arrow = statement.find("->").or_else(|| statement.find("=>"));
+            if let Some(arrow) = arrow {
+                let rate = statements
+                    .next()
+                    .filter(|rate| !rate.is_empty())
+                    .ok_or(error(AntimonyErrorKind::MissingRateLaw))?;
+
+                let (label, reactants) = match statement[..arrow].split_once(':') {
+                    Some((label, reactants)) => (label.trim().to_owned(), reactants),
+                    None => (format!("_J{}", reactions.len()), &statement[..arrow]),
+                };
+                if !is_identifier(&label) {
+                    return Err(error(AntimonyErrorKind::InvalidName(label)));
+                }
+
+                let mut stoichiometries: Vec<(&str, f64)> = Vec::new();
+                for (side, sign) in [(reactants, -1.0), (&statement[arrow + 2..], 1.0)] {
+                    for species in side.split('+').map(str::trim).filter(|s| !s.is_empty()) {
+                        let (n, species) = stoichiometry(species).ok_or_else(|| {
+                            error(AntimonyErrorKind::InvalidSpecies(species.into()))
+                        })?;
+                        let species = match species.strip_prefix('$') {
+                            Some(species) => {
+                                boundary.push(species);
+                                species
+                            }
+                            None => species,
+                        };
+                        match stoichiometries.iter_mut().find(|(s, _)| *s == species) {
+                            Some((_, total)) => *total += sign * n,
+                            None => stoichiometries.push((species, sign * n)),
+                        }
+                    }
+                }
+
+                reactions.push(Reaction {
+                    label,
+                    stoichiometries,
+                    rate: parse(rate)?,
+                });
+                continue;
+            }
+
+            let (keyword, rest) = statement.split_once(' ').unwrap_or((statement, ""));
+            if DECLARATIONS.contains(&keyword) {
+                // `species S1 = 10, S2` declares and may also initialize
+                for declaration in rest.split(',').map(str::trim) {
+                    if let Some((lhs, rhs)) = declaration.split_once('=') {
+                        let lhs = lhs.trim().trim_start_matches('$');
+                        values.push((lhs, parse(rhs)?, line));
+                    }
+                }
+                continue;
+            }
+
+            // Events, functions and other labelled statements
+            if UNSUPPORTED.contains(&keyword) || statement.replace(":=", "").contains(':') {
+                return Err(error(AntimonyErrorKind::Unsupported(statement.to_owned())));
+            }
+            if let Some((lhs, rhs)) = statement.split_once(":=") {
+                rules.push((identifier(lhs).map_err(error)?, parse(rhs)?));
+            } else if let Some((lhs, rhs)) = statement.split_once('=') {
+                let lhs = lhs.trim();
+                match lhs.strip_suffix('\'') {
+                    Some(variable) => {
+                        rates.push((identifier(variable).map_err(error)?, parse(rhs)?))
+                    }
+                    None => values.push((identifier(lhs).map_err(error)?, parse(rhs)?, line)),
+                }
+            } else {
+                return Err(error(AntimonyErrorKind::Unsupported(statement.to_owned())));
+            }
+        }
+    }
+
+    let mut model = OdeModel::new(name, Metadata::import_defaults());
+    let mut warnings = Vec::new();
+    let species: Vec<&str> = reactions
+        .iter()
+        .flat_map(|reaction| reaction.stoichiometries.iter().map(|&(s, _)| s))
+        .chain(rates.iter().map(|&(variable, _)| variable))
+        .collect();
+
+    for (name, expression, line) in values {
+        match expression.number() {
+            Some(value) => model.insert_argument(Argument::Value {
+                name: name.to_owned(),
+                value,
+            }),
+            None if species.contains(&name) => {
+                return Err(AntimonyError {
+                    line,
+                    kind: AntimonyErrorKind::InvalidInitialValue(name.to_owned()),
+                })
+            }
+            // Parameters computed from others
+            None => rules.push((name, expression)),
+        }
+    }
+    for (name, expression) in rules {
+        model.arguments.remove(name);
+        composite(expression).insert_into(&mut model, Some(name));
+    }
+
+    let mut terms: Map<&str, Vec<Expression>> = Map::new();
+    for reaction in &reactions {
+        let rate = composite(reaction.rate.clone()).insert_into(&mut model, Some(&reaction.label));
+        let rate = Expression::Name(rate.name);
+        for &(species, n) in &reaction.stoichiometries {
+            if n != 0.0 && !boundary.contains(&species) {
+                terms
+                    .entry(species)
+                    .or_default()
+                    .push(Expression::reaction_term(rate.clone(), n));
+            }
+        }
+    }
+    for (variable, expression) in &rates {
+        terms.entry(variable).or_default().push(expression.clone());
+    }
+
+    // Anything used without a value defaults to 0
+    let used: Vec<&str> = species
+        .iter()
+        .copied()
+        .chain(reactions.iter().flat_map(|reaction| reaction.rate.names()))
+        .chain(rates.iter().flat_map(|(_, expression)| expression.names()))
+        .collect();
+    for name in used {
+        if !model.arguments.contains_key(name) {
+            warnings.push(AntimonyWarning::MissingValue {
+                name: name.to_owned(),
+            });
+            model.insert_argument(Argument::Value {
+                name: name.to_owned(),
+                value: 0.0,
+            });
+        }
+    }
+
+    for (species, terms) in terms {
+        insert_rate(&mut model, species, terms);
+    }
+
+    Ok((model, warnings))
+}
+
+/// Wraps a lone name or number so that it gets a composite of its own.
+fn composite(expression: Expression) -> Expression {
+    match expression {
+        expression @ (Expression::Infixed(..) | Expression::Prefixed(..)) => expression,
+        expression => Expression::Infixed("+", vec![expression]),
+    }
+}
+
+/// Splits a reactant or product like `2 S1` or `$E` into its count and
+/// name.
+fn stoichiometry(text: &str) -> Option<(f64, &str)> {
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(text.len());
+    let (n, species) = match digits {
+        0 => (1.0, text),
+        _ => (text[..digits].parse().ok()?, text[digits..].trim()),
+    };
+    let species = species.strip_prefix('*').unwrap_or(species).trim();
+    is_identifier(species.trim_start_matches('$')).then_some((n, species))
+}
+
+fn identifier(text: &str) -> Result<&str, AntimonyErrorKind> {
+    let text = text.trim();
+    if is_identifier(text) {
+        Ok(text)
+    } else {
+        Err(AntimonyErrorKind::InvalidName(text.to_owned()))
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::test_models::{bindings, derivative};
+
+    const NETWORK: &str = "// A made up pathway
+model *pathway()
+  J0: -> S1; v0
+  J1: S1 -> 2 S2; k1*S1
+  S2 + $E => P; k2*S2*E   # unlabelled
+  P ->; k3*P
+
+  S1 = 10; S2 = 0
+  v0 = 1; k1 = 0.5; k2 = 0.2
+  k3 = k1/5
+  species E = 2
+  total := S1 + S2 + P
+end
+";
+
+    #[test]
+    fn reactions() {
+        let (model, warnings) = import_antimony(NETWORK).unwrap();
+
+        assert_eq!(model.name, "pathway");
+        assert_eq!(
+            warnings,
+            [AntimonyWarning::MissingValue { name: "P".into() }]
+        );
+        let populations: Vec<&str> = model.get_populations().map(Argument::name).collect();
+        assert_eq!(populations, ["P", "S1", "S2"]);
+        for label in ["J0", "J1", "_J2", "_J3", "k3", "total"] {
+            assert!(
+                matches!(model.arguments[label], Argument::Composite { .. }),
+                "{label} should be a composite"
+            );
+        }
+
+        let state = [("S1", 4.0), ("S2", 3.0), ("P", 2.0)];
+        assert_eq!(derivative(&model, "S1", &state), 1.0 - 0.5 * 4.0);
+        assert_eq!(
+            derivative(&model, "S2", &state),
+            2.0 * 0.5 * 4.0 - 0.2 * 3.0 * 2.0
+        );
+        assert_eq!(derivative(&model, "P", &state), 0.2 * 3.0 * 2.0 - 0.1 * 2.0);
+        assert_eq!(model.evaluate("total", &bindings(&state)), Ok(9.0));
+    }
+
+    #[test]
+    fn rate_rules_and_catalysts() {
+        // A catalyst appears on both sides, so it doesn't change
+        let text = "C + S -> C + P; k*C*S\nS = 1; C = 1; P = 0; k = 1\nP' = -P";
+        let (model, warnings) = import_antimony(text).unwrap();
+
+        assert_eq!(warnings, []);
+        let populations: Vec<&str> = model.get_populations().map(Argument::name).collect();
+        assert_eq!(populations, ["P", "S"]);
+        let state = [("S", 2.0), ("C", 3.0), ("P", 1.0)];
+        assert_eq!(derivative(&model, "P", &state), 6.0 - 1.0);
+        assert_eq!(derivative(&model, "S", &state), -6.0);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text| import_antimony(text).unwrap_err();
+
+        assert_eq!(
+            error("S1 = 1\nS1 -> S2"),
+            AntimonyError {
+                line: 2,
+                kind: AntimonyErrorKind::MissingRateLaw
+            }
+        );
+        assert_eq!(
+            error("2x S1 -> S2; k").kind,
+            AntimonyErrorKind::InvalidSpecies("2x S1".into())
+        );
+        assert_eq!(
+            error("S1 -> S2; k\nS1 = k*2").kind,
+            AntimonyErrorKind::InvalidInitialValue("S1".into())
+        );
+        assert_eq!(
+            error("at time > 5: k = 0").to_string(),
+            "line 1: `at time > 5: k = 0` is not supported"
+        );
+    }
+}
@@ -0,0 +1,524 @@
+//! Expressions read from other formats, and how they're stored in a model.
+
+use std::fmt;
+
+use crate::models::{Argument, Component, CompositionStyle, CoreModel, Equation};
+
+/// An expression tree read from another format, before it is stored in a
+/// model as composites.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Expression {
+    Name(String),
+    Number(f64),
+    Negated(Box<Expression>),
+    Infixed(&'static str, Vec<Expression>),
+    Prefixed(String, Vec<Expression>),
+}
+
+impl Expression {
+    /// Parses infix notation, like `alpha*x - beta*x*y`, with Python's
+    /// precedence. Functions are called like `max(a, b)`.
+    pub(crate) fn parse(text: &str) -> Result<Self, ExpressionError> {
+        let mut parser = Parser {
+            tokens: tokenize(text)?,
+            position: 0,
+        };
+        let expression = parser.comparison()?;
+        match parser.peek() {
+            None => Ok(expression),
+            Some(token) => Err(ExpressionError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// The value of a number, possibly negated.
+    pub(crate) fn number(&self) -> Option<f64> {
+        match self {
+            Self::Number(value) => Some(*value),
+            Self::Negated(expression) => expression.number().map(|value| -value),
+            _ => None,
+        }
+    }
+
+    /// The names the expression refers to, in order of appearance.
+    pub(crate) fn names(&self) -> Vec<&str> {
+        match self {
+            Self::Name(name) => vec![name],
+            Self::Number(_) => Vec::new(),
+            Self::Negated(expression) => expression.names(),
+            Self::Infixed(_, operands) | Self::Prefixed(_, operands) => {
+                operands.iter().flat_map(Self::names).collect()
+            }
+        }
+    }
+
+    /// A reaction's contribution to a species, given its rate and the net
+    /// number of the species it makes, which is negative if it uses it up.
+    pub(crate) fn reaction_term(rate: Self, stoichiometry: f64) -> Self {
+        let mut term = rate;
+        if stoichiometry.abs() != 1.0 {
+            term = Self::Infixed("*", vec![Self::Number(stoichiometry.abs()), term]);
+        }
+        if stoichiometry < 0.0 {
+            term = term.negated();
+        }
+        term
+    }
+
+    pub(crate) fn negated(self) -> Self {
+        match self {
+            Self::Negated(expression) => *expression,
+            expression => Self::Negated(Box::new(expression)),
+        }
+    }
+
+    /// How the expression reads, which also names its composite.
+    pub(crate) fn text(&self) -> String {
+        let operand = |expression: &Expression| match expression {
+            Self::Infixed(..) => format!("({})", expression.text()),
+            _ => expression.text(),
+        };
+
+        match self {
+            Self::Name(name) => name.clone(),
+            Self::Number(value) => value.to_string(),
+            Self::Negated(expression) => format!("-{}", operand(expression)),
+            Self::Infixed(operation, operands) => operands
+                .iter()
+                .map(operand)
+                .collect::<Vec<_>>()
+                .join(operation),
+            Self::Prefixed(function, operands) => format!(
+                "{function}({})",
+                operands
+                    .iter()
+                    .map(Self::text)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+        }
+    }
+
+    /// Stores the expression in `model`, with a composite for each operation
+    /// and a constant for each number, and returns the component referring
+    /// to it.
+    ///
+    /// Composites are named after their text, except for the outermost one,
+    /// which is called `name` unless another argument already is.
+    pub(crate) fn insert_into(&self, model: &mut CoreModel, name: Option<&str>) -> Component {
+        let component = |name: String| Component {
+            name,
+            contribution: '+',
+        };
+
+        let (operation, style, operands) = match self {
+            Self::Name(name) => return component(name.clone()),
+            Self::Number(value) if *value < 0.0 => {
+                return Self::Number(-value).negated().insert_into(model, name)
+            }
+            Self::Number(value) => return component(model.insert_number(*value)),
+            Self::Negated(expression) => {
+                let mut component = expression.insert_into(model, name);
+                component.contribution = if component.contribution == '-' {
+                    '+'
+                } else {
+                    '-'
+                };
+                return component;
+            }
+            Self::Infixed(operation, operands) => (*operation, CompositionStyle::Infixed, operands),
+            Self::Prefixed(function, operands) => {
+                (function.as_str(), CompositionStyle::Prefixed, operands)
+            }
+        };
+
+        let composition = operands
+            .iter()
+            .map(|operand| operand.insert_into(model, None))
+            .collect();
+        let name = match name {
+            Some(name) if !model.arguments.contains_key(name) => name.to_owned(),
+            _ => self.text(),
+        };
+        model.insert_argument(Argument::Composite {
+            name: name.clone(),
+            operation: operation.to_owned(),
+            style,
+            composition,
+        });
+        component(name)
+    }
+}
+
+/// Makes the sum of `terms` the rate of change of `population`, with a
+/// single equation and a composite called like `dX/dt`, since backends
+/// expect one equation for each population.
+pub(crate) fn insert_rate(model: &mut CoreModel, population: &str, mut terms: Vec<Expression>) {
+    let name = format!("d{population}/dt");
+    let expression = match terms.len() {
+        1 => terms.pop().unwrap(),
+        _ => Expression::Infixed("+", terms),
+    };
+    let argument = match expression {
+        Expression::Name(argument) => argument,
+        expression @ (Expression::Infixed(..) | Expression::Prefixed(..)) => {
+            expression.insert_into(model, Some(&name)).name
+        }
+        // Equations' contributions are ignored by backends, so a negated
+        // rate needs a composite of its own
+        expression => {
+            Expression::Infixed("+", vec![expression])
+                .insert_into(model, Some(&name))
+                .name
+        }
+    };
+    model.insert_equation(Equation {
+        name,
+        operates_on: Some(population.to_owned()),
+        argument,
+        contribution: '+',
+    });
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ExpressionError {
+    UnexpectedCharacter(char),
+    UnexpectedToken(String),
+    UnexpectedEnd,
+}
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnexpectedCharacter(c) => write!(f, "unexpected character `{c}`"),
+            Self::UnexpectedToken(token) => write!(f, "unexpected `{token}`"),
+            Self::UnexpectedEnd => write!(f, "unexpected end of expression"),
+        }
+    }
+}
+
+impl std::error::Error for ExpressionError {}
+
+/// Longer symbols first, so that `**` isn't read as two `*`.
+const SYMBOLS: [&str; 16] = [
+    "**", "==", "!=", "<=", ">=", "+", "-", "*", "/", "%", "^", "<", ">", "(", ")", ",",
+];
+const COMPARISONS: [&str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Token<'a> {
+    Number(f64),
+    Name(&'a str),
+    Symbol(&'static str),
+}
+
+impl fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(value) => value.fmt(f),
+            Self::Name(name) => name.fmt(f),
+            Self::Symbol(symbol) => symbol.fmt(f),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token<'_>>, ExpressionError> {
+    let mut tokens = Vec::new();
+    let mut rest = text.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let length = if c.is_ascii_digit() || c == '.' {
+            let mut length = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '.')
+                .unwrap_or(rest.len());
+            // An exponent, like the `e-3` of `1.5e-3`
+            let exponent = rest[length..]
+                .strip_prefix(['e', 'E'])
+                .map(|e| e.strip_prefix(['+', '-']).unwrap_or(e));
+            if let Some(digits) = exponent.filter(|e| e.starts_with(|c: char| c.is_ascii_digit())) {
+                length = rest.len() - digits.len()
+                    + digits
+                        .find(|c: char| !c.is_ascii_digit())
+                        .unwrap_or(digits.len());
+            }
+            let number = &rest[..length];
+            tokens.push(Token::Number(
+                number
+                    .parse()
+                    .map_err(|_| ExpressionError::UnexpectedToken(number.to_owned()))?,
+            ));
+            length
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let length = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Name(&rest[..length]));
+            length
+        } else {
+            let symbol = SYMBOLS
+                .into_iter()
+                .find(|symbol| rest.starts_with(symbol))
+                .ok_or(ExpressionError::UnexpectedCharacter(c))?;
+            tokens.push(Token::Symbol(symbol));
+            symbol.len()
+        };
+        rest = rest[length..].trim_start();
+    }
+
+    Ok(tokens)
+}
+
+/// A recursive descent parser, from the loosest binding operators to the
+/// tightest.
+struct Parser<'a> {
+    tokens: Vec<Token<'a>>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<Token<'a>> {
+        self.tokens.get(self.position).copied()
+    }
+
+    fn next(&mut self) -> Result<Token<'a>, ExpressionError> {
+        let token = self.peek().ok_or(ExpressionError::UnexpectedEnd)?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    /// Consumes the next token if it's one of `symbols`.
+    fn eat(&mut self, symbols: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Symbol(symbol)) if symbols.contains(&symbol) => {
+                self.position += 1;
+                Some(symbol)
+            }
+            _ => None,
+        }
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), ExpressionError> {
+        match self.next()? {
+            Token::Symbol(next) if next == symbol => Ok(()),
+            token => Err(ExpressionError::UnexpectedToken(token.to_string())),
+        }
+    }
+
+    /// Comparisons chain like in Python, so `a < b <= c` means
+    /// `a < b and b <= c`.
+    fn comparison(&mut self) -> Result<Expression, ExpressionError> {
+        let mut operands = vec![self.sum()?];
+        let mut operations = Vec::new();
+        while let Some(operation) = self.eat(&COMPARISONS) {
+            operations.push(operation);
+            operands.push(self.sum()?);
+        }
+
+        Ok(match operations.as_slice() {
+            [] => operands.pop().unwrap(),
+            [first, rest @ ..] if rest.iter().all(|operation| operation == first) => {
+                Expression::Infixed(first, operands)
+            }
+            _ => Expression::Infixed(
+                "*",
+                operations
+                    .iter()
+                    .zip(operands.windows(2))
+                    .map(|(operation, pair)| Expression::Infixed(operation, pair.to_vec()))
+                    .collect(),
+            ),
+        })
+    }
+
+    /// Sums and differences are a single sum, with the subtracted terms
+    /// negated.
+    fn sum(&mut self) -> Result<Expression, ExpressionError> {
+        let mut terms = vec![self.product()?];
+        while let Some(operation) = self.eat(&["+", "-"]) {
+            let term = self.product()?;
+            terms.push(if operation == "-" {
+                term.negated()
+            } else {
+                term
+            });
+        }
+
+        Ok(match terms.len() {
+            1 => terms.pop().unwrap(),
+            _ => Expression::Infixed("+", terms),
+        })
+    }
+
+    /// Runs of the same operation share a composite, folded from the left.
+    fn product(&mut self) -> Result<Expression, ExpressionError> {
+        let mut operands = vec![self.unary()?];
+        let mut current = None;
+        while let Some(operation) = self.eat(&["*", "/", "%"]) {
+            if let Some(previous) = current.filter(|&previous| previous != operation) {
+                operands = vec![Expression::Infixed(previous, operands)];
+            }
+            current = Some(operation);
+            operands.push(self.unary()?);
+        }
+
+        Ok(match current {
+            Some(operation) => Expression::Infixed(operation, operands),
+            None => operands.pop().unwrap(),
+        })
+    }
+
+    fn unary(&mut self) -> Result<Expression, ExpressionError> {
+        match self.eat(&["-", "+"]) {
+            Some("-") => Ok(self.unary()?.negated()),
+            Some(_) => self.unary(),
+            None => self.power(),
+        }
+    }
+
+    /// Powers are right associative and bind tighter than a unary minus on
+    /// their left, so `-a^-b` is `-(a^(-b))`.
+    fn power(&mut self) -> Result<Expression, ExpressionError> {
+        let base = self.primary()?;
+        let Some(operation) = self.eat(&["^", "**"]) else {
+            return Ok(base);
+        };
+
+        Ok(match self.unary()? {
+            Expression::Infixed(exponent, mut operands) if exponent == operation => {
+                operands.insert(0, base);
+                Expression::Infixed(operation, operands)
+            }
+            exponent => Expression::Infixed(operation, vec![base, exponent]),
+        })
+    }
+
+    fn primary(&mut self) -> Result<Expression, ExpressionError> {
+        match self.next()? {
+            Token::Number(value) => Ok(Expression::Number(value)),
+            Token::Name(name) if self.eat(&["("]).is_some() => {
+                let mut arguments = vec![self.comparison()?];
+                while self.eat(&[","]).is_some() {
+                    arguments.push(self.comparison()?);
+                }
+                self.expect(")")?;
+                Ok(Expression::Prefixed(name.to_owned(), arguments))
+            }
+            Token::Name(name) => Ok(Expression::Name(name.to_owned())),
+            Token::Symbol("(") => {
+                let expression = self.comparison()?;
+                self.expect(")")?;
+                Ok(expression)
+            }
+            token => Err(ExpressionError::UnexpectedToken(token.to_string())),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expressions_become_composites() {
+        let mut model = CoreModel::new();
+        model.insert_argument(Argument::Value {
+            name: "c2".into(),
+            value: 3.0,
+        });
+        let expression = Expression::Infixed(
+            "*",
+            vec![
+                Expression::Number(2.0),
+                Expression::Negated(Box::new(Expression::Infixed(
+                    "+",
+                    vec![Expression::Name("a".into()), Expression::Number(-0.5)],
+                ))),
+            ],
+        );
+
+        let component = expression.insert_into(&mut model, Some("rate"));
+
+        assert_eq!(component.name, "rate");
+        assert_eq!(expression.text(), "2*-(a+-0.5)");
+        let names: Vec<&String> = model.arguments.keys().collect();
+        assert_eq!(names, ["a+-0.5", "c0_5", "c2", "c2_", "rate"]);
+
+        let bindings = |name: &str| (name == "a").then_some(4.0);
+        assert_eq!(model.evaluate("rate", &bindings), Ok(-7.0));
+    }
+
+    #[test]
+    fn parsing() {
+        let name = |name: &str| Expression::Name(name.into());
+        let parse = |text| Expression::parse(text).unwrap();
+
+        assert_eq!(
+            parse("a - b*c/d + -2.5e-1"),
+            Expression::Infixed(
+                "+",
+                vec![
+                    name("a"),
+                    Expression::Infixed(
+                        "/",
+                        vec![
+                            Expression::Infixed("*", vec![name("b"), name("c")]),
+                            name("d")
+                        ]
+                    )
+                    .negated(),
+                    Expression::Number(0.25).negated(),
+                ]
+            )
+        );
+        assert_eq!(
+            parse("-a^b**c^d"),
+            Expression::Infixed(
+                "^",
+                vec![
+                    name("a"),
+                    Expression::Infixed(
+                        "**",
+                        vec![
+                            name("b"),
+                            Expression::Infixed("^", vec![name("c"), name("d")])
+                        ]
+                    )
+                ]
+            )
+            .negated()
+        );
+        assert_eq!(
+            parse("max(a, (b)) < c < 1"),
+            Expression::Infixed(
+                "<",
+                vec![
+                    Expression::Prefixed("max".into(), vec![name("a"), name("b")]),
+                    name("c"),
+                    Expression::Number(1.0)
+                ]
+            )
+        );
+        assert_eq!(
+            parse("a < b >= c"),
+            Expression::Infixed(
+                "*",
+                vec![
+                    Expression::Infixed("<", vec![name("a"), name("b")]),
+                    Expression::Infixed(">=", vec![name("b"), name("c")])
+                ]
+            )
+        );
+
+        assert_eq!(
+            Expression::parse("a +"),
+            Err(ExpressionError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Expression::parse("f(a b)"),
+            Err(ExpressionError::UnexpectedToken("b".into()))
+        );
+        assert_eq!(
+            Expression::parse("a $ b"),
+            Err(ExpressionError::UnexpectedCharacter('$'))
+        );
+    }
+}
@@ -0,0 +1,849 @@
+//! SBML import, for editing curated models from BioModels and the like.
+//!
+//! Compartments, species and parameters become values, and the rate rules
+//! and reactions changing species become equations. Anything else, like
+//! events, is skipped with a warning instead of failing the whole import.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use roxmltree::{Document, Node};
+
+use super::{insert_rate, Expression};
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::Argument;
+
+#[derive(Debug)]
+pub enum SbmlError {
+    Xml(roxmltree::Error),
+    NotSbml,
+    NoModel,
+}
+
+impl fmt::Display for SbmlError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Xml(err) => err.fmt(f),
+            Self::NotSbml => write!(f, "the document is not SBML"),
+            Self::NoModel => write!(f, "the document has no model"),
+        }
+    }
+}
+
+impl std::error::Error for SbmlError {}
+
+impl From<roxmltree::Error> for SbmlError {
+    fn from(value: roxmltree::Error) -> Self {
+        Self::Xml(value)
+    }
+}
+
+/// Something the import left out or had to guess.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SbmlWarning {
+    /// An element odeir has no equivalent for, like an event.
+    Unsupported { element: String, id: Option<String> },
+    /// A MathML element that couldn't be converted, which drops the rule or
+    /// reaction in `context`.
+    UnsupportedMath { element: String, context: String },
+    /// A species or parameter without a value, which is taken to be 0.
+    MissingValue { id: String },
+    /// A reaction without a kinetic law, which is dropped.
+    MissingKineticLaw { reaction: String },
+    /// A species reference to a species that doesn't exist.
+    UnknownSpecies { reaction: String, species: String },
+}
+
+impl fmt::Display for SbmlWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported { element, id: None } => {
+                write!(f, "`{element}` is not supported")
+            }
+            Self::Unsupported {
+                element,
+                id: Some(id),
+            } => write!(f, "`{element}` `{id}` is not supported"),
+            Self::UnsupportedMath { element, context } => {
+                write!(f, "MathML `{element}` in {context} is not supported")
+            }
+            Self::MissingValue { id } => write!(f, "`{id}` has no value, using 0"),
+            Self::MissingKineticLaw { reaction } => {
+                write!(f, "reaction `{reaction}` has no kinetic law")
+            }
+            Self::UnknownSpecies { reaction, species } => {
+                write!(
+                    f,
+                    "reaction `{reaction}` refers to unknown species `{species}`"
+                )
+            }
+        }
+    }
+}
+
+/// Reads an SBML document of any level into a model with default metadata.
+///
+/// SBML identifiers become argument names. Reactions add an equation for
+/// each species they change, with the kinetic law as its argument, scaled
+/// by the species' stoichiometry. Kinetic laws give amounts, so they're
+/// also divided by the compartment of species measured in concentrations,
+/// whose initial amounts are made concentrations too.
+pub fn import_sbml(xml: &str) -> Result<(OdeModel, Vec<SbmlWarning>), SbmlError> {
+    let document = Document::parse(xml)?;
+    let root = document.root_element();
+    if root.tag_name().name() != "sbml" {
+        return Err(SbmlError::NotSbml);
+    }
+    let sbml_model = element(root, "model").next().ok_or(SbmlError::NoModel)?;
+    let name = sbml_model
+        .attribute("name")
+        .or(sbml_model.attribute("id"))
+        .unwrap_or_default();
+
+    let mut importer = Importer {
+        model: OdeModel::new(name.to_owned(), Metadata::import_defaults()),
+        fixed_species: Vec::new(),
+        concentrations: HashMap::new(),
+        rates: Vec::new(),
+        warnings: Vec::new(),
+    };
+
+    for list in sbml_model.children().filter(Node::is_element) {
+        match list.tag_name().name() {
+            "listOfCompartments" => {
+                for compartment in element(list, "compartment") {
+                    importer.value(compartment, "size", Some(1.0));
+                }
+            }
+            "listOfSpecies" => {
+                for species in element(list, "species") {
+                    let value = if species.has_attribute("initialAmount") {
+                        "initialAmount"
+                    } else {
+                        "initialConcentration"
+                    };
+                    importer.value(species, value, None);
+                    importer.concentration(species);
+                    if species.attribute("constant") == Some("true")
+                        || species.attribute("boundaryCondition") == Some("true")
+                    {
+                        importer
+                            .fixed_species
+                            .extend(species.attribute("id").map(str::to_owned));
+                    }
+                }
+            }
+            "listOfParameters" => {
+                for parameter in element(list, "parameter") {
+                    importer.value(parameter, "value", None);
+                }
+            }
+            "listOfRules" => {
+                for rule in list.children().filter(Node::is_element) {
+                    importer.rule(rule);
+                }
+            }
+            "listOfReactions" => {
+                for reaction in element(list, "reaction") {
+                    importer.reaction(reaction);
+                }
+            }
+            // Neither changes how the model evolves
+            "listOfUnitDefinitions" | "notes" | "annotation" => {}
+            _ => {
+                for unsupported in list.children().filter(Node::is_element) {
+                    importer.warnings.push(SbmlWarning::Unsupported {
+                        element: unsupported.tag_name().name().to_owned(),
+                        id: unsupported.attribute("id").map(str::to_owned),
+                    });
+                }
+            }
+        }
+    }
+
+    for (population, terms) in importer.rates {
+        insert_rate(&mut importer.model, &population, terms);
+    }
+
+    Ok((importer.model, importer.warnings))
+}
+
+struct Importer {
+    model: OdeModel,
+    /// Species which reactions don't change.
+    fixed_species: Vec<String>,
+    /// The compartment of each species measured in concentrations.
+    concentrations: HashMap<String, String>,
+    /// Terms of the rate of change of each population, from rules and
+    /// reactions.
+    rates: Vec<(String, Vec<Expression>)>,
+    warnings: Vec<SbmlWarning>,
+}
+
+impl Importer {
+    fn value(&mut self, node: Node, attribute: &str, default: Option<f64>) {
+        let Some(id) = node.attribute("id") else {
+            return;
+        };
+        let value = node
+            .attribute(attribute)
+            .and_then(|value| value.trim().parse().ok())
+            .or(default)
+            .unwrap_or_else(|| {
+                self.warnings
+                    .push(SbmlWarning::MissingValue { id: id.to_owned() });
+                0.0
+            });
+        self.model.insert_argument(Argument::Value {
+            name: id.to_owned(),
+            value,
+        });
+    }
+
+    /// Records the compartment of `species` if it's measured in
+    /// concentrations, making its initial amount, if it has one, a
+    /// concentration.
+    fn concentration(&mut self, species: Node) {
+        let (Some(id), Some(compartment)) =
+            (species.attribute("id"), species.attribute("compartment"))
+        else {
+            return;
+        };
+        if species.attribute("hasOnlySubstanceUnits") == Some("true") {
+            return;
+        }
+        if species.has_attribute("initialAmount") {
+            let size = match self.model.arguments.get(compartment) {
+                Some(Argument::Value { value, .. }) => *value,
+                _ => 1.0,
+            };
+            if let Some(Argument::Value { value, .. }) = self.model.arguments.get_mut(id) {
+                *value /= size;
+            }
+        }
+        self.concentrations
+            .insert(id.to_owned(), compartment.to_owned());
+    }
+
+    fn rule(&mut self, rule: Node) {
+        let kind = rule.tag_name().name();
+        let Some(variable) = rule.attribute("variable") else {
+            self.warnings.push(SbmlWarning::Unsupported {
+                element: kind.to_owned(),
+                id: rule.attribute("id").map(str::to_owned),
+            });
+            return;
+        };
+        let context = format!("the rule for `{variable}`");
+        let Some(expression) = self.math(rule, &HashMap::new(), &context) else {
+            return;
+        };
+
+        match kind {
+            "rateRule" => self.add_rate(variable, expression),
+            // The variable becomes a composite, so that it's recomputed
+            // wherever it's used
+            "assignmentRule" => {
+                self.model.arguments.remove(variable);
+                Expression::Infixed("+", vec![expression])
+                    .insert_into(&mut self.model, Some(variable));
+            }
+            _ => self.warnings.push(SbmlWarning::Unsupported {
+                element: kind.to_owned(),
+                id: Some(variable.to_owned()),
+            }),
+        }
+    }
+
+    fn reaction(&mut self, reaction: Node) {
+        let Some(id) = reaction.attribute("id") else {
+            return;
+        };
+        let Some(kinetic_law) = element(reaction, "kineticLaw").next() else {
+            self.warnings.push(SbmlWarning::MissingKineticLaw {
+                reaction: id.to_owned(),
+            });
+            return;
+        };
+
+        // Local parameters only apply to their reaction, so they're renamed
+        // to keep them apart from global ones
+        let mut renames = HashMap::new();
+        for list in element(kinetic_law, "listOfLocalParameters")
+            .chain(element(kinetic_law, "listOfParameters"))
+        {
+            for parameter in list.children().filter(Node::is_element) {
+                let (Some(local), Some(value)) = (
+                    parameter.attribute("id"),
+                    parameter.attribute("value").and_then(|v| v.parse().ok()),
+                ) else {
+                    continue;
+                };
+                let name = format!("{id}_{local}");
+                self.model.insert_argument(Argument::Value {
+                    name: name.clone(),
+                    value,
+                });
+                renames.insert(local.to_owned(), name);
+            }
+        }
+
+        let context = format!("reaction `{id}`");
+        let Some(rate) = self.math(kinetic_law, &renames, &context) else {
+            return;
+        };
+        let rate = rate.insert_into(&mut self.model, Some(id));
+        let rate_is_negated = rate.contribution == '-';
+
+        // Net stoichiometry of each species, in order of appearance
+        let mut stoichiometries: Vec<(String, f64)> = Vec::new();
+        for (list, sign) in [("listOfReactants", -1.0), ("listOfProducts", 1.0)] {
+            for reference in element(reaction, list).flat_map(|l| element(l, "speciesReference")) {
+                let Some(species) = reference.attribute("species") else {
+                    continue;
+                };
+                if element(reference, "stoichiometryMath").next().is_some() {
+                    self.warnings.push(SbmlWarning::Unsupported {
+                        element: "stoichiometryMath".into(),
+                        id: Some(id.to_owned()),
+                    });
+                }
+                if !self.model.arguments.contains_key(species) {
+                    self.warnings.push(SbmlWarning::UnknownSpecies {
+                        reaction: id.to_owned(),
+                        species: species.to_owned(),
+                    });
+                    continue;
+                }
+                let stoichiometry: f64 = reference
+                    .attribute("stoichiometry")
+                    .and_then(|n| n.parse().ok())
+                    .unwrap_or(1.0);
+                match stoichiometries.iter_mut().find(|(s, _)| s == species) {
+                    Some((_, n)) => *n += sign * stoichiometry,
+                    None => stoichiometries.push((species.to_owned(), sign * stoichiometry)),
+                }
+            }
+        }
+
+        let mut rate = Expression::Name(rate.name.clone());
+        if rate_is_negated {
+            rate = rate.negated();
+        }
+        for (species, n) in stoichiometries {
+            if n == 0.0 || self.fixed_species.contains(&species) {
+                continue;
+            }
+            let rate = match self.concentrations.get(&species) {
+                Some(compartment) => per_volume(rate.clone(), compartment),
+                None => rate.clone(),
+            };
+            self.add_rate(&species, Expression::reaction_term(rate, n));
+        }
+    }
+
+    /// Adds `term` to the rate of change of `population`.
+    fn add_rate(&mut self, population: &str, term: Expression) {
+        match self.rates.iter_mut().find(|(p, _)| p == population) {
+            Some((_, terms)) => terms.push(term),
+            None => self.rates.push((population.to_owned(), vec![term])),
+        }
+    }
+
+    /// Converts the `math` child of `node`, warning about and returning
+    /// `None` for anything that can't be converted.
+    fn math(
+        &mut self,
+        node: Node,
+        renames: &HashMap<String, String>,
+        context: &str,
+    ) -> Option<Expression> {
+        let math = element(node, "math").next()?.first_element_child()?;
+        let mut unsupported = None;
+        let expression = mathml(math, renames, &mut unsupported);
+        if let Some(element) = unsupported {
+            self.warnings.push(SbmlWarning::UnsupportedMath {
+                element,
+                context: context.to_owned(),
+            });
+        }
+        expression
+    }
+}
+
+/// `rate` divided by the size of `compartment`, keeping any negation
+/// outermost.
+fn per_volume(rate: Expression, compartment: &str) -> Expression {
+    let divided = |rate| Expression::Infixed("/", vec![rate, Expression::Name(compartment.into())]);
+    match rate {
+        Expression::Negated(rate) => divided(*rate).negated(),
+        rate => divided(rate),
+    }
+}
+
+/// Records the first element that couldn't be converted.
+fn fail(unsupported: &mut Option<String>, element: &str) -> Option<Expression> {
+    unsupported.get_or_insert_with(|| element.to_owned());
+    None
+}
+
+/// Element children of `node` called `name`, in any namespace.
+fn element<'a, 'input: 'a>(
+    node: Node<'a, 'input>,
+    name: &'a str,
+) -> impl Iterator<Item = Node<'a, 'input>> + 'a {
+    node.children()
+        .filter(move |child| child.is_element() && child.tag_name().name() == name)
+}
+
+fn mathml(
+    node: Node,
+    renames: &HashMap<String, String>,
+    unsupported: &mut Option<String>,
+) -> Option<Expression> {
+    let text = || node.text().unwrap_or_default().trim().to_owned();
+
+    let tag = node.tag_name().name();
+    match tag {
+        "ci" => {
+            let name = text();
+            Some(Expression::Name(
+                renames.get(&name).cloned().unwrap_or(name),
+            ))
+        }
+        "cn" => {
+            let parts: Vec<f64> = node
+                .children()
+                .filter(Node::is_text)
+                .filter_map(|part| part.text()?.trim().parse().ok())
+                .collect();
+            match (node.attribute("type"), parts.as_slice()) {
+                (Some("e-notation"), &[mantissa, exponent]) => {
+                    Some(Expression::Number(mantissa * 10f64.powf(exponent)))
+                }
+                (Some("rational"), &[numerator, denominator]) => {
+                    Some(Expression::Number(numerator / denominator))
+                }
+                (_, &[value]) => Some(Expression::Number(value)),
+                _ => fail(unsupported, tag),
+            }
+        }
+        "true" => Some(Expression::Number(1.0)),
+        "false" => Some(Expression::Number(0.0)),
+        "pi" => Some(Expression::Number(std::f64::consts::PI)),
+        "exponentiale" => Some(Expression::Number(std::f64::consts::E)),
+        "piecewise" => {
+            let otherwise = match node.children().find(|n| n.has_tag_name("otherwise")) {
+                Some(otherwise) => mathml(otherwise.first_element_child()?, renames, unsupported)?,
+                None => Expression::Number(0.0),
+            };
+            let pieces = node
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "piece")
+                .map(|piece| {
+                    let mut parts = piece.children().filter(Node::is_element);
+                    let value = mathml(parts.next()?, renames, unsupported)?;
+                    let condition = mathml(parts.next()?, renames, unsupported)?;
+                    Some((value, condition))
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            // Conditions are 1 or 0, so each piece is
+            // `value * condition + rest * (condition == 0)`
+            Some(
+                pieces
+                    .into_iter()
+                    .rev()
+                    .fold(otherwise, |rest, (value, condition)| {
+                        let chosen = match value {
+                            Expression::Number(1.0) => condition.clone(),
+                            value => Expression::Infixed("*", vec![value, condition.clone()]),
+                        };
+                        match rest {
+                            Expression::Number(0.0) => chosen,
+                            rest => {
+                                let unmet = Expression::Infixed(
+                                    "==",
+                                    vec![condition, Expression::Number(0.0)],
+                                );
+                                Expression::Infixed(
+                                    "+",
+                                    vec![chosen, Expression::Infixed("*", vec![rest, unmet])],
+                                )
+                            }
+                        }
+                    }),
+            )
+        }
+        "apply" => {
+            let mut children = node.children().filter(Node::is_element);
+            let operator = children.next()?;
+            let qualifier = |name| {
+                node.children()
+                    .find(|n| n.has_tag_name(name))
+                    .and_then(|n| n.first_element_child())
+            };
+            let operands = children
+                .filter(|n| !matches!(n.tag_name().name(), "degree" | "logbase" | "bvar"))
+                .map(|operand| mathml(operand, renames, unsupported))
+                .collect::<Option<Vec<_>>>()?;
+
+            let infixed = |operation| Some(Expression::Infixed(operation, operands.clone()));
+            let prefixed =
+                |function: &str| Some(Expression::Prefixed(function.into(), operands.clone()));
+            let operator_tag = operator.tag_name().name();
+            match (operator_tag, operands.len()) {
+                ("plus", 0) => Some(Expression::Number(0.0)),
+                ("plus" | "times" | "and", 1) => operands.into_iter().next(),
+                ("plus", _) => infixed("+"),
+                ("minus", 1) => Some(operands.into_iter().next()?.negated()),
+                ("minus", _) => infixed("-"),
+                ("times", _) => infixed("*"),
+                ("divide", _) => infixed("/"),
+                ("power", _) => infixed("^"),
+                ("rem", _) => infixed("%"),
+                ("eq", _) => infixed("=="),
+                ("neq", _) => infixed("!="),
+                ("lt", _) => infixed("<"),
+                ("leq", _) => infixed("<="),
+                ("gt", _) => infixed(">"),
+                ("geq", _) => infixed(">="),
+                // Conditions are 1 or 0
+                ("and", _) => infixed("*"),
+                ("or", _) => prefixed("max"),
+                ("not", 1) => Some(Expression::Infixed(
+                    "==",
+                    vec![operands.into_iter().next()?, Expression::Number(0.0)],
+                )),
+                ("min" | "max" | "abs" | "exp" | "sin" | "cos" | "tan" | "floor", _) => {
+                    prefixed(operator_tag)
+                }
+                ("ceiling", _) => prefixed("ceil"),
+                ("ln", _) => prefixed("log"),
+                ("log", 1) => {
+                    let base = match qualifier("logbase") {
+                        Some(base) => mathml(base, renames, unsupported)?,
+                        None => Expression::Number(10.0),
+                    };
+                    Some(Expression::Infixed(
+                        "/",
+                        vec![
+                            Expression::Prefixed("log".into(), operands),
+                            Expression::Prefixed("log".into(), vec![base]),
+                        ],
+                    ))
+                }
+                ("root", 1) => match qualifier("degree") {
+                    Some(degree) => Some(Expression::Infixed(
+                        "^",
+                        vec![
+                            operands.into_iter().next()?,
+                            Expression::Infixed(
+                                "/",
+                                vec![
+                                    Expression::Number(1.0),
+                                    mathml(degree, renames, unsupported)?,
+                                ],
+                            ),
+                        ],
+                    )),
+                    None => prefixed("sqrt"),
+                },
+                // Calls to function definitions
+                ("ci", _) => fail(
+                    unsupported,
+                    &format!("function `{}`", operator.text()?.trim()),
+                ),
+                _ => fail(unsupported, operator_tag),
+            }
+        }
+        _ => fail(unsupported, tag),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::test_models::derivative;
+    use crate::models::Equation;
+    use crate::transformations::sbml::render_sbml;
+
+    /// A SIR model written by hand, the way most tools would.
+    const SIR: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<sbml xmlns="http://www.sbml.org/sbml/level3/version1/core" level="3" version="1">
+  <model id="sir" name="SIR">
+    <listOfCompartments>
+      <compartment id="cell" size="1" constant="true"/>
+    </listOfCompartments>
+    <listOfSpecies>
+      <species id="S" compartment="cell" initialConcentration="990" hasOnlySubstanceUnits="false" boundaryCondition="false" constant="false"/>
+      <species id="I" compartment="cell" initialAmount="10" hasOnlySubstanceUnits="true" boundaryCondition="false" constant="false"/>
+      <species id="R" compartment="cell" hasOnlySubstanceUnits="true" boundaryCondition="false" constant="false"/>
+    </listOfSpecies>
+    <listOfParameters>
+      <parameter id="beta" value="0.0003" constant="true"/>
+    </listOfParameters>
+    <listOfReactions>
+      <reaction id="infection" reversible="false">
+        <listOfReactants>
+          <speciesReference species="S" stoichiometry="1" constant="true"/>
+          <speciesReference species="I" stoichiometry="1" constant="true"/>
+        </listOfReactants>
+        <listOfProducts>
+          <speciesReference species="I" stoichiometry="2" constant="true"/>
+        </listOfProducts>
+        <kineticLaw>
+          <math xmlns="http://www.w3.org/1998/Math/MathML">
+            <apply> <times/> <ci> beta </ci> <ci> S </ci> <ci> I </ci> </apply>
+          </math>
+        </kineticLaw>
+      </reaction>
+      <reaction id="recovery" reversible="false">
+        <listOfReactants>
+          <speciesReference species="I" stoichiometry="1" constant="true"/>
+        </listOfReactants>
+        <listOfProducts>
+          <speciesReference species="R" stoichiometry="1" constant="true"/>
+        </listOfProducts>
+        <kineticLaw>
+          <math xmlns="http://www.w3.org/1998/Math/MathML">
+            <apply> <times/> <ci> gamma </ci> <ci> I </ci> </apply>
+          </math>
+          <listOfLocalParameters>
+            <localParameter id="gamma" value="0.1"/>
+          </listOfLocalParameters>
+        </kineticLaw>
+      </reaction>
+      <reaction id="dying" reversible="false">
+        <listOfReactants>
+          <speciesReference species="R" stoichiometry="2" constant="true"/>
+        </listOfReactants>
+        <kineticLaw>
+          <math xmlns="http://www.w3.org/1998/Math/MathML">
+            <apply> <minus/> <apply> <csymbol definitionURL="http://www.sbml.org/sbml/symbols/time"> t </csymbol> </apply> </apply>
+          </math>
+        </kineticLaw>
+      </reaction>
+    </listOfReactions>
+    <listOfEvents>
+      <event id="lockdown" useValuesFromTriggerTime="true"/>
+    </listOfEvents>
+  </model>
+</sbml>
+"#;
+
+    #[test]
+    fn reactions() {
+        let (model, warnings) = import_sbml(SIR).unwrap();
+
+        assert_eq!(model.name, "SIR");
+        let values: Vec<(&str, f64)> = model
+            .get_arguments_where(|arg| matches!(arg, Argument::Value { .. }))
+            .filter_map(|arg| match arg {
+                Argument::Value { name, value } => Some((name.as_str(), *value)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            values,
+            [
+                ("I", 10.0),
+                ("R", 0.0),
+                ("S", 990.0),
+                ("beta", 0.0003),
+                ("cell", 1.0),
+                ("recovery_gamma", 0.1)
+            ]
+        );
+
+        // I loses one and gains two in each infection
+        let state = [("S", 100.0), ("I", 10.0), ("R", 5.0)];
+        let infection = 0.0003 * 100.0 * 10.0;
+        let recovery = 0.1 * 10.0;
+        assert_eq!(derivative(&model, "S", &state), -infection);
+        assert_eq!(derivative(&model, "I", &state), infection - recovery);
+        assert_eq!(derivative(&model, "R", &state), recovery);
+
+        assert_eq!(
+            warnings,
+            [
+                SbmlWarning::MissingValue { id: "R".into() },
+                SbmlWarning::UnsupportedMath {
+                    element: "csymbol".into(),
+                    context: "reaction `dying`".into()
+                },
+                SbmlWarning::Unsupported {
+                    element: "event".into(),
+                    id: Some("lockdown".into())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn concentrations_are_per_volume() {
+        let xml = SIR.replace(r#"size="1""#, r#"size="2""#).replace(
+            r#"initialAmount="10" hasOnlySubstanceUnits="true""#,
+            r#"initialAmount="10" hasOnlySubstanceUnits="false""#,
+        );
+        let (model, _) = import_sbml(&xml).unwrap();
+
+        // I is given as an amount, but measured in concentrations
+        assert_eq!(
+            model.arguments["I"],
+            Argument::Value {
+                name: "I".into(),
+                value: 5.0
+            }
+        );
+
+        // Only R, the one species in amounts, gets the whole of each reaction
+        let state = [("S", 100.0), ("I", 10.0), ("R", 5.0)];
+        let infection = 0.0003 * 100.0 * 10.0;
+        let recovery = 0.1 * 10.0;
+        assert_eq!(derivative(&model, "S", &state), -infection / 2.0);
+        assert_eq!(
+            derivative(&model, "I", &state),
+            (infection - recovery) / 2.0
+        );
+        assert_eq!(derivative(&model, "R", &state), recovery);
+    }
+
+    #[test]
+    fn rules_and_piecewise() {
+        let xml = r#"<sbml xmlns="http://www.sbml.org/sbml/level3/version2/core" level="3" version="2">
+  <model id="switch">
+    <listOfParameters>
+      <parameter id="x" value="2" constant="false"/>
+      <parameter id="threshold" value="1" constant="true"/>
+      <parameter id="y" constant="false"/>
+    </listOfParameters>
+    <listOfRules>
+      <assignmentRule variable="y">
+        <math xmlns="http://www.w3.org/1998/Math/MathML">
+          <piecewise>
+            <piece> <cn> 3 </cn> <apply> <gt/> <ci> x </ci> <ci> threshold </ci> </apply> </piece>
+            <otherwise> <cn type="e-notation"> 5 <sep/> -1 </cn> </otherwise>
+          </piecewise>
+        </math>
+      </assignmentRule>
+      <rateRule variable="x">
+        <math xmlns="http://www.w3.org/1998/Math/MathML">
+          <apply> <plus/>
+            <apply> <minus/> <ci> y </ci> </apply>
+            <apply> <log/> <logbase> <cn> 2 </cn> </logbase> <ci> x </ci> </apply>
+          </apply>
+        </math>
+      </rateRule>
+    </listOfRules>
+  </model>
+</sbml>"#;
+
+        let (model, warnings) = import_sbml(xml).unwrap();
+
+        assert_eq!(warnings, [SbmlWarning::MissingValue { id: "y".into() }]);
+        assert_eq!(model.equations.len(), 1);
+        assert_eq!(derivative(&model, "x", &[("x", 2.0)]), -3.0 + 1.0);
+        assert_eq!(derivative(&model, "x", &[("x", 0.5)]), -0.5 - 1.0);
+    }
+
+    #[test]
+    fn round_trip() {
+        let mut original = OdeModel::new("Predator & prey".into(), Metadata::default());
+        for (name, value) in [
+            ("prey", 40.0),
+            ("predators", 9.0),
+            ("alpha", 0.1),
+            ("beta", 0.02),
+            ("delta", 0.01),
+            ("gamma", 0.1),
+            ("food", 1.0),
+            ("grass", 5.0),
+            ("hay", 0.0),
+        ] {
+            original.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+        }
+        for (name, operation, composition) in [
+            (
+                "hunting",
+                "*",
+                [("beta", '+'), ("prey", '+'), ("predators", '+')].as_slice(),
+            ),
+            ("births", "*", &[("alpha", '+'), ("prey", '+')]),
+            (
+                "feeding",
+                "*",
+                &[("delta", '+'), ("prey", '+'), ("predators", '+')],
+            ),
+            ("deaths", "*", &[("gamma", '+'), ("predators", '+')]),
+            ("crowding", "%", &[("prey", '+'), ("predators", '+')]),
+            (
+                "starving",
+                "<",
+                &[("prey", '+'), ("food", '+'), ("predators", '+')],
+            ),
+            ("drying", "*", &[("alpha", '+'), ("grass", '+')]),
+        ] {
+            original.insert_argument(Argument::Composite {
+                name: name.into(),
+                operation: operation.into(),
+                style: Default::default(),
+                composition: composition
+                    .iter()
+                    .map(|&(name, contribution)| crate::Component {
+                        name: name.into(),
+                        contribution,
+                    })
+                    .collect(),
+            });
+        }
+        for (population, argument, contribution) in [
+            ("prey", "births", '+'),
+            ("prey", "hunting", '-'),
+            ("prey", "crowding", '+'),
+            ("predators", "feeding", '+'),
+            ("predators", "deaths", '-'),
+            ("predators", "starving", '-'),
+            ("grass", "drying", '-'),
+            ("hay", "drying", '+'),
+        ] {
+            original.insert_equation(Equation {
+                name: argument.into(),
+                operates_on: Some(population.into()),
+                argument: argument.into(),
+                contribution,
+            });
+        }
+
+        let sbml = render_sbml(&original).unwrap();
+        let (imported, warnings) = import_sbml(&sbml).unwrap();
+
+        assert_eq!(warnings, []);
+        assert_eq!(imported.name, original.name);
+        // Only drying converts one population into another
+        assert_eq!(sbml.matches("<reaction ").count(), 1);
+        for state in [
+            [("prey", 40.0), ("predators", 9.0), ("grass", 5.0)],
+            [("prey", 0.5), ("predators", 3.0), ("grass", 2.0)],
+        ] {
+            for population in ["prey", "predators", "grass", "hay"] {
+                let expected = derivative(&original, population, &state);
+                let actual = derivative(&imported, population, &state);
+                assert!(
+                    (expected - actual).abs() < 1e-12,
+                    "{population}: {actual} != {expected}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn not_sbml() {
+        assert!(matches!(import_sbml("<html/>"), Err(SbmlError::NotSbml)));
+        assert!(matches!(import_sbml("<sbml/>"), Err(SbmlError::NoModel)));
+        assert!(matches!(import_sbml("<sbml>"), Err(SbmlError::Xml(_))));
+    }
+}
@@ -0,0 +1,32 @@
+//! Checks shared by the tests of the importers, which compare what the
+//! imported equations compute rather than how they're written.
+
+use crate::models::ode::OdeModel;
+
+/// Looks names up in `state`, leaving the rest to the model.
+pub fn bindings<'a>(state: &'a [(&str, f64)]) -> impl Fn(&str) -> Option<f64> + 'a {
+    |name| {
+        state
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|&(_, value)| value)
+    }
+}
+
+/// The rate of change of `population` in `state`: the sum of the
+/// arguments of its equations, each negated if its contribution is `-`.
+pub fn derivative(model: &OdeModel, population: &str, state: &[(&str, f64)]) -> f64 {
+    model
+        .equations
+        .iter()
+        .filter(|eq| eq.operates_on.as_deref() == Some(population))
+        .map(|eq| {
+            let value = model.evaluate(&eq.argument, &bindings(state)).unwrap();
+            if eq.contribution == '-' {
+                -value
+            } else {
+                value
+            }
+        })
+        .sum()
+}
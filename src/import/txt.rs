@@ -0,0 +1,262 @@
+//! Reads the plain text written by
+//! [`render_txt_with_equations`](crate::transformations::ode::render_txt_with_equations),
+//! so that models can be written and diffed by hand.
+//!
+//! ```text
+//! @ name = Lotka-Volterra
+//! @ end_time = 50
+//!
+//! alpha = 1.1
+//! x = 10
+//! hunting = beta*x*y
+//! dx_dt = alpha*x - hunting
+//! ```
+//!
+//! Lines starting with `@` set the metadata, and `#` starts a comment.
+//! Assigning a number declares a value, which is a population if there's
+//! a `dX_dt` line for it, and assigning an expression declares a composite.
+
+use std::fmt;
+
+use super::{insert_rate, Expression, ExpressionError};
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::Argument;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TxtError {
+    /// Starting from 1.
+    pub line: usize,
+    pub kind: TxtErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TxtErrorKind {
+    MissingEquals,
+    InvalidName(String),
+    InvalidNumber(String),
+    UnknownOption(String),
+    DuplicateName(String),
+    Expression(ExpressionError),
+}
+
+impl fmt::Display for TxtError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            TxtErrorKind::MissingEquals => write!(f, "expected `name = ...`"),
+            TxtErrorKind::InvalidName(name) => write!(f, "`{name}` is not a valid name"),
+            TxtErrorKind::InvalidNumber(number) => write!(f, "`{number}` is not a number"),
+            TxtErrorKind::UnknownOption(option) => write!(f, "unknown option `{option}`"),
+            TxtErrorKind::DuplicateName(name) => write!(f, "`{name}` is already defined"),
+            TxtErrorKind::Expression(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for TxtError {}
+
+/// Reads a model written as text, with default metadata unless it sets
+/// its own.
+pub fn import_txt(text: &str) -> Result<OdeModel, TxtError> {
+    let mut model = OdeModel::new(String::new(), Metadata::import_defaults());
+    let mut assignments: Vec<(&str, Expression)> = Vec::new();
+
+    for (line, content) in text.lines().enumerate() {
+        let line = line + 1;
+        let error = |kind| TxtError { line, kind };
+        let content = content.split('#').next().unwrap_or_default().trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let (option, content) = match content.strip_prefix('@') {
+            Some(content) => (true, content),
+            None => (false, content),
+        };
+        let (lhs, rhs) = content
+            .split_once('=')
+            .ok_or(error(TxtErrorKind::MissingEquals))?;
+        let (lhs, rhs) = (lhs.trim(), rhs.trim());
+
+        if option {
+            let number = || {
+                rhs.parse()
+                    .map_err(|_| error(TxtErrorKind::InvalidNumber(rhs.to_owned())))
+            };
+            match lhs {
+                "name" => model.name = rhs.to_owned(),
+                "start_time" => model.metadata.start_time = number()?,
+                "delta_time" => model.metadata.delta_time = number()?,
+                "end_time" => model.metadata.end_time = number()?,
+                _ => return Err(error(TxtErrorKind::UnknownOption(lhs.to_owned()))),
+            }
+            continue;
+        }
+
+        if !is_identifier(lhs) {
+            return Err(error(TxtErrorKind::InvalidName(lhs.to_owned())));
+        }
+        if assignments.iter().any(|(name, _)| *name == lhs) {
+            return Err(error(TxtErrorKind::DuplicateName(lhs.to_owned())));
+        }
+        let expression =
+            Expression::parse(rhs).map_err(|err| error(TxtErrorKind::Expression(err)))?;
+        assignments.push((lhs, expression));
+    }
+
+    // Derivatives are told apart by their names first, as their rates can
+    // be numbers too, and composites which happen to be called like one
+    // have no value to be the derivative of
+    let values: Vec<&str> = assignments
+        .iter()
+        .filter(|(_, expression)| expression.number().is_some())
+        .map(|&(name, _)| name)
+        .collect();
+    let mut equations = Vec::new();
+    let mut rest = Vec::new();
+    for (name, expression) in assignments {
+        let population = name
+            .strip_prefix('d')
+            .and_then(|name| name.strip_suffix("_dt"))
+            .filter(|population| values.contains(population));
+        match (population, expression.number()) {
+            (Some(population), _) => equations.push((population, expression)),
+            (None, Some(value)) => model.insert_argument(Argument::Value {
+                name: name.to_owned(),
+                value,
+            }),
+            (None, None) => rest.push((name, expression)),
+        }
+    }
+
+    for (name, expression) in rest {
+        let expression = match expression {
+            expression @ (Expression::Infixed(..) | Expression::Prefixed(..)) => expression,
+            expression => Expression::Infixed("+", vec![expression]),
+        };
+        expression.insert_into(&mut model, Some(name));
+    }
+
+    for (population, expression) in equations {
+        insert_rate(&mut model, population, vec![expression]);
+    }
+
+    Ok(model)
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::test_models::derivative;
+    use crate::json::Model;
+    use crate::transformations::ode::render_txt_with_equations;
+
+    const LOTKA_VOLTERRA: &str = "@ name = Lotka-Volterra
+@ start_time = 0
+@ delta_time = 0.01
+@ end_time = 50
+
+alpha = 1.1
+beta = 0.4
+delta = 0.1
+gamma = 0.4
+x = 10
+y = 10
+
+hunting = beta*x*y
+
+dx_dt = alpha*x - hunting
+dy_dt = delta*x*y - gamma*y
+";
+
+    #[test]
+    fn lotka_volterra() {
+        let model = import_txt(LOTKA_VOLTERRA).unwrap();
+
+        assert_eq!(model.name, "Lotka-Volterra");
+        assert_eq!(model.metadata.delta_time, 0.01);
+        assert_eq!(model.metadata.end_time, 50.0);
+        let populations: Vec<&str> = model.get_populations().map(Argument::name).collect();
+        assert_eq!(populations, ["x", "y"]);
+        assert_eq!(model.equations.len(), 2);
+
+        let state = [("x", 2.0), ("y", 3.0)];
+        assert!((derivative(&model, "x", &state) - (1.1 * 2.0 - 0.4 * 6.0)).abs() < 1e-12);
+        assert!((derivative(&model, "y", &state) - (0.1 * 6.0 - 0.4 * 3.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn round_trip_through_json() {
+        let model = import_txt(LOTKA_VOLTERRA).unwrap();
+        let json = serde_json::to_string(&Model::ODE(model)).unwrap();
+        let Model::ODE(model) = serde_json::from_str(&json).unwrap() else {
+            panic!("expected an ODE model");
+        };
+
+        assert_eq!(
+            render_txt_with_equations(&model, &[]).unwrap(),
+            LOTKA_VOLTERRA
+        );
+    }
+
+    #[test]
+    fn numbers_and_negations() {
+        let text = "k = -0.5\nx = 1\ndx_dt = -2*x + k*(x - 1)^2\n";
+
+        let model = import_txt(text).unwrap();
+        let rendered = render_txt_with_equations(&model, &[]).unwrap();
+
+        assert!(rendered.ends_with("\ndx_dt = -2*x + k*(x - 1)^2\n"));
+        assert_eq!(import_txt(&rendered).unwrap().equations.len(), 1);
+        let state = [("x", 3.0)];
+        assert_eq!(derivative(&model, "x", &state), -6.0 - 0.5 * 4.0);
+    }
+
+    #[test]
+    fn constant_rates() {
+        let text = "p = 1\nx = 0\ndp_dt = 0\ndx_dt = 2\n";
+
+        let model = import_txt(text).unwrap();
+
+        let populations: Vec<&str> = model.get_populations().map(Argument::name).collect();
+        assert_eq!(populations, ["p", "x"]);
+        assert!(!model.arguments.contains_key("dp_dt"));
+        let state = [("p", 1.0), ("x", 0.0)];
+        assert_eq!(derivative(&model, "p", &state), 0.0);
+        assert_eq!(derivative(&model, "x", &state), 2.0);
+    }
+
+    #[test]
+    fn errors() {
+        let error = |text| import_txt(text).unwrap_err();
+
+        assert_eq!(
+            error("x = 1\n\ny 2"),
+            TxtError {
+                line: 3,
+                kind: TxtErrorKind::MissingEquals
+            }
+        );
+        assert_eq!(
+            error("x = 1\nx = 2").kind,
+            TxtErrorKind::DuplicateName("x".into())
+        );
+        assert_eq!(
+            error("@ steps = 10").kind,
+            TxtErrorKind::UnknownOption("steps".into())
+        );
+        assert_eq!(
+            error("x = (1").kind,
+            TxtErrorKind::Expression(ExpressionError::UnexpectedEnd)
+        );
+        assert_eq!(
+            error("x = 1 # first\nx+y = 2").to_string(),
+            "line 2: `x+y` is not a valid name"
+        );
+    }
+}
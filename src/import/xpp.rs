@@ -0,0 +1,371 @@
+//! XPPAUT `.ode` import, for the many classic models distributed that way.
+//!
+//! Differential equations (`dx/dt=...` or `x'=...`), `par`, `number`,
+//! `init` and `x(0)=...` declarations, fixed quantities and the `t0`,
+//! `total` and `dt` options are read. Anything else, like `aux` or
+//! `table`, is skipped with a warning.
+
+use std::fmt;
+
+use super::{insert_rate, Expression, ExpressionError};
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::Argument;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct XppError {
+    /// Starting from 1.
+    pub line: usize,
+    pub kind: XppErrorKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum XppErrorKind {
+    /// A declaration which isn't `name=value`.
+    InvalidDeclaration(String),
+    InvalidNumber(String),
+}
+
+impl fmt::Display for XppError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            XppErrorKind::InvalidDeclaration(declaration) => {
+                write!(f, "expected `name=value`, found `{declaration}`")
+            }
+            XppErrorKind::InvalidNumber(number) => write!(f, "`{number}` is not a number"),
+        }
+    }
+}
+
+impl std::error::Error for XppError {}
+
+/// Something the import left out or had to guess.
+#[derive(Debug, Clone, PartialEq)]
+pub enum XppWarning {
+    /// A statement odeir has no equivalent for, like `aux` or a function
+    /// definition.
+    Unsupported { line: usize, statement: String },
+    /// An expression that couldn't be read, like `if(x>0)then(1)else(0)`,
+    /// which drops its statement.
+    Expression { line: usize, error: ExpressionError },
+    /// A variable without an initial value, which starts at 0.
+    MissingInitialValue { name: String },
+}
+
+impl fmt::Display for XppWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unsupported { line, statement } => {
+                write!(f, "line {line}: `{statement}` is not supported")
+            }
+            Self::Expression { line, error } => write!(f, "line {line}: {error}"),
+            Self::MissingInitialValue { name } => {
+                write!(f, "`{name}` has no initial value, using 0")
+            }
+        }
+    }
+}
+
+/// Reads an XPPAUT `.ode` file. The model is named after the first line,
+/// if it's a comment.
+pub fn import_xpp(text: &str) -> Result<(OdeModel, Vec<XppWarning>), XppError> {
+    let name = text
+        .lines()
+        .next()
+        .and_then(|line| line.strip_prefix('#'))
+        .unwrap_or_default()
+        .trim();
+    let mut model = OdeModel::new(name.to_owned(), Metadata::import_defaults());
+    let mut warnings = Vec::new();
+
+    let mut values: Vec<(&str, f64)> = Vec::new();
+    let mut derivatives: Vec<(&str, Expression)> = Vec::new();
+    let mut quantities: Vec<(&str, Expression)> = Vec::new();
+    let mut options: Vec<(String, f64)> = Vec::new();
+
+    for (line, content) in text.lines().enumerate() {
+        let line = line + 1;
+        let error = |kind| XppError { line, kind };
+        let content = content.split('#').next().unwrap_or_default().trim();
+        if content.is_empty() {
+            continue;
+        }
+
+        let (head, rest) = content
+            .split_once(char::is_whitespace)
+            .map(|(head, rest)| (head, rest.trim()))
+            .unwrap_or((content, ""));
+        // `a = 1` is a fixed quantity called `a`, not an `aux`
+        let keyword = if rest.starts_with('=') || head.contains('=') {
+            None
+        } else {
+            Some(head.to_lowercase())
+        };
+
+        match keyword.as_deref() {
+            Some("done") => break,
+            Some("p" | "par" | "param" | "number" | "num" | "i" | "init") => {
+                for (name, value) in declarations(rest).map_err(error)? {
+                    values.push((
+                        name,
+                        value
+                            .parse()
+                            .map_err(|_| error(XppErrorKind::InvalidNumber(value.to_owned())))?,
+                    ));
+                }
+            }
+            Some("@" | "option" | "opt" | "o") => {
+                for (name, value) in declarations(rest).map_err(error)? {
+                    let name = name.to_lowercase();
+                    if matches!(name.as_str(), "t0" | "total" | "dt") {
+                        let value = value
+                            .parse()
+                            .map_err(|_| error(XppErrorKind::InvalidNumber(value.to_owned())))?;
+                        options.push((name, value));
+                    }
+                }
+            }
+            Some(_) => warnings.push(XppWarning::Unsupported {
+                line,
+                statement: head.to_owned(),
+            }),
+            None => {
+                let Some((lhs, rhs)) = content.split_once('=') else {
+                    return Err(error(XppErrorKind::InvalidDeclaration(content.to_owned())));
+                };
+                let lhs = lhs.trim();
+                let expression = match Expression::parse(rhs) {
+                    Ok(expression) => expression,
+                    Err(err) => {
+                        warnings.push(XppWarning::Expression { line, error: err });
+                        continue;
+                    }
+                };
+
+                if let Some(variable) = lhs
+                    .strip_prefix(['d', 'D'])
+                    .and_then(|lhs| lhs.strip_suffix("/dt"))
+                    .or(lhs.strip_suffix('\''))
+                {
+                    derivatives.push((variable.trim(), expression));
+                } else if let Some(variable) = lhs.strip_suffix("(0)") {
+                    match expression.number() {
+                        Some(value) => values.push((variable.trim(), value)),
+                        None => warnings.push(XppWarning::Unsupported {
+                            line,
+                            statement: lhs.to_owned(),
+                        }),
+                    }
+                } else if lhs.contains('(') {
+                    // Function definitions, maps and Volterra equations
+                    warnings.push(XppWarning::Unsupported {
+                        line,
+                        statement: lhs.to_owned(),
+                    });
+                } else {
+                    // Derived parameters are fixed quantities computed once
+                    let lhs = lhs.trim_start_matches('!').trim();
+                    match expression.number() {
+                        Some(value) => values.push((lhs, value)),
+                        None => quantities.push((lhs, expression)),
+                    }
+                }
+            }
+        }
+    }
+
+    for (name, value) in values {
+        model.insert_argument(Argument::Value {
+            name: name.to_owned(),
+            value,
+        });
+    }
+    for (name, expression) in quantities {
+        let expression = match expression {
+            expression @ (Expression::Infixed(..) | Expression::Prefixed(..)) => expression,
+            expression => Expression::Infixed("+", vec![expression]),
+        };
+        expression.insert_into(&mut model, Some(name));
+    }
+    for (variable, expression) in derivatives {
+        if !model.arguments.contains_key(variable) {
+            warnings.push(XppWarning::MissingInitialValue {
+                name: variable.to_owned(),
+            });
+            model.insert_argument(Argument::Value {
+                name: variable.to_owned(),
+                value: 0.0,
+            });
+        }
+        insert_rate(&mut model, variable, vec![expression]);
+    }
+
+    let option = |name: &str| {
+        options
+            .iter()
+            .rev()
+            .find(|(option, _)| option == name)
+            .map(|&(_, value)| value)
+    };
+    let metadata = &mut model.metadata;
+    metadata.start_time = option("t0").unwrap_or(metadata.start_time);
+    metadata.delta_time = option("dt").unwrap_or(metadata.delta_time);
+    metadata.end_time = metadata.start_time + option("total").unwrap_or(metadata.end_time);
+
+    Ok((model, warnings))
+}
+
+/// The `name=value` pairs of a declaration, separated by commas or spaces.
+fn declarations(text: &str) -> Result<Vec<(&str, &str)>, XppErrorKind> {
+    let mut declarations = Vec::new();
+    let mut rest = text.trim();
+
+    while !rest.is_empty() {
+        let (name, after) = rest
+            .split_once('=')
+            .ok_or_else(|| XppErrorKind::InvalidDeclaration(rest.to_owned()))?;
+        let after = after.trim_start();
+        let end = after
+            .find(|c: char| c == ',' || c.is_whitespace())
+            .unwrap_or(after.len());
+        let (name, value) = (name.trim(), &after[..end]);
+        if name.is_empty() || value.is_empty() || name.contains([',', ' ']) {
+            return Err(XppErrorKind::InvalidDeclaration(rest.to_owned()));
+        }
+
+        declarations.push((name, value));
+        rest = after[end..].trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+    }
+
+    Ok(declarations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::import::test_models::derivative;
+    use crate::transformations::xpp::render_xpp;
+
+    fn values(model: &OdeModel) -> Vec<(&str, f64)> {
+        model
+            .arguments
+            .values()
+            .filter_map(|arg| match arg {
+                Argument::Value { name, value } => Some((name.as_str(), *value)),
+                _ => None,
+            })
+            .filter(|(name, _)| !model.is_number(name))
+            .collect()
+    }
+
+    #[test]
+    fn fitzhugh_nagumo() {
+        let (model, warnings) = import_xpp(include_str!("fixtures/fitzhugh_nagumo.ode")).unwrap();
+
+        assert_eq!(warnings, []);
+        assert_eq!(model.name, "FitzHugh-Nagumo model of a spiking neuron");
+        assert_eq!(
+            values(&model),
+            [
+                ("I", 0.5),
+                ("a", 0.7),
+                ("b", 0.8),
+                ("eps", 0.08),
+                ("v", -1.0),
+                ("w", 1.0)
+            ]
+        );
+        assert_eq!(model.metadata.delta_time, 0.05);
+        assert_eq!(model.metadata.end_time, 200.0);
+
+        let state = [("v", 2.0), ("w", 0.5)];
+        let dv = 2.0 - 8.0 / 3.0 - 0.5 + 0.5;
+        let dw = 0.08 * (2.0 + 0.7 - 0.8 * 0.5);
+        assert!((derivative(&model, "v", &state) - dv).abs() < 1e-12);
+        assert!((derivative(&model, "w", &state) - dw).abs() < 1e-12);
+    }
+
+    #[test]
+    fn lotka_volterra() {
+        let (model, warnings) = import_xpp(include_str!("fixtures/lotka_volterra.ode")).unwrap();
+
+        assert_eq!(
+            warnings,
+            [XppWarning::Unsupported {
+                line: 8,
+                statement: "aux".into()
+            }]
+        );
+        assert_eq!(
+            values(&model),
+            [
+                ("a", 1.0),
+                ("b", 0.1),
+                ("c", 1.5),
+                ("d", 0.075),
+                ("x", 10.0),
+                ("y", 5.0)
+            ]
+        );
+        assert_eq!(model.metadata.delta_time, 0.01);
+        assert_eq!(model.metadata.end_time, 50.0);
+
+        let state = [("x", 4.0), ("y", 2.0)];
+        assert!((derivative(&model, "x", &state) - (4.0 - 0.1 * 8.0)).abs() < 1e-12);
+        assert!((derivative(&model, "y", &state) - (-3.0 + 0.075 * 8.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn round_trip() {
+        let (model, _) = import_xpp(include_str!("fixtures/fitzhugh_nagumo.ode")).unwrap();
+        let xpp = render_xpp(&model).unwrap();
+        let (imported, warnings) = import_xpp(&xpp).unwrap();
+
+        assert_eq!(warnings, []);
+        assert_eq!(imported.name, model.name);
+        assert_eq!(values(&imported), values(&model));
+        assert_eq!(imported.metadata.end_time, model.metadata.end_time);
+        for state in [[("v", 2.0), ("w", 0.5)], [("v", -1.5), ("w", 3.0)]] {
+            for variable in ["v", "w"] {
+                let expected = derivative(&model, variable, &state);
+                let actual = derivative(&imported, variable, &state);
+                assert!((expected - actual).abs() < 1e-12);
+            }
+        }
+        // Written back the same way
+        assert_eq!(render_xpp(&imported).unwrap(), xpp);
+    }
+
+    #[test]
+    fn unsupported_statements() {
+        let text = "f(x)=x^2
+dx/dt=if(x>1)then(0)else(1)
+dy/dt=f(y)
+table w % 3 0 2 0 1 4
+par k=a
+";
+        let error = import_xpp(text).unwrap_err();
+        assert_eq!(error.to_string(), "line 5: `a` is not a number");
+
+        let (model, warnings) = import_xpp(&text.replace("par k=a", "")).unwrap();
+        assert_eq!(
+            warnings,
+            [
+                XppWarning::Unsupported {
+                    line: 1,
+                    statement: "f(x)".into()
+                },
+                XppWarning::Expression {
+                    line: 2,
+                    error: ExpressionError::UnexpectedToken("then".into())
+                },
+                XppWarning::Unsupported {
+                    line: 4,
+                    statement: "table".into()
+                },
+                XppWarning::MissingInitialValue { name: "y".into() },
+            ]
+        );
+        assert_eq!(model.equations.len(), 1);
+    }
+}
@@ -1,28 +1,36 @@
-use serde::{Deserialize, Serialize};
+use schemars::JsonSchema;
+use serde::{Deserialize, Deserializer, Serialize};
 
 use crate::{
     models::{self, cellular_automata::CaModel, ode::OdeModel, Argument, CoreModel, Equation},
     Map,
 };
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+pub mod migration;
+
+/// A model file, as saved by the editor.
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[schemars(title = "odeir model")]
 pub struct Json {
     pub metadata: Metadata,
     pub arguments: Vec<Argument>,
     pub equations: Vec<Equation>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[serde(tag = "type")]
 pub enum ModelMetadata {
     #[serde(rename = "ode")]
     ODE(models::ode::Metadata),
     #[serde(rename = "cellular-automata")]
-    CellularAutomata {},
+    CellularAutomata(models::cellular_automata::Metadata),
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 pub struct Metadata {
+    /// The shape of the file, older ones being migrated when loaded.
+    #[serde(default)]
+    pub version: u64,
     #[serde(default)]
     pub name: String,
     #[serde(flatten)]
@@ -31,36 +39,53 @@ pub struct Metadata {
     pub positions: Map<String, Position>,
     #[serde(default)]
     pub extension_files: Vec<String>,
+    /// Constants standing for numbers written in an expression, which text
+    /// formats write as the numbers themselves.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub numbers: Vec<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Position {
     pub x: f64,
     pub y: f64,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-#[serde(from = "Json")]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 #[serde(into = "Json")]
 pub enum Model {
     ODE(OdeModel),
     CellularAutomata(CaModel),
 }
 
+/// Files from older versions are upgraded first, silently; use
+/// [`migration::from_str`] to know what changed.
+impl<'de> Deserialize<'de> for Model {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let json = serde_json::Value::deserialize(deserializer)?;
+        migration::from_value(json)
+            .map(|(model, _)| model)
+            .map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<Json> for Model {
     fn from(value: Json) -> Self {
-        let core = CoreModel {
-            arguments: value
-                .arguments
-                .into_iter()
-                .map(|arg| (arg.name().to_owned(), arg))
-                .collect(),
-            equations: value.equations,
-            positions: value.metadata.positions,
-        };
+        let mut core = CoreModel::new();
+        for argument in value.arguments {
+            core.insert_argument(argument);
+        }
+        core.equations = value.equations;
+        core.positions = value.metadata.positions;
+        core.numbers = value.metadata.numbers.into_iter().collect();
         let name = value.metadata.name;
         match value.metadata.model_metadata {
-            ModelMetadata::CellularAutomata {} => Self::CellularAutomata(CaModel { name, core }),
+            ModelMetadata::CellularAutomata(metadata) => Self::CellularAutomata(CaModel {
+                name,
+                core,
+                metadata,
+                extension_files: value.metadata.extension_files,
+            }),
             ModelMetadata::ODE(metadata) => Self::ODE(OdeModel {
                 name,
                 core,
@@ -76,9 +101,9 @@ impl From<Model> for Json {
         let (equations, model_metadata, name, extension_files) = match value {
             Model::CellularAutomata(model) => (
                 model.core,
-                ModelMetadata::CellularAutomata {},
+                ModelMetadata::CellularAutomata(model.metadata),
                 model.name,
-                Vec::new(),
+                model.extension_files,
             ),
             Model::ODE(model) => (
                 model.core,
@@ -88,27 +113,436 @@ impl From<Model> for Json {
             ),
         };
         Self {
-            arguments: equations.arguments.values().cloned().collect(),
+            arguments: equations.arguments_in_order().cloned().collect(),
             equations: equations.equations,
             metadata: Metadata {
+                version: migration::CURRENT_VERSION,
                 name,
                 model_metadata,
                 extension_files,
-                positions: Map::new(),
+                positions: equations.positions,
+                numbers: equations.numbers.into_iter().collect(),
             },
         }
     }
 }
 
+/// The JSON Schema of model files, which `schema/model.schema.json` is kept
+/// in sync with for tools that validate them without this crate.
+pub fn schema() -> serde_json::Value {
+    serde_json::to_value(schemars::schema_for!(Json)).unwrap()
+}
+
+/// YAML with the same fields as the JSON.
+#[cfg(feature = "yaml")]
+impl Model {
+    pub fn from_yaml(yaml: &str) -> Result<Self, serde_yaml::Error> {
+        serde_yaml::from_str(yaml)
+    }
+
+    pub fn to_yaml(&self) -> Result<String, serde_yaml::Error> {
+        serde_yaml::to_string(self)
+    }
+}
+
+/// TOML with the same fields as the JSON, the arguments and equations being
+/// arrays of tables.
+#[cfg(feature = "toml")]
+impl Model {
+    pub fn from_toml(toml: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(toml)
+    }
+
+    pub fn to_toml(&self) -> Result<String, toml::ser::Error> {
+        toml::to_string(self)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use assert_json_diff::assert_json_eq;
+    use proptest::prelude::*;
 
     use super::*;
 
-    use crate::models::Component;
+    use crate::models::cellular_automata::{InitialCondition, Lattice, PatternFormat};
+    use crate::models::{Component, CompositionStyle};
+
+    #[test]
+    fn cellular_automata_metadata_defaults() {
+        let json = r#"{
+            "metadata": { "type": "cellular-automata" },
+            "arguments": [],
+            "equations": []
+        }"#;
+
+        let Model::CellularAutomata(model) = serde_json::from_str::<Model>(json).unwrap() else {
+            panic!("expected a cellular automaton");
+        };
+
+        assert_eq!((model.metadata.width, model.metadata.height), (64, 64));
+        assert!(matches!(
+            model.metadata.initial_condition,
+            InitialCondition::Random { background: None, ref densities } if densities.is_empty()
+        ));
+    }
+
+    #[test]
+    fn cellular_automata_initial_condition_round_trip() {
+        let json = serde_json::json!({
+            "metadata": {
+                "version": 1,
+                "name": "Glider",
+                "type": "cellular-automata",
+                "lattice": "square",
+                "width": 16,
+                "height": 16,
+                "depth": 1,
+                "seed": 7,
+                "initial_condition": {
+                    "kind": "pattern",
+                    "background": "dead",
+                    "alive": "alive",
+                    "format": "rle",
+                    "source": "x = 3, y = 3\nbob$2bo$3o!",
+                    "x": 2,
+                    "y": 4
+                },
+                "positions": {},
+                "extension_files": []
+            },
+            "arguments": [],
+            "equations": []
+        });
+
+        let model = serde_json::from_value::<Model>(json.clone()).unwrap();
+        let Model::CellularAutomata(ca) = &model else {
+            panic!("expected a cellular automaton");
+        };
+
+        assert!(matches!(
+            ca.metadata.initial_condition,
+            InitialCondition::Pattern {
+                format: PatternFormat::Rle,
+                x: 2,
+                y: 4,
+                ..
+            }
+        ));
+        assert_json_eq!(serde_json::to_value(&model).unwrap(), json);
+    }
+
+    #[test]
+    fn elementary_rule_shorthand() {
+        let json = serde_json::json!({
+            "metadata": { "type": "cellular-automata", "rule": 90, "width": 9 },
+            "arguments": [],
+            "equations": []
+        });
+
+        let Model::CellularAutomata(model) = serde_json::from_value::<Model>(json.clone()).unwrap()
+        else {
+            panic!("expected a cellular automaton");
+        };
+
+        assert_eq!(model, CaModel::elementary(90, 9).unwrap());
+
+        // Saved models keep the rule, and load back the same
+        let saved = serde_json::to_value(Model::CellularAutomata(model.clone())).unwrap();
+        assert_eq!(saved["metadata"]["rule"], 90);
+        assert_eq!(
+            serde_json::from_value::<Model>(saved).unwrap(),
+            Model::CellularAutomata(model)
+        );
+
+        let mut empty = json;
+        empty["metadata"]["width"] = 0.into();
+        assert!(serde_json::from_value::<Model>(empty)
+            .unwrap_err()
+            .to_string()
+            .contains("no cells"));
+    }
+
+    #[test]
+    fn import_pattern_file() {
+        let initial_condition =
+            InitialCondition::from_pattern_file("fixtures/glider.rle", "dead", "alive").unwrap();
+
+        assert!(matches!(
+            initial_condition,
+            InitialCondition::Pattern {
+                format: PatternFormat::Rle,
+                ref source,
+                ..
+            } if source.contains("bob$2bo$3o!")
+        ));
+    }
+
+    #[cfg(all(feature = "yaml", feature = "toml"))]
+    #[test]
+    fn formats_round_trip() {
+        let json: serde_json::Value =
+            serde_json::from_str(include_str!("../fixtures/lotka-volterra-v1.json")).unwrap();
+        let model = serde_json::from_value::<Model>(json).unwrap();
+        let expected = serde_json::to_value(&model).unwrap();
+
+        let yaml = model.to_yaml().unwrap();
+        assert!(yaml.contains("type: ode"));
+        let from_yaml = Model::from_yaml(&yaml).unwrap();
+        assert_json_eq!(serde_json::to_value(&from_yaml).unwrap(), expected);
+
+        let toml = model.to_toml().unwrap();
+        assert!(toml.contains("type = \"ode\""));
+        let from_toml = Model::from_toml(&toml).unwrap();
+        assert_json_eq!(serde_json::to_value(&from_toml).unwrap(), expected);
+
+        // And back again, through the other format
+        let from_both = Model::from_toml(&from_yaml.to_toml().unwrap()).unwrap();
+        assert_eq!(from_both.to_yaml().unwrap(), yaml);
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn toml_metadata_type() {
+        let toml = r#"
+            arguments = []
+            equations = []
+
+            [metadata]
+            name = "Empty"
+            type = "cellular-automata"
+            width = 8
+        "#;
+
+        let Model::CellularAutomata(model) = Model::from_toml(toml).unwrap() else {
+            panic!("expected a cellular automaton");
+        };
+
+        assert_eq!(model.name, "Empty");
+        assert_eq!(model.metadata.width, 8);
+        assert!(Model::from_toml(&toml.replace("cellular-automata", "pde")).is_err());
+    }
+
+    /// Run with `UPDATE_SCHEMA=1` to rewrite the schema after changing the
+    /// model types.
+    #[test]
+    fn schema_is_up_to_date() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/schema/model.schema.json");
+        let schema = serde_json::to_string_pretty(&schema()).unwrap() + "\n";
+        if std::env::var_os("UPDATE_SCHEMA").is_some() {
+            std::fs::write(path, &schema).unwrap();
+        }
+
+        let saved = std::fs::read_to_string(path).unwrap_or_default();
+        assert!(
+            saved == schema,
+            "{path} is stale, rerun this test with UPDATE_SCHEMA=1 to update it"
+        );
+    }
+
+    #[test]
+    fn schema_validates_types() {
+        let schema = schema();
+        let definitions = &schema["$defs"];
+
+        assert_eq!(schema["title"], "odeir model");
+        assert_eq!(
+            definitions["Argument"]["anyOf"].as_array().map(Vec::len),
+            Some(2)
+        );
+        // ModelMetadata is flattened into the metadata
+        let tags: Vec<_> = definitions["Metadata"]["oneOf"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|variant| &variant["properties"]["type"]["const"])
+            .collect();
+        assert_eq!(tags, ["ode", "cellular-automata"]);
+        assert_eq!(
+            definitions["Component"]["properties"]["contribution"]["enum"],
+            serde_json::json!(["+", "-"])
+        );
+    }
+
+    #[test]
+    fn saving_keeps_what_was_loaded() {
+        let json = serde_json::json!({
+            "metadata": {
+                "version": 1,
+                "name": "Decay",
+                "type": "ode",
+                "start_time": 0.0,
+                "delta_time": 0.5,
+                "end_time": 5.0,
+                "zoom": 1.5,
+                "positions": { "x": { "x": 10.0, "y": -2.5 } },
+                "extension_files": []
+            },
+            "arguments": [
+                { "name": "x", "value": 3.0 },
+                { "name": "k", "value": 0.1 },
+                {
+                    "name": "decay",
+                    "operation": "max",
+                    "style": "Prefixed",
+                    "composition": [
+                        { "name": "k", "contribution": "-" },
+                        { "name": "x", "contribution": "+" }
+                    ]
+                }
+            ],
+            "equations": [
+                { "name": "dx", "operates_on": "x", "argument": "decay", "contribution": "+" }
+            ]
+        });
+
+        let model = serde_json::from_value::<Model>(json.clone()).unwrap();
+
+        assert_json_eq!(serde_json::to_value(&model).unwrap(), json);
+
+        // The order of the arguments doesn't make models different
+        let mut reordered = json;
+        reordered["arguments"].as_array_mut().unwrap().reverse();
+        assert_eq!(serde_json::from_value::<Model>(reordered).unwrap(), model);
+    }
+
+    fn name() -> impl Strategy<Value = String> {
+        "[a-z][a-z0-9_]{0,5}"
+    }
+
+    fn contribution() -> impl Strategy<Value = char> {
+        prop_oneof![Just('+'), Just('-')]
+    }
+
+    fn argument() -> impl Strategy<Value = Argument> {
+        let value = (
+            name(),
+            any::<f64>().prop_filter("finite", |v| v.is_finite()),
+        )
+            .prop_map(|(name, value)| Argument::Value { name, value });
+        let component = (name(), contribution())
+            .prop_map(|(name, contribution)| Component { name, contribution });
+        let composite = (
+            name(),
+            prop::sample::select(vec!["+", "-", "*", "/", "^", "max", "sin"]),
+            prop_oneof![
+                Just(CompositionStyle::Infixed),
+                Just(CompositionStyle::Prefixed)
+            ],
+            prop::collection::vec(component, 0..4),
+        )
+            .prop_map(
+                |(name, operation, style, composition)| Argument::Composite {
+                    name,
+                    operation: operation.to_owned(),
+                    style,
+                    composition,
+                },
+            );
+        prop_oneof![value, composite]
+    }
+
+    fn core() -> impl Strategy<Value = CoreModel> {
+        let equation = (name(), prop::option::of(name()), name(), contribution()).prop_map(
+            |(name, operates_on, argument, contribution)| Equation {
+                name,
+                operates_on,
+                argument,
+                contribution,
+            },
+        );
+        let position = (-1e4..1e4, -1e4..1e4).prop_map(|(x, y)| Position { x, y });
+        (
+            prop::collection::vec(argument(), 0..8),
+            prop::collection::vec(equation, 0..4),
+            prop::collection::btree_map(name(), position, 0..4),
+        )
+            .prop_map(|(arguments, equations, positions)| {
+                let mut core = CoreModel::new();
+                for argument in arguments {
+                    core.insert_argument(argument);
+                }
+                core.equations = equations;
+                core.positions = positions;
+                core
+            })
+    }
 
-    const GAME_OF_LIFE: &str = include_str!("../fixtures/game-of-life.json");
+    /// Fields from a newer version, which can't clash with the known ones.
+    fn unknown_fields() -> impl Strategy<Value = Map<String, serde_json::Value>> {
+        let value = prop_oneof![
+            any::<i64>().prop_map(serde_json::Value::from),
+            any::<bool>().prop_map(serde_json::Value::from),
+            "[ -~]{0,8}".prop_map(serde_json::Value::from),
+        ];
+        prop::collection::btree_map("new_[a-z]{1,6}", value, 0..3)
+    }
+
+    fn model() -> impl Strategy<Value = Model> {
+        let ode = (
+            any::<f64>().prop_filter("finite", |v| v.is_finite()),
+            0.001..1.0,
+            unknown_fields(),
+        )
+            .prop_map(|(start_time, delta_time, unknown_fields)| {
+                ModelMetadata::ODE(models::ode::Metadata {
+                    start_time,
+                    delta_time,
+                    end_time: start_time + 10.0,
+                    unknown_fields,
+                })
+            });
+        let ca = (
+            prop::sample::select(vec![
+                Lattice::Linear,
+                Lattice::Square,
+                Lattice::Hexagonal,
+                Lattice::Cubic,
+            ]),
+            1..100usize,
+            any::<u64>(),
+            name(),
+            unknown_fields(),
+        )
+            .prop_map(|(lattice, width, seed, state, unknown_fields)| {
+                ModelMetadata::CellularAutomata(models::cellular_automata::Metadata {
+                    lattice,
+                    width,
+                    seed,
+                    initial_condition: InitialCondition::Uniform { state },
+                    unknown_fields,
+                    ..Default::default()
+                })
+            });
+        (
+            "[ -~]{0,12}",
+            prop_oneof![ode, ca],
+            core(),
+            prop::collection::vec("[a-z]{1,8}\\.jinja", 0..2),
+        )
+            .prop_map(|(name, metadata, core, extension_files)| match metadata {
+                ModelMetadata::ODE(metadata) => Model::ODE(OdeModel {
+                    name,
+                    metadata,
+                    extension_files,
+                    core,
+                }),
+                ModelMetadata::CellularAutomata(metadata) => Model::CellularAutomata(CaModel {
+                    name,
+                    metadata,
+                    extension_files,
+                    core,
+                }),
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn json_round_trip(model in model()) {
+            let json = serde_json::to_string(&model).unwrap();
+            prop_assert_eq!(serde_json::from_str::<Model>(&json).unwrap(), model);
+        }
+    }
 
     /* fn fixture_game_of_life() -> Json {
         Json {
@@ -0,0 +1,341 @@
+//! Upgrades of files saved by older versions of the editor.
+//!
+//! Files record the shape they were saved in as `metadata.version`, which is
+//! missing from the ones written before it existed. Loading a file runs it
+//! through every migration from its version up to [`CURRENT_VERSION`],
+//! editing the raw JSON before it's deserialized.
+
+use std::fmt;
+
+use serde_json::{json, Value};
+
+use super::{Json, Model};
+use crate::models::number_name;
+
+/// The version written when saving.
+pub const CURRENT_VERSION: u64 = 1;
+
+/// Migrations from each version to the next, starting with version 0.
+const MIGRATIONS: [fn(&mut Value, &mut Vec<MigrationWarning>); CURRENT_VERSION as usize] =
+    [from_unversioned];
+
+/// Something about a file which was changed when loading it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MigrationWarning {
+    /// `equations` was a map from each population to its argument.
+    EquationsMap,
+    /// A component of `argument` was wrapped like `{ "Argument": ... }`.
+    TaggedComponent { argument: String },
+    /// A component of `argument` was a number, which is now the constant
+    /// called `constant`.
+    NumberComponent { argument: String, constant: String },
+    /// The file was saved by a newer version, so it's loaded as if it were
+    /// the current one.
+    NewerVersion { version: u64 },
+}
+
+impl fmt::Display for MigrationWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::EquationsMap => write!(f, "converted the map of equations to a list"),
+            Self::TaggedComponent { argument } => {
+                write!(f, "unwrapped a tagged component of `{argument}`")
+            }
+            Self::NumberComponent { argument, constant } => {
+                write!(f, "replaced a number in `{argument}` with `{constant}`")
+            }
+            Self::NewerVersion { version } => write!(
+                f,
+                "the file is version {version}, newer than {CURRENT_VERSION}"
+            ),
+        }
+    }
+}
+
+/// Loads a model of any version, reporting what had to be upgraded.
+pub fn from_str(json: &str) -> Result<(Model, Vec<MigrationWarning>), serde_json::Error> {
+    from_value(serde_json::from_str(json)?)
+}
+
+/// Loads a model of any version, reporting what had to be upgraded.
+///
+/// Cellular automata given as an elementary rule number are expanded here,
+/// see [`CaModel::expand_rule`](crate::models::cellular_automata::CaModel::expand_rule).
+pub fn from_value(mut json: Value) -> Result<(Model, Vec<MigrationWarning>), serde_json::Error> {
+    let warnings = migrate(&mut json);
+    let mut model = serde_json::from_value::<Json>(json)?.into();
+    if let Model::CellularAutomata(ca) = &mut model {
+        ca.expand_rule().map_err(serde::de::Error::custom)?;
+    }
+    Ok((model, warnings))
+}
+
+/// Upgrades `json` to the current version in place.
+pub fn migrate(json: &mut Value) -> Vec<MigrationWarning> {
+    let mut warnings = Vec::new();
+    let version = json["metadata"]["version"].as_u64().unwrap_or(0);
+
+    if version > CURRENT_VERSION {
+        warnings.push(MigrationWarning::NewerVersion { version });
+        return warnings;
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(json, &mut warnings);
+    }
+    if let Some(metadata) = json["metadata"].as_object_mut() {
+        metadata.insert("version".into(), CURRENT_VERSION.into());
+    }
+    warnings
+}
+
+/// Files from before versioning may have equations as a map from each
+/// population to its argument, and numbers or tagged components in
+/// compositions.
+fn from_unversioned(json: &mut Value, warnings: &mut Vec<MigrationWarning>) {
+    if let Some(equations) = json["equations"].as_object() {
+        let equations = equations
+            .iter()
+            .map(|(population, argument)| {
+                json!({
+                    "name": argument,
+                    "operates_on": population,
+                    "argument": argument,
+                    "contribution": "+",
+                })
+            })
+            .collect();
+        json["equations"] = Value::Array(equations);
+        warnings.push(MigrationWarning::EquationsMap);
+    }
+
+    let Some(arguments) = json["arguments"].as_array_mut() else {
+        return;
+    };
+    let taken: Vec<String> = arguments
+        .iter()
+        .filter_map(|argument| argument["name"].as_str())
+        .map(str::to_owned)
+        .collect();
+    let mut numbers: Vec<(String, f64)> = Vec::new();
+    for argument in arguments.iter_mut() {
+        let name = argument["name"].as_str().unwrap_or_default().to_owned();
+        let Some(composition) = argument["composition"].as_array_mut() else {
+            continue;
+        };
+
+        for component in composition {
+            let tagged = component
+                .as_object()
+                .filter(|object| object.len() == 1)
+                .and_then(|object| object.get("Argument").or(object.get("Constant")));
+            if let Some(inner) = tagged {
+                *component = inner.clone();
+                warnings.push(MigrationWarning::TaggedComponent {
+                    argument: name.clone(),
+                });
+            }
+
+            let Some(value) = component["value"].as_f64() else {
+                continue;
+            };
+            let mut contribution = component["contribution"].as_str().unwrap_or("+");
+            if value < 0.0 {
+                contribution = if contribution == "-" { "+" } else { "-" };
+            }
+            let constant = number_constant(&taken, &mut numbers, value.abs());
+            *component = json!({ "name": constant, "contribution": contribution });
+            warnings.push(MigrationWarning::NumberComponent {
+                argument: name.clone(),
+                constant,
+            });
+        }
+    }
+
+    if numbers.is_empty() {
+        return;
+    }
+    for (name, value) in &numbers {
+        arguments.push(json!({ "name": name, "value": value }));
+    }
+    if let Some(metadata) = json["metadata"].as_object_mut() {
+        let names = numbers.into_iter().map(|(name, _)| name.into()).collect();
+        metadata.insert("numbers".into(), Value::Array(names));
+    }
+}
+
+/// The name of the constant for the number `value`, which is added to
+/// `numbers` unless it already is. Names of other arguments in `taken` are
+/// avoided by appending underscores, so that the number doesn't take the
+/// value of a constant the file already had.
+fn number_constant(taken: &[String], numbers: &mut Vec<(String, f64)>, value: f64) -> String {
+    let mut name = number_name(value);
+    loop {
+        match numbers.iter().find(|(number, _)| *number == name) {
+            Some((_, existing)) if *existing == value => return name,
+            Some(_) => name.push('_'),
+            None if taken.contains(&name) => name.push('_'),
+            None => break,
+        }
+    }
+    numbers.push((name.clone(), value));
+    name
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::Argument;
+
+    #[test]
+    fn game_of_life() {
+        let json = include_str!("../../fixtures/game-of-life-serialized.json");
+        assert!(serde_json::from_str::<Json>(json).is_err());
+
+        let (model, warnings) = from_str(json).unwrap();
+
+        let Model::CellularAutomata(model) = model else {
+            panic!("expected a cellular automaton");
+        };
+        assert_eq!(model.name, "Conway's Game of Life");
+        let equations: Vec<_> = model
+            .equations
+            .iter()
+            .map(|eq| (eq.operates_on.as_deref().unwrap(), eq.argument.as_str()))
+            .collect();
+        assert_eq!(
+            equations,
+            [("alive", "alive_equation"), ("dead", "dead_equation")]
+        );
+        assert!(model.is_number("c3"));
+        let Argument::Composite { composition, .. } = &model.arguments["reproduction"] else {
+            panic!("expected a composite");
+        };
+        assert_eq!(composition[1].name, "c3");
+
+        assert_eq!(warnings[0], MigrationWarning::EquationsMap);
+        assert_eq!(
+            warnings[1],
+            MigrationWarning::NumberComponent {
+                argument: "reproduction".into(),
+                constant: "c3".into()
+            }
+        );
+        assert_eq!(warnings.len(), 5);
+        assert_eq!(
+            from_str(include_str!("../../fixtures/game-of-life.json"))
+                .unwrap()
+                .0,
+            Model::CellularAutomata(model)
+        );
+    }
+
+    #[test]
+    fn equations_map() {
+        let (model, warnings) =
+            from_str(include_str!("../../fixtures/lotka-volterra.json")).unwrap();
+
+        assert_eq!(warnings, [MigrationWarning::EquationsMap]);
+        let (current, warnings) =
+            from_str(include_str!("../../fixtures/lotka-volterra-v1.json")).unwrap();
+        assert_eq!(warnings, []);
+        assert_eq!(model, current);
+    }
+
+    #[test]
+    fn tagged_components() {
+        let mut json = json!({
+            "metadata": { "type": "ode", "start_time": 0, "delta_time": 1, "end_time": 2 },
+            "arguments": [
+                { "name": "x", "value": 1.0 },
+                {
+                    "name": "dx",
+                    "operation": "*",
+                    "composition": [
+                        { "Argument": { "name": "x", "contribution": "+" } },
+                        { "Constant": { "value": -0.5, "contribution": "+" } }
+                    ]
+                }
+            ],
+            "equations": { "x": "dx" }
+        });
+
+        let warnings = migrate(&mut json);
+
+        assert_eq!(
+            json["arguments"][1]["composition"],
+            json!([
+                { "name": "x", "contribution": "+" },
+                { "name": "c0_5", "contribution": "-" }
+            ])
+        );
+        assert_eq!(
+            json["arguments"][2],
+            json!({ "name": "c0_5", "value": 0.5 })
+        );
+        assert_eq!(json["metadata"]["version"], CURRENT_VERSION);
+        assert_eq!(warnings.len(), 4);
+        assert_eq!(
+            warnings[3].to_string(),
+            "replaced a number in `dx` with `c0_5`"
+        );
+
+        // Migrating again changes nothing
+        let migrated = json.clone();
+        assert_eq!(migrate(&mut json), []);
+        assert_eq!(json, migrated);
+    }
+
+    #[test]
+    fn numbers_keep_clear_of_other_constants() {
+        let json = json!({
+            "metadata": { "type": "ode", "start_time": 0, "delta_time": 1, "end_time": 2 },
+            "arguments": [
+                { "name": "x", "value": 1.0 },
+                { "name": "c3", "value": 5.0 },
+                {
+                    "name": "dx",
+                    "operation": "*",
+                    "composition": [
+                        { "name": "c3", "contribution": "+" },
+                        { "value": 3.0, "contribution": "+" },
+                        { "value": 3.0, "contribution": "-" }
+                    ]
+                }
+            ],
+            "equations": { "x": "dx" }
+        });
+
+        let (Model::ODE(model), warnings) = from_value(json).unwrap() else {
+            panic!("expected an ODE model");
+        };
+
+        assert_eq!(
+            warnings[1],
+            MigrationWarning::NumberComponent {
+                argument: "dx".into(),
+                constant: "c3_".into()
+            }
+        );
+        assert_eq!(model.evaluate("dx", &|_| None).unwrap(), 5.0 * 3.0 * -3.0);
+        assert!(model.is_number("c3_"));
+        assert!(!model.is_number("c3"));
+    }
+
+    #[test]
+    fn newer_versions_load_as_they_are() {
+        let json = json!({
+            "metadata": { "type": "cellular-automata", "version": CURRENT_VERSION + 1 },
+            "arguments": [],
+            "equations": []
+        });
+
+        let (_, warnings) = from_value(json).unwrap();
+
+        assert_eq!(
+            warnings,
+            [MigrationWarning::NewerVersion {
+                version: CURRENT_VERSION + 1
+            }]
+        );
+    }
+}
@@ -1,7 +1,9 @@
 pub type Map<K, V> = std::collections::BTreeMap<K, V>;
 
+pub mod import;
 pub mod json;
 pub mod models;
+pub mod output;
 pub mod transformations;
 
 pub use json::{Json, Metadata, Model, ModelMetadata, Position};
@@ -1,16 +1,33 @@
+use std::collections::{BTreeSet, HashSet};
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use crate::{Map, Position};
 
 pub mod cellular_automata;
+pub mod evaluation;
 pub mod ode;
 
+/// Models are equal when their arguments, equations, positions and numbers
+/// are, whatever order the arguments were inserted in.
+///
+/// The insertion order is private, so models are built with
+/// [`CoreModel::new`] or [`Default`] rather than a struct literal.
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CoreModel {
     pub arguments: Map<String, Argument>,
     pub equations: Vec<Equation>,
     pub positions: Map<String, Position>,
+    /// Names of the arguments in the order they were inserted, so that
+    /// saving a model keeps the order of the file it came from.
+    #[serde(skip)]
+    order: Vec<String>,
+    /// Constants standing for numbers written in an expression, added by
+    /// [`CoreModel::insert_number`], which text formats write as the numbers
+    /// themselves.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub(crate) numbers: BTreeSet<String>,
 }
 
 impl CoreModel {
@@ -18,14 +35,67 @@ impl CoreModel {
         Default::default()
     }
     pub fn insert_argument(&mut self, arg: Argument) {
+        if !self.arguments.contains_key(arg.name()) {
+            self.order.push(arg.name().to_owned());
+        }
         self.arguments.insert(arg.name().to_owned(), arg);
     }
+    /// The arguments in the order they were inserted, followed by the ones
+    /// added to `arguments` directly in alphabetical order.
+    pub fn arguments_in_order(&self) -> impl Iterator<Item = &'_ Argument> {
+        let inserted = self
+            .order
+            .iter()
+            .filter_map(|name| self.arguments.get(name));
+        let ordered: HashSet<&str> = self.order.iter().map(String::as_str).collect();
+        let rest = self
+            .arguments
+            .values()
+            .filter(move |arg| !ordered.contains(arg.name()));
+        inserted.chain(rest)
+    }
     pub fn insert_equation(&mut self, eq: Equation) {
         self.equations.push(eq);
     }
+    /// Adds a constant standing for the number `value`, named like `c0_5`
+    /// so that every backend accepts it, unless the model already has one.
+    /// Underscores are appended to the name while another argument has it.
+    pub(crate) fn insert_number(&mut self, value: f64) -> String {
+        let mut name = number_name(value);
+        loop {
+            match self.arguments.get(&name) {
+                None => break,
+                Some(Argument::Value {
+                    value: existing, ..
+                }) if *existing == value && self.numbers.contains(&name) => return name,
+                Some(_) => name.push('_'),
+            }
+        }
+        self.insert_argument(Argument::Value {
+            name: name.clone(),
+            value,
+        });
+        self.numbers.insert(name.clone());
+        name
+    }
+}
+
+/// The name of the constant standing for a number in an expression, before
+/// making it unique.
+pub(crate) fn number_name(value: f64) -> String {
+    format!("c{value}").replace(['.', '-'], "_")
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+impl PartialEq for CoreModel {
+    fn eq(&self, other: &Self) -> bool {
+        self.arguments == other.arguments
+            && self.equations == other.equations
+            && self.positions == other.positions
+            && self.numbers == other.numbers
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 #[serde(untagged)]
 pub enum Argument {
     Value {
@@ -41,7 +111,7 @@ pub enum Argument {
     },
 }
 
-#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Default, Clone, PartialEq, Copy)]
 pub enum CompositionStyle {
     #[default]
     Infixed,
@@ -57,17 +127,18 @@ impl Argument {
     }
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq, Default)]
 pub struct Component {
     pub name: String,
+    #[schemars(extend("enum" = ["+", "-"]))]
     pub contribution: char,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, PartialEq)]
 pub struct Equation {
     pub name: String,
     pub operates_on: Option<String>,
     pub argument: String,
+    #[schemars(extend("enum" = ["+", "-"]))]
     pub contribution: char,
 }
-
@@ -1,7 +1,119 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
 use super::CoreModel;
+use crate::Map;
+
+pub mod grid;
+pub mod initial_condition;
+pub mod lattice;
+pub mod pattern;
+pub(crate) mod rng;
+pub mod stepping;
+
+pub use grid::Grid;
+pub use initial_condition::{Cell, InitialCondition, InitialConditionError};
+pub use lattice::Lattice;
+pub use pattern::{Pattern, PatternError, PatternFormat};
+pub use stepping::SimulationError;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+#[schemars(rename = "CaMetadata")]
+pub struct Metadata {
+    pub lattice: Lattice,
+    pub width: usize,
+    /// Ignored by linear lattices.
+    pub height: usize,
+    /// Only used by cubic lattices.
+    pub depth: usize,
+    /// Seed for every random draw of the simulation, so that runs can be
+    /// reproduced from the model file alone.
+    pub seed: u64,
+    pub initial_condition: InitialCondition,
+    /// Wolfram's elementary rule number, a shorthand for a linear automaton
+    /// whose states and rules are filled in by [`CaModel::expand_rule`] when
+    /// the model has none.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<u8>,
+    /// Fields this version doesn't know about, kept so that saving a model
+    /// from a newer editor doesn't lose them.
+    #[serde(flatten)]
+    pub unknown_fields: Map<String, serde_json::Value>,
+}
 
-#[derive(Debug, Clone)]
+impl Default for Metadata {
+    fn default() -> Self {
+        Self {
+            lattice: Lattice::Square,
+            width: 64,
+            height: 64,
+            depth: 1,
+            seed: 0,
+            initial_condition: Default::default(),
+            rule: None,
+            unknown_fields: Map::new(),
+        }
+    }
+}
+
+impl Metadata {
+    /// The `(width, height, depth)` of the grid, once the dimensions the
+    /// lattice does not have are left out.
+    pub fn dimensions(&self) -> (usize, usize, usize) {
+        match self.lattice {
+            Lattice::Linear => (self.width, 1, 1),
+            Lattice::Square | Lattice::Hexagonal => (self.width, self.height, 1),
+            Lattice::Cubic => (self.width, self.height, self.depth),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct CaModel {
     pub name: String,
+    pub metadata: Metadata,
+    pub extension_files: Vec<String>,
+    #[serde(flatten)]
     pub core: CoreModel,
 }
+
+impl CaModel {
+    pub fn new(name: String, metadata: Metadata) -> Self {
+        Self {
+            name,
+            metadata,
+            core: CoreModel::new(),
+            extension_files: Vec::new(),
+        }
+    }
+
+    /// The cell states of this automaton, in the order used to index a
+    /// [`Grid`]'s cells.
+    pub fn states(&self) -> Vec<&str> {
+        self.get_populations().map(|arg| arg.name()).collect()
+    }
+
+    pub fn state_index(&self, state: &str) -> Option<usize> {
+        self.get_populations().position(|arg| arg.name() == state)
+    }
+
+    /// Builds the starting grid described by `metadata.initial_condition`.
+    pub fn initial_grid(&self) -> Result<Grid, InitialConditionError> {
+        self.metadata.initial_condition.build(self)
+    }
+}
+
+impl std::ops::Deref for CaModel {
+    type Target = CoreModel;
+
+    fn deref(&self) -> &Self::Target {
+        &self.core
+    }
+}
+
+impl std::ops::DerefMut for CaModel {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.core
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+/// A lattice of cells, each holding the index of its state in
+/// [`CaModel::states`](super::CaModel::states).
+///
+/// Cells are stored layer by layer, row by row. 2D lattices have a single
+/// layer and linear ones a single row.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Grid {
+    pub width: usize,
+    pub height: usize,
+    #[serde(default = "one")]
+    pub depth: usize,
+    pub cells: Vec<usize>,
+}
+
+fn one() -> usize {
+    1
+}
+
+impl Grid {
+    pub fn new(width: usize, height: usize, state: usize) -> Self {
+        Self::with_depth(width, height, 1, state)
+    }
+
+    pub fn with_depth(width: usize, height: usize, depth: usize, state: usize) -> Self {
+        Self {
+            width,
+            height,
+            depth,
+            cells: vec![state; width * height * depth],
+        }
+    }
+
+    pub fn index(&self, x: usize, y: usize) -> usize {
+        self.index_at(x, y, 0)
+    }
+
+    pub fn index_at(&self, x: usize, y: usize, z: usize) -> usize {
+        (z * self.height + y) * self.width + x
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> usize {
+        self.get_at(x, y, 0)
+    }
+
+    pub fn get_at(&self, x: usize, y: usize, z: usize) -> usize {
+        self.cells[self.index_at(x, y, z)]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, state: usize) {
+        self.set_at(x, y, 0, state);
+    }
+
+    pub fn set_at(&mut self, x: usize, y: usize, z: usize, state: usize) {
+        let index = self.index_at(x, y, z);
+        self.cells[index] = state;
+    }
+
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        self.contains_at(x, y, 0)
+    }
+
+    pub fn contains_at(&self, x: usize, y: usize, z: usize) -> bool {
+        x < self.width && y < self.height && z < self.depth
+    }
+
+    /// Number of cells currently in `state`.
+    pub fn count(&self, state: usize) -> usize {
+        self.cells.iter().filter(|&&cell| cell == state).count()
+    }
+
+    /// Stacks the first row of each grid into a single 2D grid, one row per
+    /// generation. This is the usual way of drawing linear automata.
+    pub fn space_time<'a>(generations: impl IntoIterator<Item = &'a Grid>) -> Self {
+        let mut width = 0;
+        let mut cells = Vec::new();
+        let mut height = 0;
+        for grid in generations {
+            width = grid.width;
+            cells.extend_from_slice(&grid.cells[..grid.width]);
+            height += 1;
+        }
+        Self {
+            width,
+            height,
+            depth: 1,
+            cells,
+        }
+    }
+}
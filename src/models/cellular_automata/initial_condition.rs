@@ -0,0 +1,371 @@
+use std::{fmt, path::Path};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::{Argument, Map};
+
+use super::{
+    pattern::{Pattern, PatternError, PatternFormat},
+    rng, CaModel, Grid, Lattice,
+};
+
+/// How the cells of a [`CaModel`] are set up before the first step.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(tag = "kind", rename_all = "kebab-case")]
+pub enum InitialCondition {
+    /// Every cell starts in `state`.
+    Uniform { state: String },
+    /// Every cell draws its state independently, seeded by
+    /// [`Metadata::seed`](super::Metadata::seed).
+    ///
+    /// With a `background`, `densities` are the probabilities of each state
+    /// and the remaining probability goes to the background. Without one,
+    /// `densities` are relative weights. When `densities` is empty, the
+    /// value of each state is used instead.
+    Random {
+        #[serde(default)]
+        background: Option<String>,
+        #[serde(default)]
+        densities: Map<String, f64>,
+    },
+    /// Every cell starts in `background`, except for the listed ones.
+    Cells {
+        background: String,
+        cells: Vec<Cell>,
+    },
+    /// A two-state Life pattern whose top left corner is placed at `(x, y)`
+    /// of the first layer. Its live cells start in `alive` and everything
+    /// else in `background`.
+    Pattern {
+        background: String,
+        alive: String,
+        format: PatternFormat,
+        source: String,
+        #[serde(default)]
+        x: usize,
+        #[serde(default)]
+        y: usize,
+    },
+}
+
+impl Default for InitialCondition {
+    fn default() -> Self {
+        Self::Random {
+            background: None,
+            densities: Map::new(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Cell {
+    pub x: usize,
+    #[serde(default)]
+    pub y: usize,
+    #[serde(default)]
+    pub z: usize,
+    pub state: String,
+}
+
+#[derive(Debug)]
+pub enum InitialConditionError {
+    UnknownState(String),
+    OutOfBounds {
+        x: usize,
+        y: usize,
+        z: usize,
+    },
+    EmptyGrid,
+    /// Hexagonal lattices only wrap around with an even number of rows.
+    OddHexagonalHeight(usize),
+    InvalidDensities,
+    DensitiesAboveOne(f64),
+    Pattern(PatternError),
+}
+
+impl fmt::Display for InitialConditionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownState(state) => write!(f, "`{state}` is not a state of this model"),
+            Self::OutOfBounds { x, y, z } => {
+                write!(f, "cell ({x}, {y}, {z}) is outside of the grid")
+            }
+            Self::EmptyGrid => write!(f, "the grid has no cells"),
+            Self::OddHexagonalHeight(height) => write!(
+                f,
+                "hexagonal grids need an even height to wrap around, not {height}"
+            ),
+            Self::InvalidDensities => write!(f, "densities must be non-negative and not all zero"),
+            Self::DensitiesAboveOne(total) => write!(
+                f,
+                "densities add up to {total}, leaving nothing for the background"
+            ),
+            Self::Pattern(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for InitialConditionError {}
+
+impl From<PatternError> for InitialConditionError {
+    fn from(value: PatternError) -> Self {
+        Self::Pattern(value)
+    }
+}
+
+impl InitialCondition {
+    /// Imports a `.rle` or `.cells` file, embedding its contents so that the
+    /// model no longer depends on the file.
+    pub fn from_pattern_file(
+        path: impl AsRef<Path>,
+        background: impl Into<String>,
+        alive: impl Into<String>,
+    ) -> Result<Self, PatternError> {
+        let path = path.as_ref();
+        let format = PatternFormat::from_path(path)
+            .ok_or_else(|| PatternError::UnknownFormat(path.display().to_string()))?;
+        let source = std::fs::read_to_string(path)?;
+
+        // Fail on import rather than when the model is first simulated
+        Pattern::parse(format, &source)?;
+
+        Ok(Self::Pattern {
+            background: background.into(),
+            alive: alive.into(),
+            format,
+            source,
+            x: 0,
+            y: 0,
+        })
+    }
+
+    pub fn build(&self, model: &CaModel) -> Result<Grid, InitialConditionError> {
+        let state_index = |state: &str| {
+            model
+                .state_index(state)
+                .ok_or_else(|| InitialConditionError::UnknownState(state.to_owned()))
+        };
+        let (width, height, depth) = model.metadata.dimensions();
+        if model.metadata.lattice == Lattice::Hexagonal && height % 2 == 1 {
+            return Err(InitialConditionError::OddHexagonalHeight(height));
+        }
+        let grid = |state| Grid::with_depth(width, height, depth, state);
+
+        match self {
+            Self::Uniform { state } => Ok(grid(state_index(state)?)),
+            Self::Random {
+                background,
+                densities,
+            } => {
+                let weights: Vec<(usize, f64)> = if densities.is_empty() {
+                    model
+                        .get_populations()
+                        .enumerate()
+                        .map(|(index, arg)| match arg {
+                            Argument::Value { value, .. } => (index, *value),
+                            _ => unreachable!("populations are always values"),
+                        })
+                        .collect()
+                } else {
+                    densities
+                        .iter()
+                        .map(|(state, density)| Ok((state_index(state)?, *density)))
+                        .collect::<Result<_, InitialConditionError>>()?
+                };
+
+                let total: f64 = weights.iter().map(|(_, weight)| weight).sum();
+                if weights.iter().any(|(_, weight)| *weight < 0.0) || total <= 0.0 {
+                    return Err(InitialConditionError::InvalidDensities);
+                }
+
+                let (background, scale) = match background {
+                    Some(_) if total > 1.0 + f64::EPSILON * weights.len() as f64 => {
+                        return Err(InitialConditionError::DensitiesAboveOne(total))
+                    }
+                    Some(state) => (Some(state_index(state)?), 1.0),
+                    None => (None, total),
+                };
+
+                let mut grid = grid(0);
+                for (index, cell) in grid.cells.iter_mut().enumerate() {
+                    let draw = rng::uniform(model.metadata.seed, 0, index as u64, 0) * scale;
+                    let mut cumulative = 0.0;
+                    let drawn = weights.iter().find(|(_, weight)| {
+                        cumulative += weight;
+                        draw < cumulative
+                    });
+                    *cell = match (drawn, background) {
+                        (Some((state, _)), _) => *state,
+                        (None, Some(background)) => background,
+                        // Only reachable through rounding errors
+                        (None, None) => weights.last().unwrap().0,
+                    };
+                }
+                Ok(grid)
+            }
+            Self::Cells { background, cells } => {
+                let mut grid = grid(state_index(background)?);
+                for &Cell { x, y, z, ref state } in cells {
+                    if !grid.contains_at(x, y, z) {
+                        return Err(InitialConditionError::OutOfBounds { x, y, z });
+                    }
+                    grid.set_at(x, y, z, state_index(state)?);
+                }
+                Ok(grid)
+            }
+            Self::Pattern {
+                background,
+                alive,
+                format,
+                source,
+                x,
+                y,
+            } => {
+                let mut grid = grid(state_index(background)?);
+                let alive = state_index(alive)?;
+                for (px, py) in Pattern::parse(*format, source)?.alive {
+                    let (cx, cy) = (x + px, y + py);
+                    if !grid.contains(cx, cy) {
+                        return Err(InitialConditionError::OutOfBounds { x: cx, y: cy, z: 0 });
+                    }
+                    grid.set(cx, cy, alive);
+                }
+                Ok(grid)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::cellular_automata::Metadata;
+    use crate::Equation;
+
+    use super::*;
+
+    fn game_of_life(initial_condition: InitialCondition) -> CaModel {
+        let mut model = CaModel::new(
+            "Game of Life".into(),
+            Metadata {
+                width: 8,
+                height: 6,
+                seed: 42,
+                initial_condition,
+                ..Default::default()
+            },
+        );
+
+        for (name, value) in [("alive", 0.25), ("dead", 0.75)] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+            model.insert_equation(Equation {
+                name: name.into(),
+                operates_on: Some(name.into()),
+                argument: name.into(),
+                contribution: '+',
+            });
+        }
+
+        model
+    }
+
+    #[test]
+    fn uniform() {
+        let model = game_of_life(InitialCondition::Uniform {
+            state: "dead".into(),
+        });
+        let grid = model.initial_grid().unwrap();
+
+        assert_eq!(grid, Grid::new(8, 6, model.state_index("dead").unwrap()));
+    }
+
+    #[test]
+    fn random_is_reproducible_from_seed() {
+        let mut model = game_of_life(InitialCondition::default());
+        let first = model.initial_grid().unwrap();
+
+        assert_eq!(first, model.initial_grid().unwrap());
+
+        model.metadata.seed += 1;
+        assert_ne!(first, model.initial_grid().unwrap());
+    }
+
+    #[test]
+    fn random_density_with_background() {
+        let mut model = game_of_life(InitialCondition::Random {
+            background: Some("dead".into()),
+            densities: [("alive".to_owned(), 0.1)].into_iter().collect(),
+        });
+        model.metadata.width = 200;
+        model.metadata.height = 200;
+
+        let grid = model.initial_grid().unwrap();
+        let alive = grid.count(model.state_index("alive").unwrap()) as f64;
+
+        assert!((alive / 40_000.0 - 0.1).abs() < 0.01);
+    }
+
+    #[test]
+    fn densities_above_one_with_background() {
+        let model = game_of_life(InitialCondition::Random {
+            background: Some("dead".into()),
+            densities: [("alive".to_owned(), 0.6), ("dead".to_owned(), 0.6)]
+                .into_iter()
+                .collect(),
+        });
+
+        assert!(matches!(
+            model.initial_grid(),
+            Err(InitialConditionError::DensitiesAboveOne(total)) if total == 1.2
+        ));
+    }
+
+    #[test]
+    fn cells() {
+        let model = game_of_life(InitialCondition::Cells {
+            background: "dead".into(),
+            cells: vec![Cell {
+                x: 7,
+                y: 5,
+                z: 0,
+                state: "alive".into(),
+            }],
+        });
+        let grid = model.initial_grid().unwrap();
+
+        assert_eq!(grid.count(model.state_index("alive").unwrap()), 1);
+        assert_eq!(grid.get(7, 5), model.state_index("alive").unwrap());
+    }
+
+    #[test]
+    fn pattern_out_of_bounds() {
+        let model = game_of_life(InitialCondition::Pattern {
+            background: "dead".into(),
+            alive: "alive".into(),
+            format: PatternFormat::Plaintext,
+            source: ".O\n..O\nOOO".into(),
+            x: 6,
+            y: 0,
+        });
+
+        assert!(matches!(
+            model.initial_grid(),
+            Err(InitialConditionError::OutOfBounds { x: 8, y: 1, z: 0 })
+        ));
+    }
+
+    #[test]
+    fn unknown_state() {
+        let model = game_of_life(InitialCondition::Uniform {
+            state: "zombie".into(),
+        });
+
+        assert!(matches!(
+            model.initial_grid(),
+            Err(InitialConditionError::UnknownState(state)) if state == "zombie"
+        ));
+    }
+}
@@ -0,0 +1,350 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::models::{Argument, Component, Equation};
+
+use super::{CaModel, Cell, InitialCondition, InitialConditionError, Metadata};
+
+/// The arrangement of cells and the neighbourhood that comes with it. All
+/// lattices wrap around their edges.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum Lattice {
+    /// 2D grid with the 8 cell Moore neighbourhood.
+    #[default]
+    Square,
+    /// 2D grid of hexagons in "odd-r" layout, where odd rows are shifted
+    /// half a cell to the right. Each cell has 6 neighbours. The height
+    /// must be even for the wrap around to line up.
+    Hexagonal,
+    /// A single row where each cell has its left and right neighbours, as
+    /// in Wolfram's elementary automata.
+    Linear,
+    /// 3D grid with the 26 cell Moore neighbourhood.
+    Cubic,
+}
+
+type Offset = (isize, isize, isize);
+
+const SQUARE: [Offset; 8] = [
+    (-1, -1, 0),
+    (0, -1, 0),
+    (1, -1, 0),
+    (-1, 0, 0),
+    (1, 0, 0),
+    (-1, 1, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+];
+
+const HEXAGONAL_EVEN: [Offset; 6] = [
+    (-1, -1, 0),
+    (0, -1, 0),
+    (-1, 0, 0),
+    (1, 0, 0),
+    (-1, 1, 0),
+    (0, 1, 0),
+];
+
+const HEXAGONAL_ODD: [Offset; 6] = [
+    (0, -1, 0),
+    (1, -1, 0),
+    (-1, 0, 0),
+    (1, 0, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+];
+
+const LINEAR: [Offset; 2] = [(-1, 0, 0), (1, 0, 0)];
+
+const CUBIC: [Offset; 26] = {
+    let mut offsets = [(0, 0, 0); 26];
+    let mut i = 0;
+    let mut n = 0;
+    while n < 27 {
+        let offset = (n % 3 - 1, n / 3 % 3 - 1, n / 9 - 1);
+        if n != 13 {
+            offsets[i] = offset;
+            i += 1;
+        }
+        n += 1;
+    }
+    offsets
+};
+
+impl Lattice {
+    /// Offsets `(dx, dy, dz)` from a cell in row `y` to its neighbours.
+    pub fn neighbours(self, y: usize) -> &'static [(isize, isize, isize)] {
+        match self {
+            Self::Square => &SQUARE,
+            Self::Hexagonal if y.is_multiple_of(2) => &HEXAGONAL_EVEN,
+            Self::Hexagonal => &HEXAGONAL_ODD,
+            Self::Linear => &LINEAR,
+            Self::Cubic => &CUBIC,
+        }
+    }
+}
+
+impl CaModel {
+    /// Builds Wolfram's elementary automaton number `rule`: a linear lattice
+    /// of `off` and `on` cells, starting with a single `on` cell in the
+    /// middle.
+    pub fn elementary(rule: u8, width: usize) -> Result<Self, InitialConditionError> {
+        let mut model = Self::new(
+            String::new(),
+            Metadata {
+                width,
+                rule: Some(rule),
+                ..Default::default()
+            },
+        );
+        model.expand_rule()?;
+        Ok(model)
+    }
+
+    /// Fills in the `off` and `on` states and the rules of the elementary
+    /// automaton `metadata.rule`, unless the model already has arguments or
+    /// equations. Without an explicit initial condition, a single `on` cell
+    /// starts in the middle.
+    ///
+    /// Bit `n` of the rule is the next state of cells whose `pattern` is
+    /// `n`, so each rule becomes a sum of `pattern == n` comparisons.
+    pub fn expand_rule(&mut self) -> Result<(), InitialConditionError> {
+        let Some(rule) = self.metadata.rule else {
+            return Ok(());
+        };
+        let width = self.metadata.width;
+        if width == 0 {
+            return Err(InitialConditionError::EmptyGrid);
+        }
+        if !self.arguments.is_empty() || !self.equations.is_empty() {
+            return Ok(());
+        }
+
+        if self.name.is_empty() {
+            self.name = format!("Rule {rule}");
+        }
+        self.metadata.lattice = Lattice::Linear;
+        self.metadata.height = 1;
+        if self.metadata.initial_condition == InitialCondition::default() {
+            self.metadata.initial_condition = InitialCondition::Cells {
+                background: "off".into(),
+                cells: vec![Cell {
+                    x: width / 2,
+                    y: 0,
+                    z: 0,
+                    state: "on".into(),
+                }],
+            };
+        }
+        let value = |name: String, value: f64| Argument::Value { name, value };
+        let positive = |name: String| Component {
+            name,
+            contribution: '+',
+        };
+
+        self.insert_argument(value("off".into(), 0.5));
+        self.insert_argument(value("on".into(), 0.5));
+        // Bound to the neighbourhood pattern while stepping
+        self.insert_argument(value("pattern".into(), 0.0));
+        self.insert_argument(value("never".into(), 0.0));
+
+        for pattern in 0..8 {
+            self.insert_argument(value(format!("pattern_{pattern}"), pattern as f64));
+            self.insert_argument(Argument::Composite {
+                name: format!("matches_{pattern}"),
+                operation: "==".into(),
+                style: Default::default(),
+                composition: vec![
+                    positive("pattern".into()),
+                    positive(format!("pattern_{pattern}")),
+                ],
+            });
+        }
+
+        for (state, bit) in [("off", false), ("on", true)] {
+            let patterns: Vec<Component> = (0..8)
+                .filter(|pattern| (rule >> pattern & 1 == 1) == bit)
+                .map(|pattern| positive(format!("matches_{pattern}")))
+                .collect();
+
+            let argument = if patterns.is_empty() {
+                "never".to_owned()
+            } else {
+                let name = format!("to_{state}");
+                self.insert_argument(Argument::Composite {
+                    name: name.clone(),
+                    operation: "+".into(),
+                    style: Default::default(),
+                    composition: patterns,
+                });
+                name
+            };
+
+            self.insert_equation(Equation {
+                name: format!("to_{state}"),
+                operates_on: Some(state.into()),
+                argument,
+                contribution: '+',
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::cellular_automata::Grid;
+
+    use super::*;
+
+    /// Reference implementation of an elementary automaton step.
+    fn wolfram_step(rule: u8, row: &[usize]) -> Vec<usize> {
+        let n = row.len();
+        (0..n)
+            .map(|x| {
+                let pattern = row[(x + n - 1) % n] * 4 + row[x] * 2 + row[(x + 1) % n];
+                (rule >> pattern & 1) as usize
+            })
+            .collect()
+    }
+
+    #[test]
+    fn elementary_rules_match_wolfram() {
+        for rule in [0, 30, 90, 110, 184, 255] {
+            let model = CaModel::elementary(rule, 31).unwrap();
+            let grids = model.run(15).unwrap();
+
+            let mut expected = grids[0].cells.clone();
+            for grid in &grids[1..] {
+                expected = wolfram_step(rule, &expected);
+                assert_eq!(grid.cells, expected, "rule {rule}");
+            }
+        }
+    }
+
+    #[test]
+    fn rule_90_draws_sierpinski() {
+        let model = CaModel::elementary(90, 9).unwrap();
+        let diagram = Grid::space_time(&model.run(3).unwrap());
+
+        let rows: Vec<String> = diagram
+            .cells
+            .chunks(diagram.width)
+            .map(|row| {
+                row.iter()
+                    .map(|&on| if on == 1 { '#' } else { '.' })
+                    .collect()
+            })
+            .collect();
+        assert_eq!(rows, ["....#....", "...#.#...", "..#...#..", ".#.#.#.#."]);
+    }
+
+    #[test]
+    fn elementary_needs_cells() {
+        assert!(matches!(
+            CaModel::elementary(30, 0),
+            Err(InitialConditionError::EmptyGrid)
+        ));
+    }
+
+    /// A single live cell which spreads to all of its neighbours.
+    fn spread(lattice: Lattice) -> CaModel {
+        let mut metadata = Metadata {
+            lattice,
+            width: 6,
+            height: 6,
+            depth: 6,
+            ..Default::default()
+        };
+        let (_, height, depth) = metadata.dimensions();
+        metadata.initial_condition = InitialCondition::Cells {
+            background: "dead".into(),
+            cells: vec![Cell {
+                x: 2,
+                y: 3.min(height - 1),
+                z: 2.min(depth - 1),
+                state: "alive".into(),
+            }],
+        };
+
+        let mut model = CaModel::new("Spread".into(), metadata);
+        for (name, value) in [("alive", 0.5), ("dead", 0.5), ("never", 0.0)] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+        }
+        for (state, argument) in [("alive", "alive"), ("dead", "never")] {
+            model.insert_equation(Equation {
+                name: format!("to_{state}"),
+                operates_on: Some(state.into()),
+                argument: argument.into(),
+                contribution: '+',
+            });
+        }
+        model
+    }
+
+    #[test]
+    fn neighbourhoods_while_stepping() {
+        for (lattice, cells) in [
+            (Lattice::Linear, 3),
+            (Lattice::Square, 9),
+            (Lattice::Hexagonal, 7),
+            (Lattice::Cubic, 27),
+        ] {
+            let model = spread(lattice);
+            let grids = model.run(1).unwrap();
+            let alive = model.state_index("alive").unwrap();
+
+            assert_eq!(grids[1].count(alive), cells, "{lattice:?}");
+        }
+    }
+
+    #[test]
+    fn hexagonal_odd_rows_lean_right() {
+        let model = spread(Lattice::Hexagonal);
+        let next = &model.run(1).unwrap()[1];
+        let alive = model.state_index("alive").unwrap();
+
+        // (2, 3) is on an odd row, so its diagonal neighbours are x = 2 and 3
+        for (x, y) in [(2, 2), (3, 2), (1, 3), (3, 3), (2, 4), (3, 4)] {
+            assert_eq!(next.get(x, y), alive, "({x}, {y})");
+        }
+        assert_ne!(next.get(1, 2), alive);
+    }
+
+    #[test]
+    fn hexagonal_heights_are_even() {
+        let mut model = spread(Lattice::Hexagonal);
+        model.metadata.height = 5;
+
+        assert!(matches!(
+            model.initial_grid(),
+            Err(InitialConditionError::OddHexagonalHeight(5))
+        ));
+    }
+
+    #[test]
+    fn neighbourhood_sizes() {
+        assert_eq!(Lattice::Square.neighbours(0).len(), 8);
+        assert_eq!(Lattice::Hexagonal.neighbours(0).len(), 6);
+        assert_eq!(Lattice::Hexagonal.neighbours(1).len(), 6);
+        assert_eq!(Lattice::Linear.neighbours(0).len(), 2);
+        assert_eq!(Lattice::Cubic.neighbours(0).len(), 26);
+        assert!(!CUBIC.contains(&(0, 0, 0)));
+    }
+
+    #[test]
+    fn hexagonal_neighbourhood_is_symmetric() {
+        // If b is a's neighbour, a is b's, whatever the parity of their rows
+        for y in [0_isize, 1] {
+            for &(dx, dy, _) in Lattice::Hexagonal.neighbours(y as usize) {
+                let back = Lattice::Hexagonal.neighbours((y + dy).rem_euclid(2) as usize);
+                assert!(back.contains(&(-dx, -dy, 0)), "{dx}, {dy} from row {y}");
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+//! Readers for the Life pattern formats found in the wild: run-length
+//! encoded `.rle` files and plaintext `.cells` files.
+//!
+//! Both formats describe two-state patterns, so a [`Pattern`] only records
+//! which cells are alive.
+
+use std::{fmt, path::Path};
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum PatternFormat {
+    Rle,
+    Plaintext,
+}
+
+impl PatternFormat {
+    /// Guesses the format from the file extension (`.rle` or `.cells`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "rle" => Some(Self::Rle),
+            "cells" => Some(Self::Plaintext),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Pattern {
+    pub width: usize,
+    pub height: usize,
+    /// `(x, y)` coordinates of the live cells, relative to the pattern's top
+    /// left corner.
+    pub alive: Vec<(usize, usize)>,
+}
+
+#[derive(Debug)]
+pub enum PatternError {
+    Io(std::io::Error),
+    UnknownFormat(String),
+    UnexpectedCharacter { line: usize, character: char },
+    InvalidHeader(String),
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "could not read pattern: {err}"),
+            Self::UnknownFormat(path) => write!(f, "unknown pattern format for `{path}`"),
+            Self::UnexpectedCharacter { line, character } => {
+                write!(f, "unexpected character `{character}` on line {line}")
+            }
+            Self::InvalidHeader(header) => write!(f, "invalid RLE header `{header}`"),
+        }
+    }
+}
+
+impl std::error::Error for PatternError {}
+
+impl From<std::io::Error> for PatternError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl Pattern {
+    pub fn parse(format: PatternFormat, source: &str) -> Result<Self, PatternError> {
+        match format {
+            PatternFormat::Rle => Self::parse_rle(source),
+            PatternFormat::Plaintext => Self::parse_plaintext(source),
+        }
+    }
+
+    pub fn read(path: impl AsRef<Path>) -> Result<Self, PatternError> {
+        let path = path.as_ref();
+        let format = PatternFormat::from_path(path)
+            .ok_or_else(|| PatternError::UnknownFormat(path.display().to_string()))?;
+        Self::parse(format, &std::fs::read_to_string(path)?)
+    }
+
+    /// Parses the run-length encoded format, e.g. a glider:
+    ///
+    /// ```text
+    /// x = 3, y = 3, rule = B3/S23
+    /// bob$2bo$3o!
+    /// ```
+    pub fn parse_rle(source: &str) -> Result<Self, PatternError> {
+        let mut pattern = Self::default();
+        let (mut x, mut y) = (0, 0);
+        let mut run: Option<usize> = None;
+
+        'lines: for (line_number, line) in source.lines().enumerate() {
+            let line = line.trim();
+
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+
+            if line.starts_with('x') {
+                let (width, height) = parse_rle_header(line)?;
+                pattern.width = width;
+                pattern.height = height;
+                continue;
+            }
+
+            for character in line.chars() {
+                match character {
+                    '0'..='9' => {
+                        let digit = character.to_digit(10).unwrap() as usize;
+                        run = Some(run.unwrap_or(0) * 10 + digit);
+                        continue;
+                    }
+                    'b' | '.' => x += run.unwrap_or(1),
+                    'o' | 'A' => {
+                        for _ in 0..run.unwrap_or(1) {
+                            pattern.alive.push((x, y));
+                            x += 1;
+                        }
+                    }
+                    '$' => {
+                        y += run.unwrap_or(1);
+                        x = 0;
+                    }
+                    '!' => break 'lines,
+                    c if c.is_whitespace() => {}
+                    character => {
+                        return Err(PatternError::UnexpectedCharacter {
+                            line: line_number + 1,
+                            character,
+                        })
+                    }
+                }
+                run = None;
+                pattern.width = pattern.width.max(x);
+                pattern.height = pattern.height.max(y + 1);
+            }
+        }
+
+        Ok(pattern)
+    }
+
+    /// Parses the plaintext format, where `!` starts a comment line, `.` is
+    /// a dead cell and `O` a live one.
+    pub fn parse_plaintext(source: &str) -> Result<Self, PatternError> {
+        let mut pattern = Self::default();
+
+        let rows = source
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.starts_with('!'));
+
+        for (y, (line_number, line)) in rows.enumerate() {
+            let line = line.trim_end();
+
+            for (x, character) in line.chars().enumerate() {
+                match character {
+                    '.' => {}
+                    'O' | '*' => pattern.alive.push((x, y)),
+                    character => {
+                        return Err(PatternError::UnexpectedCharacter {
+                            line: line_number + 1,
+                            character,
+                        })
+                    }
+                }
+            }
+
+            pattern.width = pattern.width.max(line.chars().count());
+            pattern.height = y + 1;
+        }
+
+        Ok(pattern)
+    }
+}
+
+fn parse_rle_header(header: &str) -> Result<(usize, usize), PatternError> {
+    let invalid = || PatternError::InvalidHeader(header.to_owned());
+    let (mut width, mut height) = (None, None);
+
+    for assignment in header.split(',') {
+        let (key, value) = assignment.split_once('=').ok_or_else(invalid)?;
+        let dimension = match key.trim() {
+            "x" => &mut width,
+            "y" => &mut height,
+            _ => continue,
+        };
+        *dimension = Some(value.trim().parse().map_err(|_| invalid())?);
+    }
+
+    Ok((width.ok_or_else(invalid)?, height.ok_or_else(invalid)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLIDER: [(usize, usize); 5] = [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)];
+
+    #[test]
+    fn parse_rle_glider() {
+        let source =
+            "#N Glider\n#C The smallest spaceship\nx = 3, y = 3, rule = B3/S23\nbob$2bo$3o!";
+        let pattern = Pattern::parse_rle(source).unwrap();
+
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.alive, GLIDER);
+    }
+
+    #[test]
+    fn parse_rle_runs_across_lines() {
+        let source = "x = 12, y = 3\n2o10b$\n12o\n$b10ob!";
+        let pattern = Pattern::parse_rle(source).unwrap();
+
+        assert_eq!((pattern.width, pattern.height), (12, 3));
+        assert_eq!(pattern.alive.len(), 2 + 12 + 10);
+        assert!(pattern.alive.contains(&(11, 1)));
+        assert!(!pattern.alive.contains(&(0, 2)));
+    }
+
+    #[test]
+    fn parse_plaintext_glider() {
+        let source = "!Name: Glider\n!\n.O\n..O\nOOO\n";
+        let pattern = Pattern::parse_plaintext(source).unwrap();
+
+        assert_eq!((pattern.width, pattern.height), (3, 3));
+        assert_eq!(pattern.alive, GLIDER);
+    }
+
+    #[test]
+    fn reject_unknown_characters() {
+        let err = Pattern::parse_plaintext(".O\n.X.").unwrap_err();
+
+        assert!(matches!(
+            err,
+            PatternError::UnexpectedCharacter {
+                line: 2,
+                character: 'X'
+            }
+        ));
+    }
+}
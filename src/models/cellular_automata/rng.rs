@@ -0,0 +1,24 @@
+//! Counter-based random numbers.
+//!
+//! Every draw is a pure function of the model seed and the position of the
+//! draw (step, cell and stream), so results do not depend on the order in
+//! which cells are visited.
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+fn splitmix64(mut z: u64) -> u64 {
+    z = z.wrapping_add(GOLDEN_GAMMA);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// A uniform sample in `[0, 1)`.
+pub fn uniform(seed: u64, step: u64, cell: u64, stream: u64) -> f64 {
+    let mut z = splitmix64(seed);
+    for counter in [step, cell, stream] {
+        z = splitmix64(z ^ counter);
+    }
+    // The top 53 bits fill an f64 mantissa exactly
+    (z >> 11) as f64 / (1_u64 << 53) as f64
+}
@@ -0,0 +1,434 @@
+//! Stepping of every lattice, with the same rules as the generated Python
+//! script (see [`render_ca`](crate::transformations::ca::render_ca)).
+//!
+//! Every random draw is a pure function of the seed, step, cell and target
+//! state, so each cell's next state depends only on its neighbourhood:
+//! cells can be updated in any order, or in parallel, and still reproduce
+//! the same run.
+
+use std::fmt;
+
+use crate::{models::evaluation::EvaluationError, Map};
+
+use super::{rng, CaModel, Grid, InitialConditionError, Lattice};
+
+#[derive(Debug)]
+pub enum SimulationError {
+    InitialCondition(InitialConditionError),
+    Evaluation(EvaluationError),
+    /// The grid has `cells` cells rather than its width times its height
+    /// times its depth.
+    WrongSize {
+        cells: usize,
+    },
+    /// The cell at `index` is in a state the model doesn't have.
+    UnknownState {
+        index: usize,
+        state: usize,
+    },
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InitialCondition(err) => err.fmt(f),
+            Self::Evaluation(err) => err.fmt(f),
+            Self::WrongSize { cells } => {
+                write!(
+                    f,
+                    "the grid has {cells} cells, which doesn't match its size"
+                )
+            }
+            Self::UnknownState { index, state } => {
+                write!(
+                    f,
+                    "cell {index} is in state {state}, which the model doesn't have"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+impl From<InitialConditionError> for SimulationError {
+    fn from(value: InitialConditionError) -> Self {
+        Self::InitialCondition(value)
+    }
+}
+
+impl From<EvaluationError> for SimulationError {
+    fn from(value: EvaluationError) -> Self {
+        Self::Evaluation(value)
+    }
+}
+
+/// States and rules of a model, looked up once per step rather than once
+/// per cell.
+struct Rules<'a> {
+    states: Vec<&'a str>,
+    /// The rule (argument name) moving cells into each state, by state index
+    rules: Vec<(usize, &'a str)>,
+}
+
+impl CaModel {
+    fn rules(&self) -> Rules<'_> {
+        let equations = self
+            .equations
+            .iter()
+            .filter_map(|eq| Some((eq.operates_on.as_deref()?, eq.argument.as_str())))
+            .collect::<Map<_, _>>();
+
+        let states = self.states();
+        let rules = states
+            .iter()
+            .enumerate()
+            .filter_map(|(index, state)| Some((index, *equations.get(state)?)))
+            .collect();
+
+        Rules { states, rules }
+    }
+
+    /// The state of the cell at `index` (see [`Grid::index_at`]) in
+    /// generation `step`, given the previous generation `grid`.
+    ///
+    /// Inside a rule:
+    ///
+    /// - each state's name is the number of the cell's neighbours in that
+    ///   state, the neighbourhood being given by the model's [`Lattice`];
+    /// - `is_<state>` is 1 when the cell itself is in that state, 0 otherwise;
+    /// - on linear lattices, `pattern` numbers the states of the left
+    ///   neighbour, the cell and the right neighbour as the digits of a
+    ///   number in base "number of states", like Wolfram's rule numbering.
+    ///
+    /// The value of a rule is the probability of moving into its state.
+    ///
+    /// Panics if the grid doesn't fit the model, which [`CaModel::step`]
+    /// checks.
+    ///
+    /// [`Lattice`]: super::Lattice
+    pub fn next_state(
+        &self,
+        grid: &Grid,
+        index: usize,
+        step: u64,
+    ) -> Result<usize, EvaluationError> {
+        self.next_state_with(&self.rules(), grid, index, step)
+    }
+
+    fn next_state_with(
+        &self,
+        rules: &Rules,
+        grid: &Grid,
+        index: usize,
+        step: u64,
+    ) -> Result<usize, EvaluationError> {
+        let current = grid.cells[index];
+        let (x, y, z) = (
+            index % grid.width,
+            index / grid.width % grid.height,
+            index / (grid.width * grid.height),
+        );
+        let neighbour = |(dx, dy, dz): (isize, isize, isize)| {
+            let wrap = |position: usize, offset: isize, size: usize| {
+                (position as isize + offset).rem_euclid(size as isize) as usize
+            };
+            grid.get_at(
+                wrap(x, dx, grid.width),
+                wrap(y, dy, grid.height),
+                wrap(z, dz, grid.depth),
+            )
+        };
+
+        let mut counts = vec![0.0; rules.states.len()];
+        for &offset in self.metadata.lattice.neighbours(y) {
+            counts[neighbour(offset)] += 1.0;
+        }
+
+        let pattern = (self.metadata.lattice == Lattice::Linear).then(|| {
+            let states = rules.states.len();
+            let (left, right) = (neighbour((-1, 0, 0)), neighbour((1, 0, 0)));
+            ((left * states + current) * states + right) as f64
+        });
+
+        let state_index = |name: &str| rules.states.iter().position(|state| *state == name);
+        let bindings = |name: &str| {
+            if let Some(index) = state_index(name) {
+                return Some(counts[index]);
+            }
+            if name == "pattern" {
+                return pattern;
+            }
+            let index = state_index(name.strip_prefix("is_")?)?;
+            Some(if index == current { 1.0 } else { 0.0 })
+        };
+
+        for &(state, rule) in &rules.rules {
+            let probability = self.evaluate(rule, &bindings)?;
+            if rng::uniform(self.metadata.seed, step, index as u64, state as u64) < probability {
+                return Ok(state);
+            }
+        }
+
+        Ok(current)
+    }
+
+    /// Computes generation `step` from the previous generation `grid`.
+    pub fn step(&self, grid: &Grid, step: u64) -> Result<Grid, SimulationError> {
+        let rules = self.rules();
+        let size = grid
+            .width
+            .checked_mul(grid.height)
+            .and_then(|area| area.checked_mul(grid.depth));
+        if size != Some(grid.cells.len()) {
+            return Err(SimulationError::WrongSize {
+                cells: grid.cells.len(),
+            });
+        }
+        let unknown = grid
+            .cells
+            .iter()
+            .position(|&state| state >= rules.states.len());
+        if let Some(index) = unknown {
+            return Err(SimulationError::UnknownState {
+                index,
+                state: grid.cells[index],
+            });
+        }
+        let cells = (0..grid.cells.len())
+            .map(|index| self.next_state_with(&rules, grid, index, step))
+            .collect::<Result<_, _>>()?;
+        Ok(Grid {
+            cells,
+            ..grid.clone()
+        })
+    }
+
+    /// The initial grid followed by `steps` generations.
+    pub fn run(&self, steps: u64) -> Result<Vec<Grid>, SimulationError> {
+        let mut grids = vec![self.initial_grid()?];
+        for step in 1..=steps {
+            let next = self.step(grids.last().unwrap(), step)?;
+            grids.push(next);
+        }
+        Ok(grids)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::cellular_automata::{InitialCondition, Metadata, PatternFormat};
+    use crate::models::{Argument, Component};
+    use crate::Equation;
+
+    use super::*;
+
+    fn value(model: &mut CaModel, name: &str, value: f64) {
+        model.insert_argument(Argument::Value {
+            name: name.into(),
+            value,
+        });
+    }
+
+    fn product(model: &mut CaModel, name: &str, factors: &[&str]) {
+        model.insert_argument(Argument::Composite {
+            name: name.into(),
+            operation: "*".into(),
+            style: Default::default(),
+            composition: factors
+                .iter()
+                .map(|factor| Component {
+                    name: (*factor).into(),
+                    contribution: '+',
+                })
+                .collect(),
+        });
+    }
+
+    fn rule(model: &mut CaModel, state: &str, argument: &str) {
+        model.insert_equation(Equation {
+            name: format!("to_{state}"),
+            operates_on: Some(state.into()),
+            argument: argument.into(),
+            contribution: '+',
+        });
+    }
+
+    /// Trees next to fire ignite with probability `ignition`, fires always
+    /// burn out and no tree grows back.
+    fn forest_fire(initial_condition: InitialCondition, ignition: f64) -> CaModel {
+        let mut model = CaModel::new(
+            "Forest fire".into(),
+            Metadata {
+                width: 200,
+                height: 200,
+                seed: 2023,
+                initial_condition,
+                ..Default::default()
+            },
+        );
+
+        for (state, density) in [("empty", 0.35), ("fire", 0.05), ("tree", 0.6)] {
+            value(&mut model, state, density);
+        }
+        value(&mut model, "is_tree", 0.0);
+        value(&mut model, "is_fire", 0.0);
+        value(&mut model, "zero", 0.0);
+        value(&mut model, "ignition", ignition);
+
+        model.insert_argument(Argument::Composite {
+            name: "fire_nearby".into(),
+            operation: ">".into(),
+            style: Default::default(),
+            composition: vec![
+                Component {
+                    name: "fire".into(),
+                    contribution: '+',
+                },
+                Component {
+                    name: "zero".into(),
+                    contribution: '+',
+                },
+            ],
+        });
+        product(
+            &mut model,
+            "ignites",
+            &["is_tree", "fire_nearby", "ignition"],
+        );
+
+        rule(&mut model, "empty", "is_fire");
+        rule(&mut model, "fire", "ignites");
+        rule(&mut model, "tree", "zero");
+
+        model
+    }
+
+    #[test]
+    fn ignition_rate_matches_probability() {
+        // Every other column is on fire, so every tree has burning neighbours
+        let source = "x = 200, y = 200\n".to_owned() + &("bo".repeat(100) + "$").repeat(200) + "!";
+        let model = forest_fire(
+            InitialCondition::Pattern {
+                background: "tree".into(),
+                alive: "fire".into(),
+                format: PatternFormat::Rle,
+                source,
+                x: 0,
+                y: 0,
+            },
+            0.3,
+        );
+        let (fire, tree) = (
+            model.state_index("fire").unwrap(),
+            model.state_index("tree").unwrap(),
+        );
+
+        let grid = model.initial_grid().unwrap();
+        let trees = grid.count(tree) as f64;
+        let next = model.step(&grid, 1).unwrap();
+
+        // Burning cells all burned out, so any fire is a newly ignited tree
+        let rate = next.count(fire) as f64 / trees;
+        let sigma = (0.3 * 0.7 / trees).sqrt();
+        assert!((rate - 0.3).abs() < 4.0 * sigma, "ignition rate {rate}");
+        assert_eq!(next.count(model.state_index("empty").unwrap()), 20_000);
+    }
+
+    #[test]
+    fn trees_far_from_fire_never_ignite() {
+        let model = forest_fire(
+            InitialCondition::Uniform {
+                state: "tree".into(),
+            },
+            1.0,
+        );
+        let grid = model.initial_grid().unwrap();
+
+        assert_eq!(model.step(&grid, 1).unwrap(), grid);
+    }
+
+    #[test]
+    fn grids_must_fit_the_model() {
+        let model = forest_fire(InitialCondition::default(), 0.5);
+        let grid = Grid::new(3, 2, 0);
+
+        let mut short = grid.clone();
+        short.cells.pop();
+        assert!(matches!(
+            model.step(&short, 1),
+            Err(SimulationError::WrongSize { cells: 5 })
+        ));
+
+        let mut unknown = grid;
+        unknown.cells[4] = 3;
+        assert!(matches!(
+            model.step(&unknown, 1),
+            Err(SimulationError::UnknownState { index: 4, state: 3 })
+        ));
+    }
+
+    #[test]
+    fn runs_are_reproducible() {
+        let model = forest_fire(InitialCondition::default(), 0.5);
+
+        let first = model.run(3).unwrap();
+        assert_eq!(first, model.run(3).unwrap());
+
+        let mut reseeded = model.clone();
+        reseeded.metadata.seed += 1;
+        assert_ne!(first, reseeded.run(3).unwrap());
+    }
+
+    #[test]
+    fn cells_only_depend_on_their_neighbourhood() {
+        let model = forest_fire(InitialCondition::default(), 0.5);
+        let grid = model.initial_grid().unwrap();
+
+        let mut perturbed = grid.clone();
+        perturbed.set(150, 150, model.state_index("fire").unwrap());
+
+        let next = model.step(&grid, 7).unwrap();
+        for (x, y) in [(10, 10), (100, 20), (42, 199)] {
+            let index = grid.index(x, y);
+            assert_eq!(
+                model.next_state(&perturbed, index, 7).unwrap(),
+                next.cells[index]
+            );
+        }
+    }
+
+    #[test]
+    fn uniform_transition_rate() {
+        // Empty cells sprout with probability `growth` regardless of neighbours
+        let mut model = CaModel::new(
+            "Growth".into(),
+            Metadata {
+                width: 100,
+                height: 100,
+                seed: 99,
+                initial_condition: InitialCondition::Uniform {
+                    state: "empty".into(),
+                },
+                ..Default::default()
+            },
+        );
+        value(&mut model, "empty", 0.0);
+        value(&mut model, "tree", 0.0);
+        value(&mut model, "growth", 0.05);
+        value(&mut model, "never", 0.0);
+        rule(&mut model, "empty", "never");
+        rule(&mut model, "tree", "growth");
+
+        let grids = model.run(10).unwrap();
+        let tree = model.state_index("tree").unwrap();
+
+        // Each step, 5% of the remaining empty cells become trees
+        for (before, after) in grids.iter().zip(&grids[1..]) {
+            let empty = (10_000 - before.count(tree)) as f64;
+            let rate = (after.count(tree) - before.count(tree)) as f64 / empty;
+            let sigma = (0.05 * 0.95 / empty).sqrt();
+            assert!((rate - 0.05).abs() < 4.0 * sigma, "growth rate {rate}");
+        }
+    }
+}
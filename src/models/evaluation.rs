@@ -0,0 +1,318 @@
+//! Numerical evaluation of arguments, following the semantics of the
+//! generated Python code: infixed compositions are folded left to right,
+//! prefixed ones are function calls and comparisons yield `1.0` or `0.0`.
+
+use std::fmt;
+
+use super::{Argument, CompositionStyle, CoreModel};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluationError {
+    UnknownArgument(String),
+    UnknownOperation(String),
+    WrongArity { operation: String, arguments: usize },
+    Cycle(String),
+}
+
+impl fmt::Display for EvaluationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownArgument(name) => write!(f, "unknown argument `{name}`"),
+            Self::UnknownOperation(operation) => write!(f, "unknown operation `{operation}`"),
+            Self::WrongArity {
+                operation,
+                arguments,
+            } => write!(
+                f,
+                "`{operation}` cannot be applied to {arguments} arguments"
+            ),
+            Self::Cycle(name) => write!(f, "`{name}` depends on itself"),
+        }
+    }
+}
+
+impl std::error::Error for EvaluationError {}
+
+impl CoreModel {
+    /// Evaluates the argument called `name`.
+    ///
+    /// Names are looked up in `bindings` before the model, so that the
+    /// current value of a population (or any other name) can be supplied
+    /// without modifying the model.
+    pub fn evaluate(
+        &self,
+        name: &str,
+        bindings: &impl Fn(&str) -> Option<f64>,
+    ) -> Result<f64, EvaluationError> {
+        self.evaluate_inner(name, bindings, &mut Vec::new())
+    }
+
+    fn evaluate_inner<'a>(
+        &'a self,
+        name: &'a str,
+        bindings: &impl Fn(&str) -> Option<f64>,
+        stack: &mut Vec<&'a str>,
+    ) -> Result<f64, EvaluationError> {
+        if let Some(value) = bindings(name) {
+            return Ok(value);
+        }
+
+        let argument = self
+            .arguments
+            .get(name)
+            .ok_or_else(|| EvaluationError::UnknownArgument(name.to_owned()))?;
+
+        match argument {
+            Argument::Value { value, .. } => Ok(*value),
+            Argument::Composite {
+                operation,
+                style,
+                composition,
+                ..
+            } => {
+                if stack.contains(&name) {
+                    return Err(EvaluationError::Cycle(name.to_owned()));
+                }
+                stack.push(name);
+
+                let values = composition
+                    .iter()
+                    .map(|component| {
+                        let value = self.evaluate_inner(&component.name, bindings, stack)?;
+                        Ok(if component.contribution == '-' {
+                            -value
+                        } else {
+                            value
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                stack.pop();
+                apply(operation, *style, &values)
+            }
+        }
+    }
+}
+
+fn apply(operation: &str, style: CompositionStyle, values: &[f64]) -> Result<f64, EvaluationError> {
+    let wrong_arity = || EvaluationError::WrongArity {
+        operation: operation.to_owned(),
+        arguments: values.len(),
+    };
+    let truth = |condition: bool| if condition { 1.0 } else { 0.0 };
+
+    let Some((&first, rest)) = values.split_first() else {
+        return Err(wrong_arity());
+    };
+
+    match style {
+        CompositionStyle::Infixed => {
+            let fold =
+                |f: fn(f64, f64) -> f64| rest.iter().fold(first, |acc, &value| f(acc, value));
+            // Python chains comparisons: `a < b < c` means `a < b and b < c`
+            let chain = |f: fn(&f64, &f64) -> bool| {
+                truth(values.windows(2).all(|pair| f(&pair[0], &pair[1])))
+            };
+
+            Ok(match operation {
+                "+" => fold(|a, b| a + b),
+                "-" => fold(|a, b| a - b),
+                "*" => fold(|a, b| a * b),
+                "/" => fold(|a, b| a / b),
+                // Floored, taking the sign of the divisor like Python's
+                "%" => fold(|a, b| a - b * (a / b).floor()),
+                // Exponentiation is right associative
+                "**" | "^" => values
+                    .iter()
+                    .rev()
+                    .copied()
+                    .reduce(|b, a| a.powf(b))
+                    .unwrap(),
+                "==" => chain(f64::eq),
+                "!=" => chain(f64::ne),
+                "<" => chain(f64::lt),
+                "<=" => chain(f64::le),
+                ">" => chain(f64::gt),
+                ">=" => chain(f64::ge),
+                _ => return Err(EvaluationError::UnknownOperation(operation.to_owned())),
+            })
+        }
+        CompositionStyle::Prefixed => {
+            let unary = |f: fn(f64) -> f64| match rest {
+                [] => Ok(f(first)),
+                _ => Err(wrong_arity()),
+            };
+
+            match operation {
+                "min" => Ok(values.iter().copied().fold(f64::INFINITY, f64::min)),
+                "max" => Ok(values.iter().copied().fold(f64::NEG_INFINITY, f64::max)),
+                "pow" => match rest {
+                    [exponent] => Ok(first.powf(*exponent)),
+                    _ => Err(wrong_arity()),
+                },
+                "abs" => unary(f64::abs),
+                "exp" => unary(f64::exp),
+                "log" => unary(f64::ln),
+                "sqrt" => unary(f64::sqrt),
+                "sin" => unary(f64::sin),
+                "cos" => unary(f64::cos),
+                "tan" => unary(f64::tan),
+                "floor" => unary(f64::floor),
+                "ceil" => unary(f64::ceil),
+                _ => Err(EvaluationError::UnknownOperation(operation.to_owned())),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::Component;
+
+    use super::*;
+
+    fn model() -> CoreModel {
+        let mut model = CoreModel::new();
+        for (name, value) in [("a", 2.0), ("b", 3.0), ("c", 4.0)] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+        }
+        model
+    }
+
+    fn composite(
+        name: &str,
+        operation: &str,
+        style: CompositionStyle,
+        composition: &[(&str, char)],
+    ) -> Argument {
+        Argument::Composite {
+            name: name.into(),
+            operation: operation.into(),
+            style,
+            composition: composition
+                .iter()
+                .map(|&(name, contribution)| Component {
+                    name: name.into(),
+                    contribution,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn infixed_arithmetic_and_comparisons() {
+        let mut model = model();
+        let infixed = CompositionStyle::Infixed;
+        model.insert_argument(composite(
+            "a-b-c",
+            "-",
+            infixed,
+            &[("a", '+'), ("b", '+'), ("c", '+')],
+        ));
+        model.insert_argument(composite(
+            "a^b^a",
+            "^",
+            infixed,
+            &[("a", '+'), ("b", '+'), ("a", '+')],
+        ));
+        model.insert_argument(composite(
+            "a<b<c",
+            "<",
+            infixed,
+            &[("a", '+'), ("b", '+'), ("c", '+')],
+        ));
+        model.insert_argument(composite(
+            "a+-(a-b-c)",
+            "+",
+            infixed,
+            &[("a", '+'), ("a-b-c", '-')],
+        ));
+
+        let none = |_: &str| None;
+        assert_eq!(model.evaluate("a-b-c", &none), Ok(-5.0));
+        assert_eq!(model.evaluate("a^b^a", &none), Ok(512.0));
+        assert_eq!(model.evaluate("a<b<c", &none), Ok(1.0));
+        assert_eq!(model.evaluate("a+-(a-b-c)", &none), Ok(7.0));
+    }
+
+    #[test]
+    fn modulo_takes_the_sign_of_the_divisor() {
+        let mut model = model();
+        let infixed = CompositionStyle::Infixed;
+        model.insert_argument(composite("a%-b", "%", infixed, &[("a", '+'), ("b", '-')]));
+        model.insert_argument(composite("-a%b", "%", infixed, &[("a", '-'), ("b", '+')]));
+
+        let none = |_: &str| None;
+        assert_eq!(model.evaluate("a%-b", &none), Ok(-1.0));
+        assert_eq!(model.evaluate("-a%b", &none), Ok(1.0));
+    }
+
+    #[test]
+    fn prefixed_functions() {
+        let mut model = model();
+        let prefixed = CompositionStyle::Prefixed;
+        model.insert_argument(composite(
+            "max",
+            "max",
+            prefixed,
+            &[("a", '+'), ("c", '+'), ("b", '+')],
+        ));
+        model.insert_argument(composite("pow", "pow", prefixed, &[("a", '+'), ("b", '+')]));
+        model.insert_argument(composite(
+            "sqrt",
+            "sqrt",
+            prefixed,
+            &[("a", '+'), ("b", '+')],
+        ));
+
+        let none = |_: &str| None;
+        assert_eq!(model.evaluate("max", &none), Ok(4.0));
+        assert_eq!(model.evaluate("pow", &none), Ok(8.0));
+        assert_eq!(
+            model.evaluate("sqrt", &none),
+            Err(EvaluationError::WrongArity {
+                operation: "sqrt".into(),
+                arguments: 2
+            })
+        );
+    }
+
+    #[test]
+    fn bindings_take_precedence() {
+        let mut model = model();
+        model.insert_argument(composite(
+            "a*b",
+            "*",
+            CompositionStyle::Infixed,
+            &[("a", '+'), ("b", '+')],
+        ));
+
+        let bindings = |name: &str| (name == "a").then_some(10.0);
+        assert_eq!(model.evaluate("a*b", &bindings), Ok(30.0));
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let mut model = model();
+        model.insert_argument(composite(
+            "x",
+            "+",
+            CompositionStyle::Infixed,
+            &[("a", '+'), ("y", '+')],
+        ));
+        model.insert_argument(composite(
+            "y",
+            "+",
+            CompositionStyle::Infixed,
+            &[("x", '+')],
+        ));
+
+        assert_eq!(
+            model.evaluate("x", &|_| None),
+            Err(EvaluationError::Cycle("x".into()))
+        );
+    }
+}
@@ -1,15 +1,35 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::CoreModel;
+use crate::Map;
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize, JsonSchema)]
+#[schemars(rename = "OdeMetadata")]
 pub struct Metadata {
     pub start_time: f64,
     pub delta_time: f64,
     pub end_time: f64,
+    /// Fields this version doesn't know about, kept so that saving a model
+    /// from a newer editor doesn't lose them.
+    #[serde(flatten)]
+    pub unknown_fields: Map<String, serde_json::Value>,
+}
+
+impl Metadata {
+    /// Times for models imported from formats which may not give them:
+    /// from 0 to 10 in steps of 0.1.
+    pub fn import_defaults() -> Self {
+        Self {
+            start_time: 0.0,
+            delta_time: 0.1,
+            end_time: 10.0,
+            unknown_fields: Map::new(),
+        }
+    }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize)]
 pub struct OdeModel {
     pub name: String,
     pub metadata: Metadata,
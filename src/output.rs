@@ -0,0 +1,186 @@
+//! Pictures of cellular automata runs: still frames in the Netpbm formats
+//! and animated GIFs, neither of which need anything outside of Rust.
+
+use std::{fmt, io, str::FromStr};
+
+use crate::{
+    models::cellular_automata::{CaModel, Grid},
+    Map,
+};
+
+pub mod animation;
+pub mod netpbm;
+
+pub use netpbm::ImageFormat;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Rgb(pub u8, pub u8, pub u8);
+
+impl Rgb {
+    /// Perceived brightness, as used for grayscale output.
+    pub fn luma(self) -> u8 {
+        let Rgb(r, g, b) = self;
+        ((299 * r as u32 + 587 * g as u32 + 114 * b as u32) / 1000) as u8
+    }
+}
+
+impl FromStr for Rgb {
+    type Err = OutputError;
+
+    /// Parses `#rrggbb` hex colours.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || OutputError::InvalidColor(s.to_owned());
+        let hex = s.strip_prefix('#').ok_or_else(invalid)?;
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).map_err(|_| invalid());
+        Ok(Rgb(channel(0)?, channel(2)?, channel(4)?))
+    }
+}
+
+impl fmt::Display for Rgb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{:02x}{:02x}{:02x}", self.0, self.1, self.2)
+    }
+}
+
+/// Colours for each state, indexed like the cells of a [`Grid`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Palette {
+    pub colors: Vec<Rgb>,
+}
+
+impl Palette {
+    /// Evenly spaced shades from black (first state) to white (last state).
+    pub fn grayscale(states: usize) -> Self {
+        let last = states.saturating_sub(1).max(1);
+        let colors = (0..states)
+            .map(|state| {
+                let level = (state * 255 / last) as u8;
+                Rgb(level, level, level)
+            })
+            .collect();
+        Self { colors }
+    }
+
+    /// Grayscale palette for the model's states, with the colours in
+    /// `colors` (keyed by state name) taking precedence.
+    pub fn for_model(model: &CaModel, colors: &Map<String, Rgb>) -> Result<Self, OutputError> {
+        let mut palette = Self::grayscale(model.states().len());
+        for (state, color) in colors {
+            let index = model
+                .state_index(state)
+                .ok_or_else(|| OutputError::UnknownState(state.clone()))?;
+            palette.colors[index] = *color;
+        }
+        Ok(palette)
+    }
+
+    /// States outside of the palette are drawn black.
+    pub fn color(&self, state: usize) -> Rgb {
+        self.colors.get(state).copied().unwrap_or_default()
+    }
+}
+
+/// Draws grids with a palette, each cell becoming a `scale`×`scale` square
+/// of pixels.
+#[derive(Debug, Clone)]
+pub struct Renderer {
+    pub palette: Palette,
+    pub scale: usize,
+}
+
+impl Renderer {
+    pub fn new(palette: Palette) -> Self {
+        Self { palette, scale: 1 }
+    }
+
+    pub fn with_scale(mut self, scale: usize) -> Self {
+        self.scale = scale.max(1);
+        self
+    }
+
+    pub fn dimensions(&self, grid: &Grid) -> (usize, usize) {
+        (grid.width * self.scale, grid.height * self.scale)
+    }
+
+    /// The state behind every pixel, row by row.
+    fn pixels<'a>(&'a self, grid: &'a Grid) -> impl Iterator<Item = usize> + 'a {
+        (0..grid.height * self.scale).flat_map(move |py| {
+            (0..grid.width * self.scale).map(move |px| grid.get(px / self.scale, py / self.scale))
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum OutputError {
+    Io(io::Error),
+    Gif(gif::EncodingError),
+    UnknownState(String),
+    InvalidColor(String),
+    NoFrames,
+    TooLarge { width: usize, height: usize },
+    MismatchedFrame { width: usize, height: usize },
+    TooManyStates(usize),
+}
+
+impl fmt::Display for OutputError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => err.fmt(f),
+            Self::Gif(err) => err.fmt(f),
+            Self::UnknownState(state) => write!(f, "`{state}` is not a state of this model"),
+            Self::InvalidColor(color) => write!(f, "`{color}` is not a `#rrggbb` colour"),
+            Self::NoFrames => write!(f, "there are no frames to render"),
+            Self::TooLarge { width, height } => {
+                write!(f, "a {width}x{height} image is too large for this format")
+            }
+            Self::MismatchedFrame { width, height } => {
+                write!(f, "a {width}x{height} frame does not match the first one")
+            }
+            Self::TooManyStates(states) => {
+                write!(f, "{states} states do not fit in a 256 colour palette")
+            }
+        }
+    }
+}
+
+impl std::error::Error for OutputError {}
+
+impl From<io::Error> for OutputError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+impl From<gif::EncodingError> for OutputError {
+    fn from(value: gif::EncodingError) -> Self {
+        Self::Gif(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_display_colors() {
+        let color = "#ff8000".parse::<Rgb>().unwrap();
+
+        assert_eq!(color, Rgb(255, 128, 0));
+        assert_eq!(color.to_string(), "#ff8000");
+        assert!("ff8000".parse::<Rgb>().is_err());
+        assert!("#ff80".parse::<Rgb>().is_err());
+    }
+
+    #[test]
+    fn grayscale_spans_black_to_white() {
+        let palette = Palette::grayscale(3);
+
+        assert_eq!(
+            palette.colors,
+            [Rgb(0, 0, 0), Rgb(127, 127, 127), Rgb(255, 255, 255)]
+        );
+    }
+}
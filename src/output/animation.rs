@@ -0,0 +1,163 @@
+use std::{io::Write, time::Duration};
+
+use gif::{Encoder, Frame, Repeat};
+
+use crate::models::cellular_automata::Grid;
+
+use super::{OutputError, Renderer, Rgb};
+
+impl Renderer {
+    /// Assembles the grids into a looping GIF, showing each one for `delay`.
+    ///
+    /// The palette becomes the GIF's global colour table, so every pixel is
+    /// stored as its state index and no colour quantization takes place.
+    /// States outside of the palette are drawn black, as in
+    /// [`Palette::color`](crate::output::Palette::color).
+    pub fn write_gif<'a>(
+        &self,
+        frames: impl IntoIterator<Item = &'a Grid>,
+        delay: Duration,
+        writer: impl Write,
+    ) -> Result<(), OutputError> {
+        let mut frames = frames.into_iter().peekable();
+        let (width, height) = self.dimensions(frames.peek().ok_or(OutputError::NoFrames)?);
+        let too_large = || OutputError::TooLarge { width, height };
+        let (gif_width, gif_height) = (
+            u16::try_from(width).map_err(|_| too_large())?,
+            u16::try_from(height).map_err(|_| too_large())?,
+        );
+
+        if self.palette.colors.len() > 256 {
+            return Err(OutputError::TooManyStates(self.palette.colors.len()));
+        }
+        let mut colors = self.palette.colors.clone();
+        let black = match colors.iter().position(|&color| color == Rgb::default()) {
+            Some(index) => Some(index),
+            None if colors.len() < 256 => {
+                colors.push(Rgb::default());
+                Some(colors.len() - 1)
+            }
+            None => None,
+        };
+        let color_table: Vec<u8> = colors
+            .iter()
+            .flat_map(|color| [color.0, color.1, color.2])
+            .collect();
+        let states = self.palette.colors.len();
+        let index = |state: usize| match (state < states, black) {
+            (true, _) => Ok(state as u8),
+            (false, Some(black)) => Ok(black as u8),
+            (false, None) => Err(OutputError::TooManyStates(state + 1)),
+        };
+
+        let mut encoder = Encoder::new(writer, gif_width, gif_height, &color_table)?;
+        encoder.set_repeat(Repeat::Infinite)?;
+
+        // GIF delays are counted in hundredths of a second
+        let delay = u16::try_from(delay.as_millis() / 10).unwrap_or(u16::MAX);
+
+        for grid in frames {
+            let (frame_width, frame_height) = self.dimensions(grid);
+            if (frame_width, frame_height) != (width, height) {
+                return Err(OutputError::MismatchedFrame {
+                    width: frame_width,
+                    height: frame_height,
+                });
+            }
+            let pixels = self
+                .pixels(grid)
+                .map(index)
+                .collect::<Result<Vec<u8>, _>>()?;
+            let mut frame = Frame::from_indexed_pixels(gif_width, gif_height, pixels, None);
+            frame.delay = delay;
+            encoder.write_frame(&frame)?;
+        }
+
+        encoder.into_inner()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::Palette;
+
+    use super::*;
+
+    #[test]
+    fn gif_frames_decode_to_states() {
+        let palette = Palette {
+            colors: vec![Rgb(0, 0, 0), Rgb(0, 255, 0)],
+        };
+        let renderer = Renderer::new(palette);
+
+        let mut blinker = Grid::new(3, 3, 0);
+        for x in 0..3 {
+            blinker.set(x, 1, 1);
+        }
+        let mut blinked = Grid::new(3, 3, 0);
+        for y in 0..3 {
+            blinked.set(1, y, 1);
+        }
+
+        let mut animation = Vec::new();
+        renderer
+            .write_gif(
+                [&blinker, &blinked, &blinker],
+                Duration::from_millis(250),
+                &mut animation,
+            )
+            .unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(animation.as_slice()).unwrap();
+
+        assert_eq!(decoder.global_palette().unwrap(), [0, 0, 0, 0, 255, 0]);
+
+        let mut decoded = Vec::new();
+        while let Some(frame) = decoder.read_next_frame().unwrap() {
+            assert_eq!(frame.delay, 25);
+            decoded.push(frame.buffer.to_vec());
+        }
+
+        let expected: Vec<Vec<u8>> = [&blinker, &blinked, &blinker]
+            .iter()
+            .map(|grid| grid.cells.iter().map(|&state| state as u8).collect())
+            .collect();
+        assert_eq!(decoded, expected);
+    }
+
+    #[test]
+    fn states_outside_of_the_palette_are_black() {
+        let palette = Palette {
+            colors: vec![Rgb(255, 255, 255), Rgb(0, 255, 0)],
+        };
+        let renderer = Renderer::new(palette);
+
+        let mut grid = Grid::new(2, 1, 0);
+        grid.set(1, 0, 5);
+
+        let mut animation = Vec::new();
+        renderer
+            .write_gif([&grid], Duration::ZERO, &mut animation)
+            .unwrap();
+
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::RGBA);
+        let mut decoder = options.read_info(animation.as_slice()).unwrap();
+        let frame = decoder.read_next_frame().unwrap().unwrap();
+
+        assert_eq!(&*frame.buffer, [255, 255, 255, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn empty_animation() {
+        let renderer = Renderer::new(Palette::grayscale(2));
+
+        assert!(matches!(
+            renderer.write_gif([], Duration::ZERO, Vec::new()),
+            Err(OutputError::NoFrames)
+        ));
+    }
+}
@@ -0,0 +1,135 @@
+use std::{
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::models::cellular_automata::Grid;
+
+use super::{OutputError, Renderer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary grayscale (`P5`), using the luma of each palette colour.
+    Pgm,
+    /// Binary colour (`P6`).
+    Ppm,
+}
+
+impl ImageFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Pgm => "pgm",
+            Self::Ppm => "ppm",
+        }
+    }
+}
+
+impl Renderer {
+    pub fn write_pgm(&self, grid: &Grid, mut writer: impl Write) -> io::Result<()> {
+        let (width, height) = self.dimensions(grid);
+        write!(writer, "P5\n{width} {height}\n255\n")?;
+
+        let bytes: Vec<u8> = self
+            .pixels(grid)
+            .map(|state| self.palette.color(state).luma())
+            .collect();
+        writer.write_all(&bytes)
+    }
+
+    pub fn write_ppm(&self, grid: &Grid, mut writer: impl Write) -> io::Result<()> {
+        let (width, height) = self.dimensions(grid);
+        write!(writer, "P6\n{width} {height}\n255\n")?;
+
+        let bytes: Vec<u8> = self
+            .pixels(grid)
+            .flat_map(|state| {
+                let color = self.palette.color(state);
+                [color.0, color.1, color.2]
+            })
+            .collect();
+        writer.write_all(&bytes)
+    }
+
+    pub fn write_image(
+        &self,
+        grid: &Grid,
+        format: ImageFormat,
+        writer: impl Write,
+    ) -> io::Result<()> {
+        match format {
+            ImageFormat::Pgm => self.write_pgm(grid, writer),
+            ImageFormat::Ppm => self.write_ppm(grid, writer),
+        }
+    }
+
+    /// Writes each grid to `directory` as `frame-0000.ppm`, `frame-0001.ppm`
+    /// and so on, returning the paths that were written.
+    pub fn write_frames<'a>(
+        &self,
+        frames: impl IntoIterator<Item = &'a Grid>,
+        directory: impl AsRef<Path>,
+        format: ImageFormat,
+    ) -> Result<Vec<PathBuf>, OutputError> {
+        let directory = directory.as_ref();
+        std::fs::create_dir_all(directory)?;
+
+        frames
+            .into_iter()
+            .enumerate()
+            .map(|(index, grid)| {
+                let path = directory.join(format!("frame-{index:04}.{}", format.extension()));
+                let mut writer = BufWriter::new(std::fs::File::create(&path)?);
+                self.write_image(grid, format, &mut writer)?;
+                writer.flush()?;
+                Ok(path)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::output::{Palette, Rgb};
+
+    use super::*;
+
+    fn checkerboard() -> Grid {
+        let mut grid = Grid::new(2, 2, 0);
+        grid.set(1, 0, 1);
+        grid.set(0, 1, 1);
+        grid
+    }
+
+    #[test]
+    fn pgm() {
+        let renderer = Renderer::new(Palette::grayscale(2));
+        let mut image = Vec::new();
+        renderer.write_pgm(&checkerboard(), &mut image).unwrap();
+
+        assert_eq!(image, b"P5\n2 2\n255\n\x00\xff\xff\x00");
+    }
+
+    #[test]
+    fn scaled_ppm() {
+        let palette = Palette {
+            colors: vec![Rgb(0, 0, 255), Rgb(255, 0, 0)],
+        };
+        let renderer = Renderer::new(palette).with_scale(2);
+        let mut image = Vec::new();
+        renderer.write_ppm(&checkerboard(), &mut image).unwrap();
+
+        let (header, pixels) = image.split_at(b"P6\n4 4\n255\n".len());
+        assert_eq!(header, b"P6\n4 4\n255\n");
+
+        let pixels: Vec<&[u8]> = pixels.chunks(3).collect();
+        assert_eq!(pixels.len(), 16);
+        assert_eq!(
+            pixels[0..4],
+            [[0, 0, 255], [0, 0, 255], [255, 0, 0], [255, 0, 0]]
+        );
+        assert_eq!(
+            pixels[12..16],
+            [[255, 0, 0], [255, 0, 0], [0, 0, 255], [0, 0, 255]]
+        );
+    }
+}
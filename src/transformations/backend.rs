@@ -0,0 +1,417 @@
+use std::fmt;
+use std::ops::Deref;
+use std::path::PathBuf;
+
+use minijinja::{context, value::Value};
+use serde::Serialize;
+
+use super::templates::{TemplateError, Templates, BUILTIN_TEMPLATES};
+use crate::models::{cellular_automata::CaModel, ode::OdeModel, Argument, CoreModel, Equation};
+use crate::Map;
+
+/// A target language or format for ODE models.
+///
+/// Besides the built-in ones, backends can be added to a [`Registry`] by
+/// other crates:
+///
+/// ```
+/// use odeir::transformations::{Backend, Registry, RenderContext, TemplateError};
+///
+/// struct Names;
+///
+/// impl Backend for Names {
+///     fn name(&self) -> &str {
+///         "names"
+///     }
+///
+///     fn file_extension(&self) -> &str {
+///         "txt"
+///     }
+///
+///     fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+///         let names: Vec<&str> = context.populations.iter().map(|arg| arg.name()).collect();
+///         Ok(names.join("\n"))
+///     }
+/// }
+///
+/// let mut registry = Registry::default();
+/// registry.register(Names);
+/// assert!(registry.names().any(|name| name == "names"));
+/// ```
+pub trait Backend: Send + Sync {
+    /// The name it's looked up by, like `python`.
+    fn name(&self) -> &str;
+
+    /// The extension of the file it renders, like `py`.
+    fn file_extension(&self) -> &str;
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError>;
+
+    /// The other files the rendered one needs next to it, as their names
+    /// and contents. Most backends render everything into one file.
+    fn render_support_files(
+        &self,
+        context: &RenderContext,
+    ) -> Result<Vec<(String, String)>, TemplateError> {
+        let _ = context;
+        Ok(Vec::new())
+    }
+}
+
+/// The models templates are rendered from.
+pub trait RenderableModel: Deref<Target = CoreModel> + Serialize {
+    /// The names of the files defining the model's extension functions.
+    fn extension_files(&self) -> &[String];
+}
+
+impl RenderableModel for OdeModel {
+    fn extension_files(&self) -> &[String] {
+        &self.extension_files
+    }
+}
+
+impl RenderableModel for CaModel {
+    fn extension_files(&self) -> &[String] {
+        &self.extension_files
+    }
+}
+
+/// What every backend renders from, gathered once from an [`OdeModel`] (or
+/// a [`CaModel`], for [`render_ca`](super::ca::render_ca)).
+#[derive(Debug, Clone)]
+pub struct RenderContext<'a, M = OdeModel> {
+    pub model: &'a M,
+    pub populations: Vec<&'a Argument>,
+    pub constants: Vec<&'a Argument>,
+    /// The equation of each population.
+    pub equations: Map<&'a str, &'a Equation>,
+    /// The model's extension files which could be read, as their names and
+    /// contents.
+    pub extensions: Vec<(&'a str, String)>,
+    /// The templates of the backends, which are the built-in ones unless
+    /// [`with_templates`](Self::with_templates) is used.
+    pub templates: &'a Templates,
+}
+
+impl<'a, M: RenderableModel> RenderContext<'a, M> {
+    /// Extension files are looked up among `extension_lookup_paths` by
+    /// their names, or else relative to the working directory.
+    pub fn new(model: &'a M, extension_lookup_paths: &[&PathBuf]) -> Self {
+        let equations = model
+            .equations
+            .iter()
+            .filter_map(|eq| Some((eq.operates_on.as_deref()?, eq)))
+            .collect();
+
+        let extensions = model
+            .extension_files()
+            .iter()
+            .filter_map(|filename| {
+                let filename_as_path = PathBuf::from(filename);
+                let filename_as_path = &filename_as_path;
+                let full_path = extension_lookup_paths
+                    .iter()
+                    .find(|path| path.ends_with(filename))
+                    .unwrap_or(&filename_as_path);
+
+                let source = std::fs::read_to_string(full_path).ok()?;
+                Some((filename.as_str(), source))
+            })
+            .collect();
+
+        Self {
+            model,
+            populations: model.get_populations().collect(),
+            constants: model.get_constants().collect(),
+            equations,
+            extensions,
+            templates: &BUILTIN_TEMPLATES,
+        }
+    }
+
+    pub fn with_templates(self, templates: &'a Templates) -> Self {
+        Self { templates, ..self }
+    }
+
+    /// The contents of the extension files whose names end with `suffix`,
+    /// for languages that can't include the others.
+    pub fn extensions_ending_with(&self, suffix: &str) -> Vec<&str> {
+        self.extensions
+            .iter()
+            .filter(|(filename, _)| filename.ends_with(suffix))
+            .map(|(_, source)| source.as_str())
+            .collect()
+    }
+
+    /// Renders the templates called `names` given `model`, `populations`,
+    /// `constants`, `equations` and the contents of the extension files
+    /// ending with `extension_suffix` as `extensions`.
+    pub fn render_templates(
+        &self,
+        names: &[&str],
+        extension_suffix: &str,
+    ) -> Result<String, TemplateError> {
+        self.render_templates_with(names, extension_suffix, context! {})
+    }
+
+    /// Like [`render_templates`](Self::render_templates), with the fields of
+    /// `extra` as variables too.
+    pub fn render_templates_with(
+        &self,
+        names: &[&str],
+        extension_suffix: &str,
+        extra: impl Serialize,
+    ) -> Result<String, TemplateError> {
+        let mut ctx = Map::from([
+            ("model".to_owned(), Value::from_serializable(self.model)),
+            (
+                "equations".to_owned(),
+                Value::from_serializable(&self.equations),
+            ),
+            (
+                "populations".to_owned(),
+                Value::from_serializable(&self.populations),
+            ),
+            (
+                "constants".to_owned(),
+                Value::from_serializable(&self.constants),
+            ),
+            (
+                "extensions".to_owned(),
+                Value::from_serializable(&self.extensions_ending_with(extension_suffix)),
+            ),
+        ]);
+        let extra = Value::from_serializable(&extra);
+        for key in extra.try_iter().into_iter().flatten() {
+            if let (Some(name), Ok(value)) = (key.as_str(), extra.get_item(&key)) {
+                ctx.insert(name.to_owned(), value);
+            }
+        }
+
+        self.templates.render(names, ctx)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RenderError {
+    UnknownBackend(String),
+    Template(TemplateError),
+}
+
+impl fmt::Display for RenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::UnknownBackend(name) => write!(f, "there is no backend called `{name}`"),
+            Self::Template(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RenderError {}
+
+impl From<TemplateError> for RenderError {
+    fn from(value: TemplateError) -> Self {
+        Self::Template(value)
+    }
+}
+
+/// Backends by name, and the templates they render. The default one has
+/// all the built-in backends.
+pub struct Registry {
+    backends: Map<String, Box<dyn Backend>>,
+    templates: Templates,
+}
+
+impl Registry {
+    pub fn empty() -> Self {
+        Self {
+            backends: Map::new(),
+            templates: Templates::new(),
+        }
+    }
+
+    /// Renders with `templates` instead of the built-in ones they replace.
+    pub fn set_templates(&mut self, templates: Templates) {
+        self.templates = templates;
+    }
+
+    /// Adds `backend`, replacing any other with the same name.
+    pub fn register(&mut self, backend: impl Backend + 'static) {
+        self.backends
+            .insert(backend.name().to_owned(), Box::new(backend));
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn Backend> {
+        self.backends.get(name).map(Box::as_ref)
+    }
+
+    /// The names of the backends, alphabetically.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.backends.keys().map(String::as_str)
+    }
+
+    pub fn render(
+        &self,
+        name: &str,
+        model: &OdeModel,
+        extension_lookup_paths: &[&PathBuf],
+    ) -> Result<String, RenderError> {
+        let backend = self.backend(name)?;
+        let context =
+            RenderContext::new(model, extension_lookup_paths).with_templates(&self.templates);
+        Ok(backend.render(&context)?)
+    }
+
+    /// Renders the files which must be saved next to the one from
+    /// [`render`](Self::render), as their names and contents.
+    pub fn render_support_files(
+        &self,
+        name: &str,
+        model: &OdeModel,
+        extension_lookup_paths: &[&PathBuf],
+    ) -> Result<Vec<(String, String)>, RenderError> {
+        let backend = self.backend(name)?;
+        let context =
+            RenderContext::new(model, extension_lookup_paths).with_templates(&self.templates);
+        Ok(backend.render_support_files(&context)?)
+    }
+
+    fn backend(&self, name: &str) -> Result<&dyn Backend, RenderError> {
+        self.get(name)
+            .ok_or_else(|| RenderError::UnknownBackend(name.to_owned()))
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register(super::c::C);
+        registry.register(super::julia::Julia);
+        registry.register(super::latex::Latex);
+        registry.register(super::octave::Octave);
+        registry.register(super::ode::Text);
+        registry.register(super::r::R);
+        registry.register(super::r4k::Python);
+        registry.register(super::rust::Rust);
+        registry.register(super::sbml::Sbml);
+        registry.register(super::typescript::TypeScript);
+        registry.register(super::xpp::Xpp);
+        registry
+    }
+}
+
+impl fmt::Debug for Registry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ode::Metadata;
+
+    fn model() -> OdeModel {
+        let mut model = OdeModel::new("Growth".into(), Metadata::default());
+        model.insert_argument(Argument::Value {
+            name: "x".into(),
+            value: 1.0,
+        });
+        model.insert_argument(Argument::Value {
+            name: "r".into(),
+            value: 0.5,
+        });
+        model.insert_equation(Equation {
+            name: "dx".into(),
+            operates_on: Some("x".into()),
+            argument: "r".into(),
+            contribution: '+',
+        });
+        model.extension_files = vec!["missing.py".into()];
+        model
+    }
+
+    #[test]
+    fn context() {
+        let model = model();
+        let context = RenderContext::new(&model, &[]);
+
+        assert_eq!(context.populations[0].name(), "x");
+        assert_eq!(context.constants[0].name(), "r");
+        assert_eq!(context.populations.len() + context.constants.len(), 2);
+        assert_eq!(context.equations["x"].argument, "r");
+        assert!(context.extensions.is_empty());
+    }
+
+    #[test]
+    fn registry() {
+        struct Shout;
+
+        impl Backend for Shout {
+            fn name(&self) -> &str {
+                "python"
+            }
+
+            fn file_extension(&self) -> &str {
+                "txt"
+            }
+
+            fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+                Ok(context.model.name.to_uppercase())
+            }
+        }
+
+        let model = model();
+        let mut registry = Registry::default();
+        assert_eq!(
+            registry.names().collect::<Vec<_>>(),
+            [
+                "c",
+                "julia",
+                "latex",
+                "octave",
+                "python",
+                "r",
+                "rust",
+                "sbml",
+                "text",
+                "typescript",
+                "xpp"
+            ]
+        );
+        assert_eq!(
+            registry.render("python", &model, &[]).unwrap(),
+            super::super::r4k::render_ode(&model, &[]).unwrap()
+        );
+
+        registry.register(Shout);
+
+        assert_eq!(registry.render("python", &model, &[]).unwrap(), "GROWTH");
+        assert_eq!(registry.get("python").unwrap().file_extension(), "txt");
+        assert_eq!(
+            registry.render("fortran", &model, &[]),
+            Err(RenderError::UnknownBackend("fortran".into()))
+        );
+    }
+
+    #[test]
+    fn registry_templates() {
+        let model = model();
+        let mut registry = Registry::default();
+        let mut templates = Templates::new();
+        templates.insert("ode-support.py", "# {{ model.name }}\n");
+        templates.insert(
+            "ode.txt.jinja",
+            "{% for name, rhs in equations %}{{ name }}' = {{ rhs }}\n{% endfor %}{{ 1 + }}",
+        );
+        registry.set_templates(templates);
+
+        let python = registry.render("python", &model, &[]).unwrap();
+        assert!(python.ends_with("\n# Growth"));
+        assert!(python.starts_with(&super::super::r4k::render_ode(&model, &[]).unwrap()[..100]));
+        let Err(RenderError::Template(err)) = registry.render("text", &model, &[]) else {
+            panic!("expected a template error");
+        };
+        assert_eq!((err.file.as_str(), err.line), ("ode.txt.jinja", Some(2)));
+    }
+}
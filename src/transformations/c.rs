@@ -0,0 +1,119 @@
+use std::path::PathBuf;
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_c`], called `c`.
+pub struct C;
+
+impl Backend for C {
+    fn name(&self) -> &str {
+        "c"
+    }
+
+    fn file_extension(&self) -> &str {
+        "c"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode.c.jinja", "ode-support.c"], ".c")
+    }
+}
+
+/// Renders a self-contained C program with a `rhs(t, y, dy, p)` function
+/// and a fixed step RK4 driver over the metadata's time span. Running it
+/// writes the solution as CSV, with the same columns as the script from
+/// [`render_ode`](super::r4k::render_ode), and `name=value` arguments
+/// override the constants' values.
+///
+/// Only the `.c` files among the model's extension files are included.
+pub fn render_c(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    C.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::transformations::test_models::{
+        abc, assert_matches_reference, named, predator_prey, run, scratch_directory,
+    };
+    use crate::Map;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let c = render_c(&abc(), &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.c");
+
+        assert_eq!(c, EXPECTED);
+    }
+
+    #[test]
+    fn compiled_program_matches_reference() {
+        compile_and_compare("c", &predator_prey(), &[("beta", 0.5)]);
+    }
+
+    #[test]
+    fn names_of_parameters_are_kept_clear() {
+        let model = named("y", &["p", "t", "dy"]);
+        let c = render_c(&model, &[]).unwrap();
+        assert!(c.contains("const double y_ = y[0];"));
+
+        compile_and_compare("c-names", &model, &[]);
+    }
+
+    /// Compiles the program rendered from `model` and checks what it prints
+    /// when run with `overrides`.
+    fn compile_and_compare(name: &str, model: &OdeModel, overrides: &[(&str, f64)]) {
+        let directory = scratch_directory(name);
+
+        let source = directory.join("model.c");
+        let binary = directory.join("model");
+        std::fs::write(&source, render_c(model, &[]).unwrap()).unwrap();
+
+        let compiler = std::env::var("CC").unwrap_or_else(|_| "cc".into());
+        let Some(compilation) = run(Command::new(&compiler)
+            .args(["-std=c99", "-Wall", "-Werror", "-O2", "-o"])
+            .arg(&binary)
+            .arg(&source)
+            .arg("-lm"))
+        else {
+            return;
+        };
+        assert!(
+            compilation.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compilation.stderr)
+        );
+
+        let arguments = overrides
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"));
+        let run = Command::new(&binary).args(arguments).output().unwrap();
+        assert!(run.status.success());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let output = String::from_utf8(run.stdout).unwrap();
+        let mut lines = output.lines();
+        let populations: Vec<&str> = model.get_populations().map(|arg| arg.name()).collect();
+        assert_eq!(
+            lines.next(),
+            Some(format!("t,{}", populations.join(",")).as_str())
+        );
+
+        // Values are printed with 4 decimals
+        let csv = lines.collect::<Vec<_>>().join("\n");
+        assert_matches_reference(
+            model,
+            &Map::from_iter(overrides.iter().copied()),
+            &csv,
+            5e-5,
+        );
+    }
+}
@@ -0,0 +1,224 @@
+use std::{fmt, path::PathBuf};
+
+use minijinja::context;
+
+use super::{RenderContext, TemplateError};
+use crate::models::cellular_automata::{CaModel, InitialConditionError};
+
+#[derive(Debug)]
+pub enum CaRenderError {
+    InitialCondition(InitialConditionError),
+    Template(TemplateError),
+}
+
+impl fmt::Display for CaRenderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InitialCondition(err) => err.fmt(f),
+            Self::Template(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for CaRenderError {}
+
+impl From<InitialConditionError> for CaRenderError {
+    fn from(value: InitialConditionError) -> Self {
+        Self::InitialCondition(value)
+    }
+}
+
+impl From<TemplateError> for CaRenderError {
+    fn from(value: TemplateError) -> Self {
+        Self::Template(value)
+    }
+}
+
+/// The generated script steps the grid with the following rules:
+///
+/// - inside a rule, each state's name is the number of the cell's
+///   neighbours that are in that state, as given by the model's
+///   [`Lattice`](crate::models::cellular_automata::Lattice);
+/// - `is_<state>` is 1 for cells currently in that state and 0 otherwise,
+///   and linear lattices also define `pattern` (see [`CaModel::next_state`]);
+/// - the value of the rule for a state is the probability of the cell
+///   moving into it, so a comparison acts as an always/never rule;
+/// - rules are tried in state order and the first one to fire wins. Cells
+///   for which no rule fires keep their state.
+///
+/// These are the rules of [`CaModel::step`], but the script draws from
+/// NumPy's generator, so its runs are not the same as the Rust ones.
+pub fn render_ca(
+    model: &CaModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, CaRenderError> {
+    render_ca_with(&RenderContext::new(model, extension_lookup_paths))
+}
+
+/// Like [`render_ca`], from a context which may have other templates (see
+/// [`RenderContext::with_templates`]).
+pub fn render_ca_with(context: &RenderContext<CaModel>) -> Result<String, CaRenderError> {
+    let model = context.model;
+    let grid = model.initial_grid()?;
+    let initial_grid: Vec<Vec<String>> = grid
+        .cells
+        .chunks((grid.width * grid.height).max(1))
+        .map(|layer| {
+            layer
+                .chunks(grid.width.max(1))
+                .map(|row| {
+                    row.iter()
+                        .map(|state| state.to_string())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                })
+                .collect()
+        })
+        .collect();
+
+    let lattice = model.metadata.lattice;
+    let neighbours = lattice.neighbours(0);
+    let odd_row_neighbours = Some(lattice.neighbours(1)).filter(|odd| *odd != neighbours);
+
+    let extra = context! {
+        initial_grid => initial_grid,
+        neighbours => neighbours,
+        odd_row_neighbours => odd_row_neighbours,
+    };
+
+    Ok(context.render_templates_with(&["ca.py.jinja", "ca-support.py"], "", extra)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::cellular_automata::{Cell, InitialCondition, Metadata};
+    use crate::models::CompositionStyle::Infixed;
+    use crate::transformations::test_models::{arg, composite, value};
+    use crate::transformations::Templates;
+    use crate::Equation;
+
+    use super::*;
+
+    fn rule(state: impl Into<String>, argument: impl Into<String>) -> Equation {
+        let state = state.into();
+        Equation {
+            name: format!("to_{state}"),
+            operates_on: Some(state),
+            argument: argument.into(),
+            contribution: '+',
+        }
+    }
+
+    fn game_of_life() -> CaModel {
+        let mut model = CaModel::new(
+            "Game of Life".into(),
+            Metadata {
+                width: 5,
+                height: 5,
+                seed: 3,
+                initial_condition: InitialCondition::Cells {
+                    background: "dead".into(),
+                    cells: (1..4)
+                        .map(|x| Cell {
+                            x,
+                            y: 2,
+                            z: 0,
+                            state: "alive".into(),
+                        })
+                        .collect(),
+                },
+                ..Default::default()
+            },
+        );
+
+        model.insert_argument(value("alive", 0.5));
+        model.insert_argument(value("dead", 0.5));
+        model.insert_argument(value("two", 2.0));
+        model.insert_argument(value("three", 3.0));
+
+        model.insert_argument(composite(
+            "birth",
+            "==",
+            Infixed,
+            [arg("alive"), arg("three")],
+        ));
+        model.insert_argument(composite(
+            "underpopulation",
+            "<",
+            Infixed,
+            [arg("alive"), arg("two")],
+        ));
+        model.insert_argument(composite(
+            "overpopulation",
+            ">",
+            Infixed,
+            [arg("alive"), arg("three")],
+        ));
+        model.insert_argument(composite(
+            "death",
+            "+",
+            Infixed,
+            [arg("underpopulation"), arg("overpopulation")],
+        ));
+
+        model.insert_equation(rule("alive", "birth"));
+        model.insert_equation(rule("dead", "death"));
+
+        model
+    }
+
+    #[test]
+    fn render_game_of_life() {
+        let model = game_of_life();
+
+        let script = render_ca(&model, &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/game_of_life_ca.py");
+
+        assert_eq!(script, EXPECTED);
+    }
+
+    #[test]
+    fn state_names_become_identifiers() {
+        let mut model = CaModel::new(
+            "Forest fire".into(),
+            Metadata {
+                initial_condition: InitialCondition::Uniform {
+                    state: "class".into(),
+                },
+                ..Default::default()
+            },
+        );
+
+        model.insert_argument(value("class", 0.5));
+        model.insert_argument(value("on-fire", 0.5));
+        model.insert_argument(value("is_on-fire", 0.0));
+
+        model.insert_equation(rule("on-fire", "is_on-fire"));
+        model.insert_equation(rule("class", "on-fire"));
+
+        let script = render_ca(&model, &[]).unwrap();
+
+        assert!(script.contains("\n    is_class = (grid == 0)"));
+        assert!(script.contains("\n    is_on_fire = (grid == 1)"));
+        assert!(script.contains("\n    to_on_fire = as_float(is_on_fire)"));
+        assert!(script.contains("< to_on_fire)"));
+        assert!(script.contains("\n    to_class = as_float(on_fire)"));
+    }
+
+    #[test]
+    fn templates_can_be_replaced() {
+        let model = game_of_life();
+        let mut templates = Templates::new();
+        templates.insert(
+            "ca-support.py",
+            "# {{ model.name }}: {{ neighbours|length }}\n",
+        );
+
+        let script =
+            render_ca_with(&RenderContext::new(&model, &[]).with_templates(&templates)).unwrap();
+
+        assert!(script.starts_with(&render_ca(&model, &[]).unwrap()[..100]));
+        assert!(script.ends_with("\n# Game of Life: 8"));
+    }
+}
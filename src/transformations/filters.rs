@@ -0,0 +1,793 @@
+//! Functions and filters available to every template, so that templates
+//! don't write expressions themselves:
+//!
+//! - `expr(argument, lang)` writes an argument, or the argument with a
+//!   name, as an expression of `lang`;
+//! - `derivative(population, lang)` writes the sum of the equations of a
+//!   population, or the population with a name, with their signs;
+//! - `name|ident(lang)` writes the name of a value as it's called in
+//!   `lang`;
+//! - `number|fmt_float` writes a number so that it reads back the same and
+//!   is a float in every language, like `10.0` or `1e-7`.
+//!
+//! The languages are `c`, `julia`, `numpy`, `octave`, `python`, `r`,
+//! `rust`, `typescript` and `xpp`. `numpy` is Python where values are
+//! arrays, so comparisons give booleans which are converted to numbers with
+//! `as_float`.
+//!
+//! These look up the arguments in the template's `model`.
+
+use std::collections::BTreeSet;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+
+use minijinja::value::{Object, Value};
+use minijinja::{Environment, Error, ErrorKind, State};
+
+use super::xpp;
+use crate::models::{Argument, Component, CompositionStyle, CoreModel};
+use crate::Map;
+
+const COMPARISONS: [&str; 6] = ["==", "!=", "<", "<=", ">", ">="];
+
+const POWERS: [&str; 3] = ["**", "^", "pow"];
+
+/// Functions which Rust calls as methods of their only operand.
+const RUST_METHODS: [&str; 9] = [
+    "abs", "exp", "log", "sqrt", "sin", "cos", "tan", "floor", "ceil",
+];
+
+/// Functions of JavaScript's `Math`, anything else being an extension.
+const TYPESCRIPT_MATH: [&str; 35] = [
+    "abs", "acos", "acosh", "asin", "asinh", "atan", "atan2", "atanh", "cbrt", "ceil", "clz32",
+    "cos", "cosh", "exp", "expm1", "floor", "fround", "hypot", "imul", "log", "log10", "log1p",
+    "log2", "max", "min", "pow", "random", "round", "sign", "sin", "sinh", "sqrt", "tan", "tanh",
+    "trunc",
+];
+
+pub(crate) fn add_to(env: &mut Environment) {
+    env.add_function("expr", expr);
+    env.add_function("derivative", derivative);
+    env.add_filter("ident", ident);
+    env.add_filter("fmt_float", fmt_float);
+}
+
+fn expr(state: &State, argument: Value, lang: &str) -> Result<String, Error> {
+    let name = name(&argument)?;
+    with_printer(state, lang.parse()?, |printer| {
+        printer.expression(&name, &mut Vec::new())
+    })
+}
+
+fn derivative(state: &State, population: Value, lang: &str) -> Result<String, Error> {
+    let name = name(&population)?;
+    with_printer(state, lang.parse()?, |printer| printer.derivative(&name))
+}
+
+/// Either a name, or the name of an argument.
+fn name(value: &Value) -> Result<String, Error> {
+    match value.as_str() {
+        Some(name) => Ok(name.to_owned()),
+        None => Ok(value.get_attr("name")?.to_string()),
+    }
+}
+
+fn ident(state: &State, name: &str, lang: &str) -> Result<String, Error> {
+    let language: Language = lang.parse()?;
+    // Names are only told apart within a model
+    if language != Language::Xpp && state.lookup("model").is_none() {
+        return Ok(language.ident(name));
+    }
+    with_printer(state, language, |printer| Ok(printer.ident(name)))
+}
+
+fn fmt_float(value: Value) -> Result<String, Error> {
+    let number =
+        f64::try_from(value.clone()).or_else(|_| i64::try_from(value).map(|n| n as f64))?;
+    if !number.is_finite() {
+        return Err(Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{number} can't be written as a number"),
+        ));
+    }
+    // Debug, unlike Display, writes a `.0` or an exponent
+    Ok(format!("{number:?}"))
+}
+
+/// Calls `f` with a printer of the template's `model` in `language`, using
+/// the global [`MODEL`] if there is one.
+fn with_printer<T>(
+    state: &State,
+    language: Language,
+    f: impl FnOnce(&Printer) -> Result<T, Error>,
+) -> Result<T, Error> {
+    let global = state.lookup(MODEL);
+    let parsed;
+    let model = match global.as_ref().and_then(Value::downcast_object_ref) {
+        Some(model) => model,
+        None => {
+            let model = state.lookup("model").ok_or_else(|| {
+                Error::new(
+                    ErrorKind::UndefinedError,
+                    "expressions need the `model` they're in",
+                )
+            })?;
+            parsed = ParsedModel::new(&model)?;
+            &parsed
+        }
+    };
+    let names = model.names(language);
+    f(&Printer::new(&model.model, language, &names))
+}
+
+/// The global holding the template's `model` once read, which the
+/// functions use rather than reading it on every call.
+pub(crate) const MODEL: &str = "__parsed_model";
+
+/// The arguments and equations of a template's `model`, with how each
+/// language names its values, worked out on first use.
+#[derive(Debug)]
+pub(crate) struct ParsedModel {
+    model: CoreModel,
+    names: Mutex<Map<Language, Arc<Map<String, String>>>>,
+}
+
+impl ParsedModel {
+    pub(crate) fn new(model: &Value) -> Result<Self, Error> {
+        let model = serde_json::to_value(model)
+            .and_then(serde_json::from_value)
+            .map_err(|err| {
+                Error::new(ErrorKind::InvalidOperation, format!("invalid model: {err}"))
+            })?;
+        Ok(Self {
+            model,
+            names: Mutex::default(),
+        })
+    }
+
+    /// How `language` calls each value.
+    fn names(&self, language: Language) -> Arc<Map<String, String>> {
+        let mut names = self.names.lock().unwrap_or_else(|err| err.into_inner());
+        let names = names.entry(language).or_insert_with(|| {
+            let names = match language {
+                Language::Xpp => xpp::names(&self.model),
+                _ => language.names(&self.model),
+            };
+            Arc::new(
+                names
+                    .into_iter()
+                    .map(|(name, ident)| (name.to_owned(), ident))
+                    .collect(),
+            )
+        });
+        Arc::clone(names)
+    }
+}
+
+impl fmt::Display for ParsedModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<model>")
+    }
+}
+
+impl Object for ParsedModel {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Language {
+    C,
+    Julia,
+    NumPy,
+    Octave,
+    Python,
+    R,
+    Rust,
+    TypeScript,
+    Xpp,
+}
+
+impl FromStr for Language {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(match s {
+            "c" => Self::C,
+            "julia" => Self::Julia,
+            "numpy" => Self::NumPy,
+            "octave" => Self::Octave,
+            "python" => Self::Python,
+            "r" => Self::R,
+            "rust" => Self::Rust,
+            "typescript" => Self::TypeScript,
+            "xpp" => Self::Xpp,
+            _ => {
+                return Err(Error::new(
+                    ErrorKind::InvalidOperation,
+                    format!("there is no language called `{s}`"),
+                ))
+            }
+        })
+    }
+}
+
+impl Language {
+    /// Separated by spaces.
+    fn keywords(self) -> &'static str {
+        match self {
+            Self::C => {
+                "auto break case char const continue default do double else enum extern \
+                 float for goto if inline int long register restrict return short signed \
+                 sizeof static struct switch typedef union unsigned void volatile while"
+            }
+            Self::Julia => {
+                "baremodule begin break catch const continue do else elseif end export \
+                 false finally for function global if import let local macro module quote \
+                 return struct true try using while"
+            }
+            Self::NumPy | Self::Python => {
+                "False None True and as assert async await break class continue def del \
+                 elif else except finally for from global if import in is lambda nonlocal \
+                 not or pass raise return try while with yield"
+            }
+            Self::Octave => {
+                "break case catch classdef continue do else elseif end for function \
+                 global if otherwise parfor persistent return spmd switch try until while"
+            }
+            Self::R => {
+                "if else repeat while function for next break in TRUE FALSE \
+                 NULL Inf NaN NA"
+            }
+            Self::Rust => {
+                "as async await break const continue crate dyn else enum extern false fn \
+                 for if impl in let loop match mod move mut pub ref return self Self \
+                 static struct super trait true type unsafe use where while"
+            }
+            Self::TypeScript => {
+                "break case catch class const continue debugger default delete do else \
+                 enum export extends false finally for function if import in instanceof \
+                 let new null return super switch this throw true try typeof var void \
+                 while with yield"
+            }
+            Self::Xpp => "",
+        }
+    }
+
+    /// The variables the templates declare in the scope of the model's
+    /// values, like the parameters of the right hand side, separated by
+    /// spaces.
+    fn reserved(self) -> &'static str {
+        match self {
+            Self::C => "t y dy p",
+            Self::Julia => "du u p t",
+            Self::Octave => "t y p dy",
+            Self::TypeScript => "t state params",
+            _ => "",
+        }
+    }
+
+    /// `name` with anything but letters, digits and underscores replaced,
+    /// and suffixed with an underscore if it's a keyword or reserved.
+    fn ident(self, name: &str) -> String {
+        let mut ident: String = name
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        if !ident.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            ident.insert_str(0, "x_");
+        }
+        let mut taken = self.keywords().split(' ').chain(self.reserved().split(' '));
+        if taken.any(|keyword| keyword == ident) {
+            ident.push('_');
+        }
+        ident
+    }
+
+    /// The identifier of each value of `model`, told apart with a number
+    /// when two names make the same one, like `a-b` and `a_b`. Names which
+    /// are identifiers already are kept.
+    fn names(self, model: &CoreModel) -> Map<&str, String> {
+        let values: Vec<&str> = model
+            .arguments
+            .values()
+            .filter_map(|argument| match argument {
+                Argument::Value { name, .. } => Some(name.as_str()),
+                Argument::Composite { .. } => None,
+            })
+            .collect();
+        let (kept, renamed): (Vec<&str>, Vec<&str>) = values
+            .into_iter()
+            .partition(|&name| self.ident(name) == name);
+
+        let mut names: Map<&str, String> =
+            kept.iter().map(|&name| (name, name.to_owned())).collect();
+        let mut taken: BTreeSet<String> = kept.iter().map(|&name| name.to_owned()).collect();
+        for name in renamed {
+            let base = self.ident(name);
+            let mut candidate = base.clone();
+            for suffix in 1.. {
+                if !taken.contains(&candidate) {
+                    break;
+                }
+                candidate = format!("{base}_{suffix}");
+            }
+            taken.insert(candidate.clone());
+            names.insert(name, candidate);
+        }
+        names
+    }
+}
+
+/// Writes the arguments of a model in a language, following the structure
+/// of their compositions, with every composite inside another in
+/// parentheses.
+struct Printer<'a> {
+    model: &'a CoreModel,
+    language: Language,
+    /// How the language calls the values which aren't simply made
+    /// identifiers.
+    names: &'a Map<String, String>,
+}
+
+impl<'a> Printer<'a> {
+    fn new(model: &'a CoreModel, language: Language, names: &'a Map<String, String>) -> Self {
+        Self {
+            model,
+            language,
+            names,
+        }
+    }
+
+    fn ident(&self, name: &str) -> String {
+        match self.names.get(name) {
+            Some(name) => name.clone(),
+            None => self.language.ident(name),
+        }
+    }
+
+    fn expression(&self, name: &'a str, visiting: &mut Vec<&'a str>) -> Result<String, Error> {
+        let argument = self.model.arguments.get(name).ok_or_else(|| {
+            Error::new(
+                ErrorKind::UndefinedError,
+                format!("there is no argument called `{name}`"),
+            )
+        })?;
+        let Argument::Composite {
+            operation,
+            style,
+            composition,
+            ..
+        } = argument
+        else {
+            return Ok(self.ident(name));
+        };
+        if visiting.contains(&name) {
+            return Err(Error::new(
+                ErrorKind::InvalidOperation,
+                format!("`{name}` is part of itself"),
+            ));
+        }
+
+        visiting.push(name);
+        let operands = composition
+            .iter()
+            .map(|component| self.operand(component, visiting))
+            .collect::<Result<Vec<_>, _>>()?;
+        visiting.pop();
+
+        Ok(self.composite(operation, *style, &operands))
+    }
+
+    /// The sum of the equations operating on `population`, each with its
+    /// sign.
+    fn derivative(&self, population: &str) -> Result<String, Error> {
+        let equations: Vec<_> = self
+            .model
+            .equations
+            .iter()
+            .filter(|eq| eq.operates_on.as_deref() == Some(population))
+            .collect();
+
+        match equations.as_slice() {
+            [] => Ok("0.0".to_owned()),
+            [eq] if eq.contribution != '-' => self.expression(&eq.argument, &mut Vec::new()),
+            _ => {
+                let terms = equations
+                    .iter()
+                    .map(|eq| {
+                        let term = self.expression(&eq.argument, &mut Vec::new())?;
+                        Ok(self.signed(&eq.argument, eq.contribution, term))
+                    })
+                    .collect::<Result<Vec<_>, Error>>()?;
+                Ok(self.composite("+", CompositionStyle::Infixed, &terms))
+            }
+        }
+    }
+
+    /// A component, with its sign, as an operand of the composite it's in.
+    fn operand(
+        &self,
+        component: &'a Component,
+        visiting: &mut Vec<&'a str>,
+    ) -> Result<String, Error> {
+        let operand = self.expression(&component.name, visiting)?;
+        Ok(self.signed(&component.name, component.contribution, operand))
+    }
+
+    /// `operand`, the expression of the argument called `name`, in
+    /// parentheses if it's a composite and negated by a `-` contribution.
+    fn signed(&self, name: &str, contribution: char, mut operand: String) -> String {
+        if let Some(Argument::Composite { .. }) = self.model.arguments.get(name) {
+            operand = match self.language {
+                Language::NumPy => format!("as_float({operand})"),
+                _ => format!("({operand})"),
+            };
+        }
+
+        if contribution == '-' {
+            // Parenthesized, as `x**-y**z` would raise x to -(y**z)
+            operand = format!("(-{operand})");
+        }
+        operand
+    }
+
+    fn composite(&self, operation: &str, style: CompositionStyle, operands: &[String]) -> String {
+        let prefixed = style == CompositionStyle::Prefixed;
+        let is_comparison = COMPARISONS.contains(&operation);
+        let is_power = POWERS.contains(&operation);
+
+        match self.language {
+            Language::NumPy | Language::Python => {
+                if prefixed {
+                    format!("{operation}({})", operands.join(","))
+                } else {
+                    operands.join(operation)
+                }
+            }
+            Language::Julia => {
+                if prefixed {
+                    format!("{operation}({})", operands.join(","))
+                } else if operation == "%" {
+                    format!("foldl(mod, ({}))", operands.join(","))
+                } else if operation == "**" {
+                    operands.join("^")
+                } else {
+                    operands.join(operation)
+                }
+            }
+            // R doesn't chain comparisons, so `a < b < c` is spelled out
+            Language::R if is_comparison => chain(operands, operation, " & "),
+            Language::R => {
+                if prefixed {
+                    format!("{operation}({})", operands.join(","))
+                } else if operation == "%" {
+                    operands.join("%%")
+                } else {
+                    operands.join(operation)
+                }
+            }
+            Language::C if is_comparison => chain(operands, &format!(" {operation} "), " && "),
+            Language::C if is_power => nest_right("pow(", ", ", operands),
+            Language::C if ["%", "min", "max"].contains(&operation) => {
+                let function = match operation {
+                    "%" => "py_mod",
+                    "min" => "fmin",
+                    _ => "fmax",
+                };
+                fold_left(function, ", ", operands)
+            }
+            Language::C if prefixed => {
+                let function = if operation == "abs" {
+                    "fabs"
+                } else {
+                    operation
+                };
+                format!("{function}({})", operands.join(", "))
+            }
+            Language::Rust if is_comparison => {
+                let chained = chain(operands, &format!(" {operation} "), " && ");
+                format!("({chained}) as u8 as f64")
+            }
+            Language::Rust if is_power => {
+                let Some((last, bases)) = operands.split_last() else {
+                    return String::new();
+                };
+                let mut expression = last.clone();
+                for base in bases.iter().rev() {
+                    expression = format!("{base}.powf({expression})");
+                }
+                expression
+            }
+            // Floored like Python's, which rem_euclid isn't for negative divisors
+            Language::Rust if operation == "%" => {
+                let Some((first, rest)) = operands.split_first() else {
+                    return String::new();
+                };
+                rest.iter().fold(first.clone(), |a, b| {
+                    format!("({a} - {b} * ({a} / {b}).floor())")
+                })
+            }
+            Language::Rust if ["min", "max"].contains(&operation) => {
+                let Some((first, rest)) = operands.split_first() else {
+                    return String::new();
+                };
+                rest.iter().fold(first.clone(), |expression, operand| {
+                    format!("{expression}.{operation}({operand})")
+                })
+            }
+            Language::Rust if prefixed && RUST_METHODS.contains(&operation) => {
+                let method = if operation == "log" { "ln" } else { operation };
+                let first = operands.first().map(String::as_str).unwrap_or_default();
+                format!("{first}.{method}()")
+            }
+            Language::TypeScript if is_comparison => {
+                let chained = chain(operands, &format!(" {operation} "), " && ");
+                format!("Number({chained})")
+            }
+            Language::TypeScript if operation == "%" => fold_left("pyMod", ", ", operands),
+            Language::TypeScript if prefixed && TYPESCRIPT_MATH.contains(&operation) => {
+                format!("Math.{operation}({})", operands.join(", "))
+            }
+            // ^ is exclusive or in JavaScript
+            Language::TypeScript if operation == "^" => operands.join(" ** "),
+            // Python chains comparisons, XPP doesn't, and they both yield 1 or 0
+            Language::Xpp if is_comparison => {
+                let pairs = operands
+                    .windows(2)
+                    .map(|pair| format!("({}{operation}{})", pair[0], pair[1]));
+                pairs.collect::<Vec<_>>().join("*")
+            }
+            Language::Xpp if is_power => nest_right("", "^(", operands),
+            Language::Xpp if ["%", "min", "max"].contains(&operation) => {
+                let function = if operation == "%" { "pymod" } else { operation };
+                fold_left(function, ",", operands)
+            }
+            Language::Xpp if prefixed => {
+                let function = match operation {
+                    "log" => "ln",
+                    "floor" => "flr",
+                    _ => operation,
+                };
+                format!("{function}({})", operands.join(","))
+            }
+            Language::Xpp => operands.join(operation),
+            Language::Octave if is_comparison => {
+                let operation = if operation == "!=" { "~=" } else { operation };
+                chain(operands, &format!(" {operation} "), " & ")
+            }
+            // MATLAB's ^ is left associative
+            Language::Octave if is_power => nest_right("power(", ", ", operands),
+            Language::Octave if ["%", "min", "max"].contains(&operation) => {
+                let function = if operation == "%" { "mod" } else { operation };
+                fold_left(function, ", ", operands)
+            }
+            Language::C | Language::Rust | Language::TypeScript | Language::Octave => {
+                if prefixed {
+                    format!("{operation}({})", operands.join(", "))
+                } else {
+                    operands.join(&format!(" {operation} "))
+                }
+            }
+        }
+    }
+}
+
+/// Each operand compared with the next, like `a < b && b < c`.
+fn chain(operands: &[String], comparison: &str, conjunction: &str) -> String {
+    let pairs = operands
+        .windows(2)
+        .map(|pair| format!("{}{comparison}{}", pair[0], pair[1]));
+    pairs.collect::<Vec<_>>().join(conjunction)
+}
+
+/// A left associative operation as nested calls, like `f(f(a, b), c)`.
+fn fold_left(function: &str, separator: &str, operands: &[String]) -> String {
+    let Some((first, rest)) = operands.split_first() else {
+        return String::new();
+    };
+    rest.iter().fold(first.clone(), |expression, operand| {
+        format!("{function}({expression}{separator}{operand})")
+    })
+}
+
+/// A right associative operation as nested calls, like `pow(a, pow(b, c))`,
+/// or as `a^(b^(c))` without a function.
+fn nest_right(function: &str, separator: &str, operands: &[String]) -> String {
+    let Some((last, bases)) = operands.split_last() else {
+        return String::new();
+    };
+    let mut expression = last.clone();
+    for base in bases.iter().rev() {
+        expression = format!("{function}{base}{separator}{expression})");
+    }
+    expression
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::context;
+
+    use super::*;
+    use crate::models::Equation;
+
+    fn component(name: &str, contribution: char) -> Component {
+        Component {
+            name: name.into(),
+            contribution,
+        }
+    }
+
+    fn model() -> CoreModel {
+        let mut model = CoreModel::new();
+        for (name, value) in [("x", 2.0), ("lambda", 0.5), ("c2", 2.0)] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+        }
+        model.insert_number(3.0);
+        model.insert_argument(Argument::Composite {
+            name: "x<c3".into(),
+            operation: "<".into(),
+            style: CompositionStyle::Infixed,
+            composition: vec![component("x", '+'), component("c3", '+')],
+        });
+        model.insert_argument(Argument::Composite {
+            name: "rate".into(),
+            operation: "**".into(),
+            style: CompositionStyle::Infixed,
+            composition: vec![
+                component("x", '+'),
+                component("lambda", '-'),
+                component("x<c3", '+'),
+            ],
+        });
+        model.insert_equation(Equation {
+            name: "dx".into(),
+            operates_on: Some("x".into()),
+            argument: "rate".into(),
+            contribution: '+',
+        });
+        model
+    }
+
+    fn render(source: &str) -> Result<String, Error> {
+        let mut env = Environment::new();
+        add_to(&mut env);
+        env.render_str(source, context! { model => model() })
+    }
+
+    #[test]
+    fn expressions() {
+        let cases = [
+            ("python", "x**(-lambda_)**(x<c3)"),
+            ("numpy", "x**(-lambda_)**as_float(x<c3)"),
+            ("julia", "x^(-lambda)^(x<c3)"),
+            ("r", "x**(-lambda)**(x<c3)"),
+            ("c", "pow(x, pow((-lambda), (x < c3)))"),
+            ("rust", "x.powf((-lambda).powf(((x < c3) as u8 as f64)))"),
+            ("typescript", "x ** (-lambda) ** (Number(x < c3))"),
+            ("xpp", "x^((-lambda)^(((x<3))))"),
+            ("octave", "power(x, power((-lambda), (x < c3)))"),
+        ];
+
+        for (lang, expected) in cases {
+            let source = format!("{{{{ expr(model.arguments.rate, {lang:?}) }}}}");
+            assert_eq!(render(&source).unwrap(), expected, "{lang}");
+        }
+        assert_eq!(render("{{ expr('lambda', 'c') }}").unwrap(), "lambda");
+    }
+
+    #[test]
+    fn derivatives_add_up_equations() {
+        let mut model = model();
+        model.insert_equation(Equation {
+            name: "decay".into(),
+            operates_on: Some("x".into()),
+            argument: "lambda".into(),
+            contribution: '-',
+        });
+        let mut env = Environment::new();
+        add_to(&mut env);
+        let render = |lang: &str| {
+            let source = format!("{{{{ derivative('x', {lang:?}) }}}}");
+            env.render_str(&source, context! { model => model })
+                .unwrap()
+        };
+
+        assert_eq!(render("python"), "(x**(-lambda_)**(x<c3))+(-lambda_)");
+        assert_eq!(
+            render("c"),
+            "(pow(x, pow((-lambda), (x < c3)))) + (-lambda)"
+        );
+        assert_eq!(
+            render("numpy"),
+            "as_float(x**(-lambda_)**as_float(x<c3))+(-lambda_)"
+        );
+        assert_eq!(render("xpp"), "(x^((-lambda)^(((x<3)))))+(-lambda)");
+    }
+
+    #[test]
+    fn modulo_is_floored() {
+        let mut model = model();
+        model.insert_argument(Argument::Composite {
+            name: "x%-lambda".into(),
+            operation: "%".into(),
+            style: CompositionStyle::Infixed,
+            composition: vec![component("x", '+'), component("lambda", '-')],
+        });
+        let mut env = Environment::new();
+        add_to(&mut env);
+        let source = "{{ expr('x%-lambda', 'rust') }}";
+
+        assert_eq!(
+            env.render_str(source, context! { model => model }).unwrap(),
+            "(x - (-lambda) * (x / (-lambda)).floor())"
+        );
+    }
+
+    #[test]
+    fn typescript_extensions_are_plain_calls() {
+        let mut model = model();
+        for operation in ["sqrt", "hill"] {
+            model.insert_argument(Argument::Composite {
+                name: operation.into(),
+                operation: operation.into(),
+                style: CompositionStyle::Prefixed,
+                composition: vec![component("x", '+'), component("lambda", '+')],
+            });
+        }
+        let mut env = Environment::new();
+        add_to(&mut env);
+        let render = |name: &str| {
+            let source = format!("{{{{ expr({name:?}, 'typescript') }}}}");
+            env.render_str(&source, context! { model => model })
+                .unwrap()
+        };
+
+        assert_eq!(render("sqrt"), "Math.sqrt(x, lambda)");
+        assert_eq!(render("hill"), "hill(x, lambda)");
+    }
+
+    #[test]
+    fn idents_and_floats() {
+        assert_eq!(
+            render("{{ 'lambda'|ident('python') }} {{ 'end'|ident('julia') }} {{ 'a b'|ident('c') }} {{ '2x'|ident('r') }}")
+                .unwrap(),
+            "lambda_ end_ a_b x_2x"
+        );
+        // Only constants added for numbers are written as numbers
+        assert_eq!(render("{{ 'c3'|ident('xpp') }}").unwrap(), "3");
+        assert_eq!(render("{{ 'c2'|ident('xpp') }}").unwrap(), "c2");
+        assert_eq!(
+            render("{{ 10.0|fmt_float }} {{ 3|fmt_float }} {{ 0.0000001|fmt_float }} {{ 0.1|fmt_float }}")
+                .unwrap(),
+            "10.0 3.0 1e-7 0.1"
+        );
+    }
+
+    #[test]
+    fn idents_are_told_apart() {
+        let mut model = model();
+        for name in ["a-b", "a_b", "y", "y_"] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value: 1.0,
+            });
+        }
+        let source =
+            "{% for name in ['a-b', 'a_b', 'y', 'y_'] %}{{ name|ident('c') }} {% endfor %}";
+        let mut env = Environment::new();
+        add_to(&mut env);
+        let rendered = env.render_str(source, context! { model => model }).unwrap();
+        assert_eq!(rendered, "a_b_1 a_b y__1 y_ ");
+    }
+
+    #[test]
+    fn errors() {
+        let err = render("{{ expr('missing', 'python') }}").unwrap_err();
+        assert!(err.to_string().contains("no argument called `missing`"));
+        let err = render("{{ expr('x', 'cobol') }}").unwrap_err();
+        assert!(err.to_string().contains("no language called `cobol`"));
+        assert!(render("{{ (1.0 / 0.0)|fmt_float }}").is_err());
+    }
+}
@@ -0,0 +1,167 @@
+/*
+ * _
+ *
+ * Build with `cc -O2 -o model model.c -lm` and run as
+ * `./model [-o output.csv] [constant=value ...]`.
+ */
+#include <math.h>
+#include <stdio.h>
+#include <stdlib.h>
+#include <string.h>
+
+#define N_VARIABLES 2
+#define N_CONSTANTS 1
+
+static const double START_TIME = 0.0;
+static const double END_TIME = 10.0;
+static const double DELTA_TIME = 0.1;
+
+/* Both lists end with NULL, so that neither is ever empty */
+static const char *VARIABLE_NAMES[] = {
+    "A",
+    "B",
+    NULL,
+};
+
+static const char *CONSTANT_NAMES[] = {
+    "k",
+    NULL,
+};
+
+
+
+/* Python's modulo, which takes the sign of the divisor */
+static inline double py_mod(double a, double b) {
+    return a - b * floor(a / b);
+}
+
+
+static void initial_values(double *y) {
+    y[0] = 10.0; /* A */
+    y[1] = 20.0; /* B */
+    
+}
+
+
+static void constants(double *p) {
+    p[0] = 0.5; /* k */
+    
+}
+
+
+void rhs(double t, const double *y, double *dy, const double *p) {
+    /* populations */
+    const double A = y[0];
+    const double B = y[1];
+    
+    /* constants */
+    const double k = p[0];
+    
+    (void)t;
+
+    dy[0] = A + B;
+    dy[1] = pow(B, pow(k, (-(fmax((py_mod(A, B)), (k < A && A < B))))));
+}
+
+/* includes! "ode-support.c" */
+
+
+static void write_header(FILE *out) {
+    fputs("t", out);
+    for (int i = 0; VARIABLE_NAMES[i] != NULL; i++) {
+        fprintf(out, ",%s", VARIABLE_NAMES[i]);
+    }
+    fputc('\n', out);
+}
+
+
+static void write_row(FILE *out, double t, const double *y) {
+    fprintf(out, "%g", t);
+    for (int i = 0; i < N_VARIABLES; i++) {
+        fprintf(out, ",%.4f", y[i]);
+    }
+    fputc('\n', out);
+}
+
+
+/* Advances `y` from `t` to `t + h` with the classic Runge-Kutta method */
+static void rk4_step(double t, double h, double *y, const double *p) {
+    double k1[N_VARIABLES + 1], k2[N_VARIABLES + 1], k3[N_VARIABLES + 1], k4[N_VARIABLES + 1];
+    double tmp[N_VARIABLES + 1];
+
+    rhs(t, y, k1, p);
+    for (int i = 0; i < N_VARIABLES; i++) tmp[i] = y[i] + h / 2 * k1[i];
+
+    rhs(t + h / 2, tmp, k2, p);
+    for (int i = 0; i < N_VARIABLES; i++) tmp[i] = y[i] + h / 2 * k2[i];
+
+    rhs(t + h / 2, tmp, k3, p);
+    for (int i = 0; i < N_VARIABLES; i++) tmp[i] = y[i] + h * k3[i];
+
+    rhs(t + h, tmp, k4, p);
+    for (int i = 0; i < N_VARIABLES; i++) {
+        y[i] += h / 6 * (k1[i] + 2 * k2[i] + 2 * k3[i] + k4[i]);
+    }
+}
+
+
+/* Applies a `name=value` override, returning 0 if there's no such constant */
+static int update_constant(double *p, const char *assignment) {
+    const char *equals = strchr(assignment, '=');
+    size_t length = (size_t)(equals - assignment);
+
+    for (int i = 0; CONSTANT_NAMES[i] != NULL; i++) {
+        if (strlen(CONSTANT_NAMES[i]) == length && strncmp(CONSTANT_NAMES[i], assignment, length) == 0) {
+            p[i] = strtod(equals + 1, NULL);
+            return 1;
+        }
+    }
+
+    return 0;
+}
+
+
+int main(int argc, char **argv) {
+    double y[N_VARIABLES + 1];
+    double p[N_CONSTANTS + 1];
+    FILE *out = stdout;
+
+    initial_values(y);
+    constants(p);
+
+    for (int i = 1; i < argc; i++) {
+        if ((strcmp(argv[i], "-o") == 0 || strcmp(argv[i], "--output") == 0) && i + 1 < argc) {
+            out = fopen(argv[++i], "w");
+            if (out == NULL) {
+                perror(argv[i]);
+                return 1;
+            }
+        } else if (strchr(argv[i], '=') != NULL) {
+            if (!update_constant(p, argv[i])) {
+                fprintf(stderr, "unknown constant in `%s`\n", argv[i]);
+                return 1;
+            }
+        } else {
+            fprintf(stderr, "usage: %s [-o output.csv] [constant=value ...]\n", argv[0]);
+            return 1;
+        }
+    }
+
+    long steps = lround((END_TIME - START_TIME) / DELTA_TIME);
+
+    write_header(out);
+    for (long step = 0; step <= steps; step++) {
+        double t = START_TIME + step * DELTA_TIME;
+
+        write_row(out, t, y);
+        if (step < steps) {
+            rk4_step(t, DELTA_TIME, y, p);
+        }
+    }
+
+    if (out != stdout) {
+        fclose(out);
+    }
+
+    return 0;
+}
\ No newline at end of file
@@ -0,0 +1,119 @@
+using DifferentialEquations
+using Printf
+
+
+
+# Python's operators and functions which Julia spells differently
+pow(x, y) = x^y
+
+function initial_values()
+    return [
+        10.0,  # A
+        20.0,  # B
+        ]
+end
+
+
+function constants()
+    return [
+        0.5,  # k
+        ]
+end
+
+
+const CONSTANT_NAMES = [
+    "k",
+    ]
+
+
+const VARIABLE_NAMES = [
+    "A",
+    "B",
+    ]
+
+
+function f!(du, u, p, t)
+    # populations
+    A = u[1]
+    B = u[2]
+    
+    # constants
+    k = p[1]
+    
+    du[1] = A+B
+    du[2] = B^k^(-(max((foldl(mod, (A,B))),(k<A<B))))
+
+    return nothing
+end
+
+
+const u0 = initial_values()
+const p = constants()
+const tspan = (0.0, 10.0)
+const dt = 0.1
+
+# includes! "ode-support.jl"
+
+
+function simulation_output_to_csv(solution, io::IO)
+    println(io, "t,", join(VARIABLE_NAMES, ","))
+
+    for (t, u) in zip(solution.t, solution.u)
+        println(io, t, ",", join((@sprintf("%.4f", value) for value in u), ","))
+    end
+end
+
+
+function update_constants_with_params(p, params)
+    updated = copy(p)
+
+    for (name, value) in params
+        index = findfirst(==(name), CONSTANT_NAMES)
+        if index !== nothing
+            updated[index] = value
+        end
+    end
+
+    return updated
+end
+
+
+function parse_params(params::AbstractString)
+    return [
+        (name, parse(Float64, value))
+        for (name, value) in (split(param, "=") for param in split(params))
+    ]
+end
+
+
+function main(args=ARGS)
+    output = nothing
+    params = ""
+
+    i = 1
+    while i <= length(args)
+        if args[i] in ("-o", "--output")
+            output = args[i+1]
+            i += 2
+        elseif args[i] == "--params"
+            params = args[i+1]
+            i += 2
+        else
+            error("unknown argument $(args[i])")
+        end
+    end
+
+    problem = ODEProblem(f!, u0, tspan, update_constants_with_params(p, parse_params(params)))
+    solution = solve(problem; saveat=tspan[1]:dt:tspan[2])
+
+    if output === nothing
+        simulation_output_to_csv(solution, stdout)
+    else
+        open(io -> simulation_output_to_csv(solution, io), output, "w")
+    end
+end
+
+
+if abspath(PROGRAM_FILE) == @__FILE__
+    main()
+end
\ No newline at end of file
@@ -1,6 +1,9 @@
 import argparse, contextlib, sys, os
 import scipy
 import numpy as np
+np.seterr(divide="raise")
+
+
 
 def initial_values() -> np.ndarray:
     A_0 = 10.0
@@ -18,6 +21,13 @@ def constants() -> list:
         ]
 
 
+def constants_with_names() -> list:
+    constants_list = [
+        ("k", 0.5),
+        ]
+    return constants_list
+
+
 def variable_names() -> list[str]:
     return [
         "A",
@@ -28,17 +38,18 @@ def variable_names() -> list[str]:
 def system(t: np.float64, y: np.ndarray, *constants) -> np.ndarray:
     # populations
     A,B, = y
-
     # constants
     k, = constants
     
-    dA_dt = A + B 
-    dB_dt = (A + B ) * k 
+    dA_dt = A+B
+    dB_dt = (A+B)*k
 
     return np.array([dA_dt,dB_dt])
 
+# includes! "ode-support.py"
 
-def simulation_output_to_csv(sim_steps, simulation_output, write_to) -> str:
+
+def simulation_output_to_csv(sim_steps, simulation_output, write_to):
     if not simulation_output.success:
         print(simulation_output.message)
         return
@@ -53,32 +64,43 @@ def simulation_output_to_csv(sim_steps, simulation_output, write_to) -> str:
         write_to.write("\n")
 
 
-def plot_simulation(sim_steps, simulation_output, filename):
+COLORS = [
+    'tab:blue',
+    'tab:orange',
+    'tab:green',
+    'tab:red',
+    'tab:purple',
+    'tab:brown',
+    'tab:pink',
+    'tab:gray',
+    'tab:olive',
+    'tab:cyan',
+]
+
+def plot_simulation(sim_steps, simulation_output, filename, x_label="time (days)", y_label="conc/ml"):
     import matplotlib.pyplot as plt
     from matplotlib.backends.backend_pdf import PdfPages
 
     with PdfPages(filename) as pdf:
         # All
         all_fig, all_ax = plt.subplots()
-        all_ax.grid()
-        all_ax.set(title=__name__, xlabel="Time", ylabel="Concentration")
+        all_fig.set_size_inches(8, 6)
+        all_ax.set(title="", xlabel=x_label, ylabel=y_label)
 
         # Individually
-        for variable_name, variable_line_data in zip(variable_names(), simulation_output.y):
+        for i, (variable_name, variable_line_data) in enumerate(zip(variable_names(), simulation_output.y)):
             fig, ax = plt.subplots()
-            ax.grid()
-
+            fig.set_size_inches(8, 6)
             ax.set(
                 title=variable_name,
-                xlabel="Time",
-                ylabel="Concentration",
-            )
-
-            ax.plot(simulation_output.t, variable_line_data)
+                xlabel=x_label, 
+                ylabel=y_label, 
+            )            
+            ax.plot(simulation_output.t, variable_line_data, color=COLORS[i % len(COLORS)])
             all_ax.plot(simulation_output.t, variable_line_data)
 
             pdf.savefig(fig)
-
+        all_ax.legend(variable_names(),loc="best")
         pdf.savefig(all_fig)
 
 
@@ -89,43 +111,64 @@ def file_or_stdout(filename: str | None):
         return sys.stdout
 
 
-def simulate(filename, st=0, tf=10, dt=0.1, plot=False):
+def update_constants_with_params(constants, params):
+    updated_constants = constants.copy()
+
+    constant_names = [constant[0] for constant in constants]
+
+    for name, value in params.items():
+        for idx, (const_name, const_value) in enumerate(updated_constants):
+            if const_name == name:
+                updated_constants[idx] = (const_name, value)
+
+    return updated_constants
+
+
+
+def simulate(filename, st=0, tf=50, dt=0.1, plot=False, x_label="time (days)", y_label="conc/ml", params={}):
     sim_steps = np.arange(st, tf + dt, dt)
 
+    constants_values = [value for _, value in update_constants_with_params(constants_with_names(), params)]
+
     simulation_output = scipy.integrate.solve_ivp(
         fun=system,
-        t_span=(0, tf + dt),
+        t_span=(st, tf + dt * 2),
         y0=initial_values(),
-        args=constants(),
+        args=tuple(constants_values),
         t_eval=sim_steps,
     )
 
     if plot:
-        plot_simulation(sim_steps, simulation_output, filename)
-
+        plot_simulation(sim_steps, simulation_output, filename, x_label, y_label)
     else:
         with file_or_stdout(filename) as f:
             simulation_output_to_csv(sim_steps, simulation_output, f)
 
-
 if __name__ == "__main__":
     parser = argparse.ArgumentParser()
-
     parser.add_argument("--st", type=float, default=0)
-    parser.add_argument("--tf", type=float, default=10)
-    parser.add_argument("--dt", type=float, default=0.1)
+    parser.add_argument("--tf", type=float, default=50)
+    parser.add_argument("--dt", type=float, default=0.01)
     parser.add_argument("-o", "--output", default=None)
     parser.add_argument("--csv", action=argparse.BooleanOptionalAction)
+    parser.add_argument("--xlabel", type=str, default="time (days)")
+    parser.add_argument("--ylabel", type=str, default="conc/ml")
+    parser.add_argument("--params", type=str, default="")
 
     args = parser.parse_args()
 
-    if args.output is None and not args.csv:
-        parser.error("when plotting (a.k.a --no-csv), an output file name is required via --output")
-
-    if args.output:
-        dirs = os.path.dirname(args.output)
-
-        if dirs:
-            os.makedirs(dirs, exist_ok=True)
-
-    simulate(args.output, plot=not args.csv)
\ No newline at end of file
+    if args.params:
+        params = {k: float(v) for k, v in (param.split('=') for param in args.params.split())}
+    else:
+        params = {}
+
+    simulate(
+        args.output,
+        plot=not args.csv,
+        st=args.st,
+        tf=args.tf,
+        dt=args.dt,
+        x_label=args.xlabel,
+        y_label=args.ylabel,
+        params=params
+    )
\ No newline at end of file
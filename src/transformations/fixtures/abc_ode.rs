@@ -0,0 +1,128 @@
+//! _
+//!
+//! Generated by odeir. Populations and constants keep their names from the
+//! model, which needn't be snake case.
+#![allow(non_snake_case, unused_variables, unused_parens, clippy::all)]
+
+
+
+pub const START_TIME: f64 = 0.0;
+pub const END_TIME: f64 = 10.0;
+pub const DELTA_TIME: f64 = 0.1;
+
+/// Populations of the model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct State {
+    pub A: f64,
+    pub B: f64,
+    }
+
+impl State {
+    pub const NAMES: [&'static str; 2] = [
+        "A",
+        "B",
+        ];
+
+    pub fn to_array(&self) -> [f64; 2] {
+        [
+            self.A,
+            self.B,
+            ]
+    }
+
+    pub fn from_array(values: [f64; 2]) -> Self {
+        Self {
+            A: values[0],
+            B: values[1],
+            }
+    }
+}
+
+/// The initial values of the populations.
+impl Default for State {
+    fn default() -> Self {
+        Self {
+            A: 10.0,
+            B: 20.0,
+            }
+    }
+}
+
+/// Constants of the model.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Params {
+    pub k: f64,
+    }
+
+impl Params {
+    pub const NAMES: [&'static str; 1] = [
+        "k",
+        ];
+}
+
+/// The values of the constants in the model.
+impl Default for Params {
+    fn default() -> Self {
+        Self {
+            k: 0.5,
+            }
+    }
+}
+
+/// Time derivative of every population.
+pub fn rhs(t: f64, state: &State, params: &Params) -> State {
+    let State {
+        A,
+        B,
+        } = *state;
+    let Params {
+        k,
+        } = *params;
+
+    State {
+        A: A + B,
+        B: B.powf(k.powf((-(((A - B * (A / B).floor())).max(((k < A && A < B) as u8 as f64)))))),
+    }
+}
+
+// includes! "ode-support.rs"
+
+
+/// Advances `state` from `t` to `t + h` with the classic Runge-Kutta method.
+pub fn rk4_step(t: f64, h: f64, state: &State, params: &Params) -> State {
+    let shifted = |k: &State, by: f64| {
+        let mut values = state.to_array();
+        for (value, k) in values.iter_mut().zip(k.to_array()) {
+            *value += by * k;
+        }
+        State::from_array(values)
+    };
+
+    let k1 = rhs(t, state, params);
+    let k2 = rhs(t + h / 2.0, &shifted(&k1, h / 2.0), params);
+    let k3 = rhs(t + h / 2.0, &shifted(&k2, h / 2.0), params);
+    let k4 = rhs(t + h, &shifted(&k3, h), params);
+
+    let mut values = state.to_array();
+    let ks = [k1, k2, k3, k4].map(|k| k.to_array());
+    for (i, value) in values.iter_mut().enumerate() {
+        *value += h / 6.0 * (ks[0][i] + 2.0 * ks[1][i] + 2.0 * ks[2][i] + ks[3][i]);
+    }
+    State::from_array(values)
+}
+
+/// Integrates from [`START_TIME`] to [`END_TIME`] in steps of
+/// [`DELTA_TIME`], returning the time and state at every step.
+pub fn simulate(params: &Params) -> Vec<(f64, State)> {
+    let steps = ((END_TIME - START_TIME) / DELTA_TIME).round() as usize;
+    let mut state = State::default();
+    let mut trajectory = Vec::with_capacity(steps + 1);
+
+    for step in 0..=steps {
+        let t = START_TIME + step as f64 * DELTA_TIME;
+        trajectory.push((t, state));
+        state = rk4_step(t, DELTA_TIME, &state, params);
+    }
+
+    trajectory
+}
\ No newline at end of file
@@ -0,0 +1,107 @@
+// _
+//
+// Generated by odeir.
+
+
+
+export interface State {
+  A: number;
+  B: number;
+}
+
+export interface Constants {
+  k: number;
+}
+
+export const variableNames: readonly (keyof State)[] = [
+  "A",
+  "B",
+];
+
+export const constantNames: readonly (keyof Constants)[] = [
+  "k",
+];
+
+export const initialValues: State = {
+  A: 10.0,
+  B: 20.0,
+};
+
+export const constants: Constants = {
+  k: 0.5,
+};
+
+export const startTime = 0.0;
+export const deltaTime = 0.1;
+export const endTime = 10.0;
+
+// Python's modulo, which takes the sign of the divisor
+function pyMod(a: number, b: number): number {
+  return a - b * Math.floor(a / b);
+}
+
+export function rhs(t: number, state: State, params: Constants): State {
+  const { A, B } = state;
+  const { k } = params;
+
+  return {
+    A: A + B,
+    B: B ** k ** (-(Math.max((pyMod(A, B)), (Number(k < A && A < B))))),
+  };
+}
+
+// includes! "ode-support.ts"
+
+
+export interface Sample {
+  t: number;
+  state: State;
+}
+
+function shifted(state: State, k: State, by: number): State {
+  const result = { ...state };
+  for (const name of variableNames) {
+    result[name] += by * k[name];
+  }
+  return result;
+}
+
+// Advances `state` from `t` to `t + h` with the classic Runge-Kutta method
+export function rk4Step(t: number, h: number, state: State, params: Constants): State {
+  const k1 = rhs(t, state, params);
+  const k2 = rhs(t + h / 2, shifted(state, k1, h / 2), params);
+  const k3 = rhs(t + h / 2, shifted(state, k2, h / 2), params);
+  const k4 = rhs(t + h, shifted(state, k3, h), params);
+
+  const result = { ...state };
+  for (const name of variableNames) {
+    result[name] += (h / 6) * (k1[name] + 2 * k2[name] + 2 * k3[name] + k4[name]);
+  }
+  return result;
+}
+
+// Integrates from `startTime` to `endTime` in steps of `deltaTime`, with
+// `params` overriding the model's constants
+export function simulate(params: Partial<Constants> = {}): Sample[] {
+  const allParams: Constants = { ...constants, ...params };
+  const steps = Math.round((endTime - startTime) / deltaTime);
+  const samples: Sample[] = [];
+
+  let state = initialValues;
+  for (let step = 0; step <= steps; step++) {
+    const t = startTime + step * deltaTime;
+    samples.push({ t, state });
+    state = rk4Step(t, deltaTime, state, allParams);
+  }
+
+  return samples;
+}
+
+// The same layout as the Python script's CSV output
+export function toCsv(samples: Sample[]): string {
+  const lines = [["t", ...variableNames].join(",")];
+  for (const { t, state } of samples) {
+    lines.push([String(t), ...variableNames.map((name) => state[name].toFixed(4))].join(","));
+  }
+  return lines.join("\n") + "\n";
+}
\ No newline at end of file
@@ -0,0 +1,17 @@
+function dy = model_rhs(t, y, p)
+  % _
+  %
+  % Time derivative of the populations in y, given the constants in p.
+  % Pass it to ode45 as @(t, y) model_rhs(t, y, p).
+
+  % populations
+  A = y(1);
+  B = y(2);
+  
+  % constants
+  k = p(1);
+  
+  dy = zeros(2, 1);
+  dy(1) = A + B;
+  dy(2) = power(B, power(k, (-(max((mod(A, B)), (k < A & A < B))))));
+end
\ No newline at end of file
@@ -0,0 +1,43 @@
+% _
+%
+% Solves the model with ode45 and writes CSV to the file named by `output`,
+% or to stdout if it isn't set. Constants can be overridden through a
+% `params` struct, for example:
+%
+%   octave --eval "params = struct('k', 0.7); output = 'out.csv'; simulate"
+%
+% model_rhs.m must be in the same directory.
+
+variable_names = {'A', 'B'};
+constant_names = {'k'};
+
+y0 = [10.0; 20.0];
+p = [0.5];
+tspan = 0.0:0.1:10.0;
+
+if exist('params', 'var')
+  for i = 1:numel(constant_names)
+    if isfield(params, constant_names{i})
+      p(i) = params.(constant_names{i});
+    end
+  end
+end
+
+[t, y] = ode45(@(t, y) model_rhs(t, y, p), tspan, y0);
+
+if exist('output', 'var')
+  fid = fopen(output, 'w');
+else
+  fid = 1;
+end
+
+fprintf(fid, 't,%s\n', strjoin(variable_names, ','));
+for i = 1:numel(t)
+  fprintf(fid, '%g', t(i));
+  fprintf(fid, ',%.4f', y(i, :));
+  fprintf(fid, '\n');
+end
+
+if fid ~= 1
+  fclose(fid);
+end
\ No newline at end of file
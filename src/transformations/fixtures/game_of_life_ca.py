@@ -0,0 +1,217 @@
+import argparse, contextlib, sys, os
+import numpy as np
+
+
+
+SEED = 3
+LATTICE = "square"
+
+
+def state_names() -> list[str]:
+    return [
+        "alive",
+        "dead",
+        ]
+
+
+# Indexed as [z, y, x], whatever the lattice
+def initial_grid() -> np.ndarray:
+    return np.array([
+        [
+            [1, 1, 1, 1, 1],
+            [1, 1, 1, 1, 1],
+            [1, 0, 0, 0, 1],
+            [1, 1, 1, 1, 1],
+            [1, 1, 1, 1, 1],
+            ],
+        ], dtype=np.int64)
+
+
+def constants_with_names() -> list:
+    constants_list = [
+        ("three", 3.0),
+        ("two", 2.0),
+        ]
+    return constants_list
+
+
+# (dz, dy, dx) offsets to the neighbours of a cell
+NEIGHBOURS = [
+    (0, -1, -1),
+    (0, -1, 0),
+    (0, -1, 1),
+    (0, 0, -1),
+    (0, 0, 1),
+    (0, 1, -1),
+    (0, 1, 0),
+    (0, 1, 1),
+    ]
+
+
+def neighbour_sum(values: np.ndarray, offsets: list) -> np.ndarray:
+    return sum(
+        np.roll(values, (-dz, -dy, -dx), axis=(0, 1, 2))
+        for dz, dy, dx in offsets
+    )
+
+
+def neighbour_count(grid: np.ndarray, state: int) -> np.ndarray:
+    in_state = (grid == state).astype(np.float64)
+
+    return neighbour_sum(in_state, NEIGHBOURS)
+
+
+def as_float(value) -> np.ndarray:
+    return np.asarray(value, dtype=np.float64)
+
+
+def step(grid: np.ndarray, rng: np.random.Generator, *constants) -> np.ndarray:
+    # neighbour counts
+    alive = neighbour_count(grid, 0)
+    dead = neighbour_count(grid, 1)
+    
+    # constants
+    three,two, = constants
+    # current states
+    is_alive = (grid == 0).astype(np.float64)
+    is_dead = (grid == 1).astype(np.float64)
+    
+    next_grid = grid.copy()
+    undecided = np.ones(grid.shape, dtype=bool)
+
+    to_alive = as_float(alive==three)
+    fires = undecided & (rng.random(grid.shape) < to_alive)
+    next_grid[fires] = 0
+    undecided &= ~fires
+
+    to_dead = as_float(as_float(alive<two)+as_float(alive>three))
+    fires = undecided & (rng.random(grid.shape) < to_dead)
+    next_grid[fires] = 1
+    undecided &= ~fires
+
+    return next_grid
+
+# includes! "ca-support.py"
+def simulation_output_to_csv(grids, write_to):
+    write_to.write(f"step,{','.join(state_names())}\n")
+
+    for step_number, grid in enumerate(grids):
+        counts = np.bincount(grid.ravel(), minlength=len(state_names()))
+        write_to.write(f"{step_number},")
+        write_to.write(",".join(str(count) for count in counts))
+        write_to.write("\n")
+
+
+COLORS = [
+    'tab:blue',
+    'tab:orange',
+    'tab:green',
+    'tab:red',
+    'tab:purple',
+    'tab:brown',
+    'tab:pink',
+    'tab:gray',
+    'tab:olive',
+    'tab:cyan',
+]
+
+def plot_simulation(grids, filename, x_label="step", y_label="cells"):
+    import matplotlib.pyplot as plt
+    from matplotlib.backends.backend_pdf import PdfPages
+    from matplotlib.colors import ListedColormap
+
+    counts = np.array([
+        np.bincount(grid.ravel(), minlength=len(state_names()))
+        for grid in grids
+    ])
+    colormap = ListedColormap([COLORS[i % len(COLORS)] for i in range(len(state_names()))])
+
+    with PdfPages(filename) as pdf:
+        # Population of each state over time
+        counts_fig, counts_ax = plt.subplots()
+        counts_fig.set_size_inches(8, 6)
+        counts_ax.set(title="", xlabel=x_label, ylabel=y_label)
+
+        for i, state_name in enumerate(state_names()):
+            counts_ax.plot(counts[:, i], color=COLORS[i % len(COLORS)])
+
+        counts_ax.legend(state_names(), loc="best")
+        pdf.savefig(counts_fig)
+
+        # Linear automata are drawn one generation per row, others by their
+        # first and last grids (the first layer of cubic ones)
+        if LATTICE == "linear":
+            images = (("space-time", np.array([grid[0, 0] for grid in grids])),)
+        else:
+            images = (("initial", grids[0][0]), ("final", grids[-1][0]))
+
+        for title, image in images:
+            fig, ax = plt.subplots()
+            fig.set_size_inches(8, 8)
+            ax.set(title=title)
+            ax.imshow(image, cmap=colormap, vmin=0, vmax=len(state_names()) - 1, interpolation="nearest")
+
+            pdf.savefig(fig)
+
+
+def file_or_stdout(filename: str | None):
+    if filename:
+        return open(filename, 'w')
+    else:
+        return sys.stdout
+
+
+def update_constants_with_params(constants, params):
+    updated_constants = constants.copy()
+
+    for name, value in params.items():
+        for idx, (const_name, const_value) in enumerate(updated_constants):
+            if const_name == name:
+                updated_constants[idx] = (const_name, value)
+
+    return updated_constants
+
+
+
+def simulate(filename, steps=100, seed=SEED, plot=False, x_label="step", y_label="cells", params={}):
+    rng = np.random.default_rng(seed)
+
+    constants_values = [value for _, value in update_constants_with_params(constants_with_names(), params)]
+
+    grids = [initial_grid()]
+
+    for _ in range(steps):
+        grids.append(step(grids[-1], rng, *constants_values))
+
+    if plot:
+        plot_simulation(grids, filename, x_label, y_label)
+    else:
+        with file_or_stdout(filename) as f:
+            simulation_output_to_csv(grids, f)
+
+if __name__ == "__main__":
+    parser = argparse.ArgumentParser()
+    parser.add_argument("--steps", type=int, default=100)
+    parser.add_argument("--seed", type=int, default=SEED)
+    parser.add_argument("-o", "--output", default=None)
+    parser.add_argument("--csv", action=argparse.BooleanOptionalAction)
+    parser.add_argument("--xlabel", type=str, default="step")
+    parser.add_argument("--ylabel", type=str, default="cells")
+    parser.add_argument("--params", type=str, default="")
+
+    args = parser.parse_args()
+
+    if args.params:
+        params = {k: float(v) for k, v in (param.split('=') for param in args.params.split())}
+    else:
+        params = {}
+
+    simulate(
+        args.output,
+        steps=args.steps,
+        seed=args.seed,
+        plot=not args.csv,
+        x_label=args.xlabel,
+        y_label=args.ylabel,
+        params=params
+    )
\ No newline at end of file
@@ -0,0 +1,323 @@
+//! Exports the graph of a model: its arguments are the nodes, components
+//! are edges into the composites that use them and equations are edges into
+//! the populations that they operate on.
+
+use std::fmt::Write;
+
+use crate::models::{Argument, CoreModel};
+use crate::Position;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum NodeKind {
+    Population,
+    Constant,
+    Composite,
+}
+
+struct Node<'a> {
+    id: String,
+    argument: &'a Argument,
+    kind: NodeKind,
+    position: Option<&'a Position>,
+}
+
+struct Edge<'a> {
+    from: &'a str,
+    to: &'a str,
+    label: String,
+    equation: bool,
+}
+
+/// Nodes in the order of the model's arguments, with IDs that are safe in
+/// either format.
+fn nodes(model: &CoreModel) -> Vec<Node<'_>> {
+    model
+        .arguments
+        .values()
+        .enumerate()
+        .map(|(i, argument)| {
+            let kind = match argument {
+                Argument::Composite { .. } => NodeKind::Composite,
+                Argument::Value { name, .. }
+                    if model
+                        .equations
+                        .iter()
+                        .any(|eq| eq.operates_on.as_ref() == Some(name)) =>
+                {
+                    NodeKind::Population
+                }
+                Argument::Value { .. } => NodeKind::Constant,
+            };
+            Node {
+                id: format!("n{i}"),
+                argument,
+                kind,
+                position: model.positions.get(argument.name()),
+            }
+        })
+        .collect()
+}
+
+fn edges(model: &CoreModel) -> Vec<Edge<'_>> {
+    let compositions = model
+        .arguments
+        .values()
+        .flat_map(|argument| match argument {
+            Argument::Composite {
+                name, composition, ..
+            } => composition
+                .iter()
+                .map(|component| Edge {
+                    from: &component.name,
+                    to: name,
+                    label: component.contribution.to_string(),
+                    equation: false,
+                })
+                .collect(),
+            Argument::Value { .. } => Vec::new(),
+        });
+    let equations = model.equations.iter().filter_map(|equation| {
+        Some(Edge {
+            from: &equation.argument,
+            to: equation.operates_on.as_deref()?,
+            label: format!("{} ({})", equation.name, equation.contribution),
+            equation: true,
+        })
+    });
+
+    compositions.chain(equations).collect()
+}
+
+fn label(argument: &Argument) -> String {
+    match argument {
+        Argument::Value { name, value } => format!("{name}\n{value}"),
+        Argument::Composite {
+            name, operation, ..
+        } => format!("{name}\n[{operation}]"),
+    }
+}
+
+/// Renders the model as a Graphviz digraph. Populations are ellipses,
+/// constants boxes and composites hexagons, while equations are bold edges.
+///
+/// Stored positions are pinned with `pos`, which `neato` and `fdp` honour,
+/// flipping the y axis since Graphviz's points upwards.
+pub fn render_dot(model: &CoreModel) -> String {
+    let nodes = nodes(model);
+    let id = |name: &str| {
+        nodes
+            .iter()
+            .find(|node| node.argument.name() == name)
+            .map(|node| node.id.as_str())
+    };
+    let quote = |text: &str| {
+        format!(
+            "\"{}\"",
+            text.replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('\n', "\\n")
+        )
+    };
+
+    let mut dot = String::from("digraph model {\n    rankdir=LR;\n");
+
+    for node in &nodes {
+        let attributes = match node.kind {
+            NodeKind::Population => "shape=ellipse, style=filled",
+            NodeKind::Constant => "shape=box",
+            NodeKind::Composite => "shape=hexagon",
+        };
+        write!(
+            dot,
+            "    {} [label={}, {attributes}",
+            node.id,
+            quote(&label(node.argument))
+        )
+        .unwrap();
+        if let Some(Position { x, y }) = node.position {
+            write!(dot, ", pos=\"{x},{}!\"", 0.0 - y).unwrap();
+        }
+        dot.push_str("];\n");
+    }
+
+    for edge in edges(model) {
+        let (Some(from), Some(to)) = (id(edge.from), id(edge.to)) else {
+            continue;
+        };
+        write!(dot, "    {from} -> {to} [label={}", quote(&edge.label)).unwrap();
+        if edge.equation {
+            dot.push_str(", style=bold");
+        }
+        dot.push_str("];\n");
+    }
+
+    dot.push_str("}\n");
+    dot
+}
+
+/// Renders the model as a Mermaid flowchart. Populations are stadiums,
+/// constants rectangles and composites hexagons, while equations are thick
+/// edges.
+///
+/// Mermaid can't place nodes, so stored positions only decide the
+/// direction of the chart and the order in which nodes are declared.
+pub fn render_mermaid(model: &CoreModel) -> String {
+    let mut nodes = nodes(model);
+    let id = |nodes: &[Node], name: &str| {
+        nodes
+            .iter()
+            .find(|node| node.argument.name() == name)
+            .map(|node| node.id.clone())
+    };
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "#quot;").replace('\n', "<br>"));
+
+    let positions: Vec<&Position> = nodes.iter().filter_map(|node| node.position).collect();
+    let extent = |coordinate: fn(&Position) -> f64| {
+        let values = positions.iter().map(|&position| coordinate(position));
+        values.clone().fold(f64::NEG_INFINITY, f64::max) - values.fold(f64::INFINITY, f64::min)
+    };
+    let direction = if positions.is_empty() || extent(|p| p.x) >= extent(|p| p.y) {
+        "LR"
+    } else {
+        "TB"
+    };
+    // Nodes without a position keep their order, after the others
+    nodes.sort_by(|a, b| match (a.position, b.position) {
+        (Some(a), Some(b)) => a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y)),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => std::cmp::Ordering::Equal,
+    });
+
+    let mut mermaid = format!("flowchart {direction}\n");
+
+    for node in &nodes {
+        let label = quote(&label(node.argument));
+        let shape = match node.kind {
+            NodeKind::Population => format!("([{label}])"),
+            NodeKind::Constant => format!("[{label}]"),
+            NodeKind::Composite => format!("{{{{{label}}}}}"),
+        };
+        writeln!(mermaid, "    {}{shape}", node.id).unwrap();
+    }
+
+    for edge in edges(model) {
+        let (Some(from), Some(to)) = (id(&nodes, edge.from), id(&nodes, edge.to)) else {
+            continue;
+        };
+        let arrow = if edge.equation { "==>" } else { "-->" };
+        writeln!(mermaid, "    {from} {arrow}|{}| {to}", quote(&edge.label)).unwrap();
+    }
+
+    mermaid
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::{Component, Equation};
+
+    use super::*;
+
+    fn model() -> CoreModel {
+        let mut model = CoreModel::new();
+        model.insert_argument(Argument::Value {
+            name: "A".into(),
+            value: 10.0,
+        });
+        model.insert_argument(Argument::Value {
+            name: "k".into(),
+            value: 0.5,
+        });
+        model.insert_argument(Argument::Composite {
+            name: "-A*k".into(),
+            operation: "*".into(),
+            style: Default::default(),
+            composition: vec![
+                Component {
+                    name: "A".into(),
+                    contribution: '-',
+                },
+                Component {
+                    name: "k".into(),
+                    contribution: '+',
+                },
+            ],
+        });
+        model.insert_equation(Equation {
+            name: "dA/dt".into(),
+            operates_on: Some("A".into()),
+            argument: "-A*k".into(),
+            contribution: '+',
+        });
+        model
+    }
+
+    #[test]
+    fn dot() {
+        let mut model = model();
+        model
+            .positions
+            .insert("A".into(), Position { x: 100.0, y: 50.0 });
+
+        assert_eq!(
+            render_dot(&model),
+            r#"digraph model {
+    rankdir=LR;
+    n0 [label="-A*k\n[*]", shape=hexagon];
+    n1 [label="A\n10", shape=ellipse, style=filled, pos="100,-50!"];
+    n2 [label="k\n0.5", shape=box];
+    n1 -> n0 [label="-"];
+    n2 -> n0 [label="+"];
+    n0 -> n1 [label="dA/dt (+)", style=bold];
+}
+"#
+        );
+    }
+
+    #[test]
+    fn mermaid() {
+        assert_eq!(
+            render_mermaid(&model()),
+            r#"flowchart LR
+    n0{{"-A*k<br>[*]"}}
+    n1(["A<br>10"])
+    n2["k<br>0.5"]
+    n1 -->|"-"| n0
+    n2 -->|"+"| n0
+    n0 ==>|"dA/dt (+)"| n1
+"#
+        );
+    }
+
+    #[test]
+    fn mermaid_follows_positions() {
+        let mut model = model();
+        for (name, x, y) in [("k", 0.0, 0.0), ("-A*k", 0.0, 100.0), ("A", 10.0, 200.0)] {
+            model.positions.insert(name.into(), Position { x, y });
+        }
+
+        let mermaid = render_mermaid(&model);
+        let declarations: Vec<&str> = mermaid.lines().skip(1).take(3).collect();
+
+        assert!(mermaid.starts_with("flowchart TB\n"));
+        assert_eq!(
+            declarations,
+            [
+                r#"    n2["k<br>0.5"]"#,
+                r#"    n0{{"-A*k<br>[*]"}}"#,
+                r#"    n1(["A<br>10"])"#
+            ]
+        );
+    }
+
+    #[test]
+    fn mermaid_takes_any_position() {
+        let mut model = model();
+        for (name, x) in [("k", f64::NAN), ("A", 0.0)] {
+            model.positions.insert(name.into(), Position { x, y: 0.0 });
+        }
+
+        let mermaid = render_mermaid(&model);
+        assert!(mermaid.find("n1(").unwrap() < mermaid.find("n2[").unwrap());
+    }
+}
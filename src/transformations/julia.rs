@@ -0,0 +1,60 @@
+use std::path::PathBuf;
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_julia`], called `julia`.
+pub struct Julia;
+
+impl Backend for Julia {
+    fn name(&self) -> &str {
+        "julia"
+    }
+
+    fn file_extension(&self) -> &str {
+        "jl"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode.jl.jinja", "ode-support.jl"], ".jl")
+    }
+}
+
+/// Renders a DifferentialEquations.jl script with an in-place `f!(du, u, p,
+/// t)`, `u0`, `p` and `tspan`. Running it writes the solution as CSV, with
+/// the same columns as the script from [`render_ode`](super::r4k::render_ode).
+///
+/// Only the `.jl` files among the model's extension files are included.
+pub fn render_julia(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    Julia.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transformations::test_models::{abc, named};
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        let julia = render_julia(&model, &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.jl");
+
+        assert_eq!(julia, EXPECTED);
+    }
+
+    #[test]
+    fn names_of_parameters_are_kept_clear() {
+        let julia = render_julia(&named("u", &["p", "t", "du"]), &[]).unwrap();
+
+        assert!(julia.contains("\n    u_ = u[1]\n"));
+        assert!(julia.contains("\n    du_ = p[1]\n    p_ = p[2]\n    t_ = p[3]\n"));
+        assert!(julia.contains("\n    du[1] = (p_*u_)+(-t_)+(-du_)\n"));
+    }
+}
@@ -0,0 +1,601 @@
+use minijinja::context;
+
+use super::templates::{Templates, BUILTIN_TEMPLATES};
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::{ode::OdeModel, Argument, Component, CompositionStyle, CoreModel};
+
+const GREEK: [&str; 35] = [
+    "alpha",
+    "beta",
+    "gamma",
+    "delta",
+    "epsilon",
+    "zeta",
+    "eta",
+    "theta",
+    "iota",
+    "kappa",
+    "lambda",
+    "mu",
+    "nu",
+    "xi",
+    "pi",
+    "rho",
+    "sigma",
+    "tau",
+    "upsilon",
+    "phi",
+    "chi",
+    "psi",
+    "omega",
+    "Gamma",
+    "Delta",
+    "Theta",
+    "Lambda",
+    "Xi",
+    "Pi",
+    "Sigma",
+    "Upsilon",
+    "Phi",
+    "Psi",
+    "Omega",
+    "varepsilon",
+];
+
+/// The backend of [`render_latex`], called `latex`.
+pub struct Latex;
+
+impl Backend for Latex {
+    fn name(&self) -> &str {
+        "latex"
+    }
+
+    fn file_extension(&self) -> &str {
+        "tex"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        render_with(context.model, context.templates)
+    }
+}
+
+/// Renders the model's equations as an `align` environment, followed by a
+/// table with the values of its constants and the initial values of its
+/// populations.
+///
+/// Expressions only have the parentheses that their precedence requires.
+/// Comparisons, which are 1 when they hold and 0 otherwise, are written as
+/// Iverson brackets.
+pub fn render_latex(model: &OdeModel) -> Result<String, TemplateError> {
+    render_with(model, &BUILTIN_TEMPLATES)
+}
+
+fn render_with(model: &OdeModel, templates: &Templates) -> Result<String, TemplateError> {
+    let value = |arg: &Argument| match arg {
+        Argument::Value { name, value } => context! {
+            symbol => identifier(name),
+            value => value.to_string(),
+        },
+        Argument::Composite { .. } => unreachable!(),
+    };
+    let populations = model.get_populations().map(value).collect::<Vec<_>>();
+    // Constants standing for numbers are written as the numbers instead
+    let constants = model
+        .get_constants()
+        .filter(|constant| !model.is_number(constant.name()))
+        .map(value)
+        .collect::<Vec<_>>();
+    let equations = model
+        .get_populations()
+        .filter_map(|population| {
+            let terms: Vec<(Expression, bool)> = model
+                .equations
+                .iter()
+                .filter(|eq| eq.operates_on.as_deref() == Some(population.name()))
+                .map(|eq| {
+                    let term = expression(model, &eq.argument, &mut Vec::new());
+                    (term, eq.contribution == '-')
+                })
+                .collect();
+            let (first, rest) = terms.split_first()?;
+            Some(context! {
+                population => identifier(population.name()),
+                rhs => sum(first, rest, false).latex,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    let ctx = context! {
+        name => escape(&model.name),
+        equations => equations,
+        populations => populations,
+        constants => constants,
+    };
+
+    templates.render(&["ode.tex.jinja"], ctx)
+}
+
+/// How tightly an expression binds, from loosest to tightest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Sum,
+    Product,
+    Power,
+    /// Fractions need no parentheses, except as the base of a power.
+    Fraction,
+    Atom,
+}
+
+#[derive(Debug)]
+struct Expression {
+    latex: String,
+    precedence: Precedence,
+}
+
+impl Expression {
+    fn new(latex: String, precedence: Precedence) -> Self {
+        Self { latex, precedence }
+    }
+
+    /// The expression, in parentheses if it binds looser than `precedence`.
+    fn at_least(&self, precedence: Precedence) -> String {
+        if self.precedence >= precedence {
+            self.latex.clone()
+        } else {
+            format!("\\left({}\\right)", self.latex)
+        }
+    }
+}
+
+fn expression<'a>(model: &'a CoreModel, name: &'a str, stack: &mut Vec<&'a str>) -> Expression {
+    let atom = |latex| Expression::new(latex, Precedence::Atom);
+
+    let Some(Argument::Composite {
+        operation,
+        style,
+        composition,
+        ..
+    }) = model.arguments.get(name)
+    else {
+        return match model.arguments.get(name) {
+            Some(Argument::Value { value, .. }) if model.is_number(name) => atom(value.to_string()),
+            _ => atom(identifier(name)),
+        };
+    };
+    if stack.contains(&name) {
+        return atom(identifier(name));
+    }
+
+    stack.push(name);
+    let operands: Vec<(Expression, bool)> = composition
+        .iter()
+        .map(|Component { name, contribution }| {
+            (expression(model, name, stack), *contribution == '-')
+        })
+        .collect();
+    stack.pop();
+
+    let list = |separator: &str| {
+        operands
+            .iter()
+            .map(|operand| signed(operand).latex)
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+
+    match (style, operation.as_str(), operands.as_slice()) {
+        (CompositionStyle::Infixed, "+" | "-", [first, rest @ ..]) => {
+            sum(first, rest, operation == "-")
+        }
+        (CompositionStyle::Infixed, "*", [first, rest @ ..]) => {
+            let factor =
+                |operand: &(Expression, bool)| signed(operand).at_least(Precedence::Product);
+            let mut factors = vec![first.0.at_least(Precedence::Product)];
+            factors.extend(rest.iter().map(factor));
+
+            let latex = factors.join(" \\cdot ");
+            if first.1 {
+                Expression::new(format!("-{latex}"), Precedence::Sum)
+            } else {
+                Expression::new(latex, Precedence::Product)
+            }
+        }
+        (CompositionStyle::Infixed, "/", [first, rest @ ..]) => {
+            let latex = rest.iter().fold(signed(first).latex, |numerator, operand| {
+                format!("\\frac{{{numerator}}}{{{}}}", signed(operand).latex)
+            });
+            Expression::new(latex, Precedence::Fraction)
+        }
+        (CompositionStyle::Infixed, "%", [first, rest @ ..]) => {
+            let mut latex = signed(first).at_least(Precedence::Product);
+            for operand in rest {
+                latex += &format!(" \\bmod {}", signed(operand).at_least(Precedence::Power));
+            }
+            Expression::new(latex, Precedence::Product)
+        }
+        (CompositionStyle::Infixed, "**" | "^", [_, _, ..])
+        | (CompositionStyle::Prefixed, "pow", [_, _]) => {
+            // Exponentiation is right associative
+            let (last, bases) = operands.split_last().unwrap();
+            let latex = bases
+                .iter()
+                .rev()
+                .fold(signed(last).latex, |exponent, base| {
+                    format!("{}^{{{exponent}}}", signed(base).at_least(Precedence::Atom))
+                });
+            Expression::new(latex, Precedence::Power)
+        }
+        (CompositionStyle::Infixed, "==" | "!=" | "<" | "<=" | ">" | ">=", [_, _, ..]) => {
+            let relation = match operation.as_str() {
+                "==" => "=",
+                "!=" => "\\neq",
+                "<=" => "\\leq",
+                ">=" => "\\geq",
+                other => other,
+            };
+            atom(format!("\\left[{}\\right]", list(&format!(" {relation} "))))
+        }
+        (CompositionStyle::Prefixed, "abs", [_]) => atom(format!("\\left|{}\\right|", list(""))),
+        (CompositionStyle::Prefixed, "sqrt", [_]) => atom(format!("\\sqrt{{{}}}", list(""))),
+        (CompositionStyle::Prefixed, "floor", [_]) => {
+            atom(format!("\\left\\lfloor {} \\right\\rfloor", list("")))
+        }
+        (CompositionStyle::Prefixed, "ceil", [_]) => {
+            atom(format!("\\left\\lceil {} \\right\\rceil", list("")))
+        }
+        (CompositionStyle::Prefixed, function, _) => atom(format!(
+            "\\operatorname{{{}}}\\left({}\\right)",
+            escape(function),
+            list(", ")
+        )),
+        (CompositionStyle::Infixed, operation, _) => {
+            Expression::new(list(&format!(" {} ", escape(operation))), Precedence::Sum)
+        }
+    }
+}
+
+/// A negated operand, as it'd be written on its own.
+fn signed((operand, negative): &(Expression, bool)) -> Expression {
+    if *negative {
+        Expression::new(
+            format!("-{}", operand.at_least(Precedence::Product)),
+            Precedence::Sum,
+        )
+    } else {
+        Expression::new(operand.latex.clone(), operand.precedence)
+    }
+}
+
+/// `first` followed by `rest`, which are subtracted instead of added when
+/// `subtract` is set.
+fn sum(first: &(Expression, bool), rest: &[(Expression, bool)], subtract: bool) -> Expression {
+    let mut latex = signed(first).latex;
+    for (operand, negative) in rest {
+        // Subtracting a negative operand is adding it, and vice versa
+        if subtract != *negative {
+            latex += &format!(" - {}", operand.at_least(Precedence::Product));
+        } else if operand.latex.starts_with('-') {
+            latex += &format!(" + \\left({}\\right)", operand.latex);
+        } else {
+            latex += &format!(" + {}", operand.latex);
+        }
+    }
+    Expression::new(latex, Precedence::Sum)
+}
+
+/// Writes a name as a symbol: Greek letters by their command, multi-letter
+/// names in italics and whatever follows an underscore as a subscript.
+fn identifier(name: &str) -> String {
+    match name.split_once('_') {
+        Some((base, subscript)) if !base.is_empty() && !subscript.is_empty() => {
+            format!("{}_{{{}}}", symbol(base), identifier(subscript))
+        }
+        _ => symbol(name),
+    }
+}
+
+fn symbol(name: &str) -> String {
+    if GREEK.contains(&name) {
+        format!("\\{name}")
+    } else if name.chars().count() == 1 && name.chars().all(char::is_alphanumeric) {
+        name.to_owned()
+    } else if name.chars().all(char::is_alphanumeric) {
+        format!("\\mathit{{{name}}}")
+    } else {
+        format!("\\text{{{}}}", escape(name))
+    }
+}
+
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => escaped.push_str("\\textbackslash{}"),
+            '~' => escaped.push_str("\\textasciitilde{}"),
+            '^' => escaped.push_str("\\textasciicircum{}"),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::models::CompositionStyle::{Infixed, Prefixed};
+    use crate::transformations::test_models::{arg, argument, composite, value};
+    use crate::Equation;
+
+    use super::*;
+
+    fn latex(arguments: impl IntoIterator<Item = Argument>, name: &str) -> String {
+        let mut model = CoreModel::new();
+        for (name, v) in [("a", 1.0), ("b", 2.0), ("c", 3.0)] {
+            model.insert_argument(value(name, v));
+        }
+        for argument in arguments {
+            model.insert_argument(argument);
+        }
+        expression(&model, name, &mut Vec::new()).latex
+    }
+
+    #[test]
+    fn parentheses_follow_precedence() {
+        let sum = composite("a+b", "+", Infixed, [arg("a"), arg("b")]);
+        let difference = composite("b-c", "-", Infixed, [arg("b"), arg("c")]);
+        let product = composite("a*b", "*", Infixed, [arg("a"), arg("b")]);
+
+        assert_eq!(
+            latex(
+                [
+                    sum.clone(),
+                    composite("(a+b)*c", "*", Infixed, [arg("a+b"), arg("c")])
+                ],
+                "(a+b)*c"
+            ),
+            "\\left(a + b\\right) \\cdot c"
+        );
+        assert_eq!(
+            latex(
+                [
+                    product.clone(),
+                    composite("a*b+c", "+", Infixed, [arg("a*b"), arg("c")])
+                ],
+                "a*b+c"
+            ),
+            "a \\cdot b + c"
+        );
+        assert_eq!(
+            latex(
+                [
+                    difference.clone(),
+                    composite("a-(b-c)", "-", Infixed, [arg("a"), arg("b-c")])
+                ],
+                "a-(b-c)"
+            ),
+            "a - \\left(b - c\\right)"
+        );
+        assert_eq!(
+            latex(
+                [
+                    difference,
+                    composite("(b-c)-a", "-", Infixed, [arg("b-c"), arg("a")])
+                ],
+                "(b-c)-a"
+            ),
+            "b - c - a"
+        );
+        assert_eq!(
+            latex(
+                [composite(
+                    "a+-b",
+                    "+",
+                    Infixed,
+                    [arg("a"), argument("b", '-')]
+                )],
+                "a+-b"
+            ),
+            "a - b"
+        );
+        assert_eq!(
+            latex(
+                [composite(
+                    "a--b",
+                    "-",
+                    Infixed,
+                    [arg("a"), argument("b", '-')]
+                )],
+                "a--b"
+            ),
+            "a + b"
+        );
+        assert_eq!(
+            latex(
+                [
+                    product,
+                    composite("a*-(a*b)", "*", Infixed, [arg("a"), argument("a*b", '-')])
+                ],
+                "a*-(a*b)"
+            ),
+            "a \\cdot \\left(-a \\cdot b\\right)"
+        );
+    }
+
+    #[test]
+    fn fractions_and_powers() {
+        let sum = composite("a+b", "+", Infixed, [arg("a"), arg("b")]);
+        let quotient = composite("a/b", "/", Infixed, [arg("a"), arg("b")]);
+
+        assert_eq!(
+            latex(
+                [
+                    sum.clone(),
+                    composite("(a+b)/c/a", "/", Infixed, [arg("a+b"), arg("c"), arg("a")])
+                ],
+                "(a+b)/c/a"
+            ),
+            "\\frac{\\frac{a + b}{c}}{a}"
+        );
+        assert_eq!(
+            latex(
+                [
+                    quotient.clone(),
+                    composite("c*a/b", "*", Infixed, [arg("c"), arg("a/b")])
+                ],
+                "c*a/b"
+            ),
+            "c \\cdot \\frac{a}{b}"
+        );
+        assert_eq!(
+            latex(
+                [composite(
+                    "a^b^c",
+                    "**",
+                    Infixed,
+                    [arg("a"), arg("b"), arg("c")]
+                )],
+                "a^b^c"
+            ),
+            "a^{b^{c}}"
+        );
+        assert_eq!(
+            latex(
+                [
+                    quotient,
+                    sum,
+                    composite("(a/b)^(a+b)", "**", Infixed, [arg("a/b"), arg("a+b")])
+                ],
+                "(a/b)^(a+b)"
+            ),
+            "\\left(\\frac{a}{b}\\right)^{a + b}"
+        );
+    }
+
+    #[test]
+    fn functions_and_comparisons() {
+        assert_eq!(
+            latex(
+                [composite("max", "max", Prefixed, [arg("a"), arg("b")])],
+                "max"
+            ),
+            "\\operatorname{max}\\left(a, b\\right)"
+        );
+        assert_eq!(
+            latex([composite("sqrt", "sqrt", Prefixed, [arg("a")])], "sqrt"),
+            "\\sqrt{a}"
+        );
+        assert_eq!(
+            latex(
+                [composite(
+                    "a<=b<c",
+                    "<=",
+                    Infixed,
+                    [arg("a"), arg("b"), arg("c")]
+                )],
+                "a<=b<c"
+            ),
+            "\\left[a \\leq b \\leq c\\right]"
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(identifier("x"), "x");
+        assert_eq!(identifier("alpha"), "\\alpha");
+        assert_eq!(identifier("beta_xy"), "\\beta_{\\mathit{xy}}");
+        assert_eq!(identifier("S_0"), "S_{0}");
+        assert_eq!(identifier("prey"), "\\mathit{prey}");
+        assert_eq!(identifier("a&b"), "\\text{a\\&b}");
+    }
+
+    #[test]
+    fn render_lotka_volterra() {
+        let mut model = OdeModel::new("Lotka & Volterra".into(), Metadata::default());
+        for (name, v) in [
+            ("alpha", 1.1),
+            ("beta", 0.4),
+            ("gamma", 0.4),
+            ("delta", 0.1),
+            ("x", 10.0),
+            ("y", 5.0),
+        ] {
+            model.insert_argument(value(name, v));
+        }
+        model.insert_argument(composite("alpha_x", "*", Infixed, [arg("alpha"), arg("x")]));
+        model.insert_argument(composite(
+            "beta_xy",
+            "*",
+            Infixed,
+            [arg("beta"), arg("x"), arg("y")],
+        ));
+        model.insert_argument(composite(
+            "dx",
+            "-",
+            Infixed,
+            [arg("alpha_x"), arg("beta_xy")],
+        ));
+        model.insert_argument(composite(
+            "delta_xy",
+            "*",
+            Infixed,
+            [arg("delta"), arg("x"), arg("y")],
+        ));
+        model.insert_argument(composite("gamma_y", "*", Infixed, [arg("gamma"), arg("y")]));
+        model.insert_argument(composite(
+            "dy",
+            "+",
+            Infixed,
+            [argument("gamma_y", '-'), arg("delta_xy")],
+        ));
+        for (population, argument) in [("x", "dx"), ("y", "dy")] {
+            model.insert_equation(Equation {
+                name: format!("d{population}/dt"),
+                operates_on: Some(population.into()),
+                argument: argument.into(),
+                contribution: '+',
+            });
+        }
+
+        const EXPECTED: &str = include_str!("fixtures/lotka_volterra.tex");
+
+        assert_eq!(render_latex(&model).unwrap(), EXPECTED);
+    }
+
+    #[test]
+    fn numbers_are_written_as_numbers() {
+        let model = crate::import::txt::import_txt("x = 1\ndx_dt = -0.5*x\n").unwrap();
+
+        let latex = render_latex(&model).unwrap();
+
+        assert!(
+            latex.contains("\\frac{dx}{dt} &= -0.5 \\cdot x\n"),
+            "{latex}"
+        );
+        assert!(!latex.contains("c0"), "{latex}");
+    }
+
+    #[test]
+    fn equations_of_a_population_add_up() {
+        let mut model = OdeModel::new("Decay".into(), Metadata::default());
+        for (name, v) in [("x", 1.0), ("a", 0.5), ("b", 0.1)] {
+            model.insert_argument(value(name, v));
+        }
+        model.insert_argument(composite("b+x", "+", Infixed, [arg("b"), arg("x")]));
+        for (name, argument, contribution) in [("in", "a", '+'), ("out", "b+x", '-')] {
+            model.insert_equation(Equation {
+                name: name.into(),
+                operates_on: Some("x".into()),
+                argument: argument.into(),
+                contribution,
+            });
+        }
+
+        let latex = render_latex(&model).unwrap();
+
+        assert!(latex.contains("\\frac{dx}{dt} &= a - \\left(b + x\\right)\n"));
+    }
+}
@@ -1,7 +1,26 @@
 use crate::models::Argument;
 
+pub mod backend;
+pub mod c;
+pub mod ca;
+mod filters;
+pub mod graph;
+pub mod julia;
+pub mod latex;
+pub mod octave;
 pub mod ode;
+pub mod r;
 pub mod r4k;
+pub mod rust;
+pub mod sbml;
+pub mod templates;
+#[cfg(test)]
+mod test_models;
+pub mod typescript;
+pub mod xpp;
+
+pub use backend::{Backend, Registry, RenderContext, RenderError, RenderableModel};
+pub use templates::{TemplateError, Templates};
 
 impl crate::models::CoreModel {
     pub fn get_arguments_where<'a>(
@@ -28,4 +47,12 @@ impl crate::models::CoreModel {
             _ => false,
         })
     }
+
+    /// Whether `name` is a constant that an import added for a number in an
+    /// expression, which text formats can write as the number itself.
+    pub(crate) fn is_number(&self, name: &str) -> bool {
+        self.numbers.contains(name)
+            && matches!(self.arguments.get(name), Some(Argument::Value { .. }))
+            && self.get_constants().any(|constant| constant.name() == name)
+    }
 }
@@ -0,0 +1,121 @@
+use std::{io, path::Path};
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_octave`], called `octave`. It renders the driver
+/// script, with the function file as its support file.
+pub struct Octave;
+
+impl Octave {
+    fn render_rhs(context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode-rhs.m.jinja"], ".m")
+    }
+}
+
+impl Backend for Octave {
+    fn name(&self) -> &str {
+        "octave"
+    }
+
+    fn file_extension(&self) -> &str {
+        "m"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode-driver.m.jinja"], ".m")
+    }
+
+    fn render_support_files(
+        &self,
+        context: &RenderContext,
+    ) -> Result<Vec<(String, String)>, TemplateError> {
+        Ok(vec![(
+            OctaveFiles::RHS_FILE.to_owned(),
+            Self::render_rhs(context)?,
+        )])
+    }
+}
+
+/// The files of an Octave (or MATLAB) simulation. They must be saved as
+/// [`OctaveFiles::RHS_FILE`] and [`OctaveFiles::DRIVER_FILE`] in the same
+/// directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OctaveFiles {
+    /// Function file with the model's right hand side, for `ode45`.
+    pub rhs: String,
+    /// Script which solves the model and writes CSV.
+    pub driver: String,
+}
+
+impl OctaveFiles {
+    pub const RHS_FILE: &'static str = "model_rhs.m";
+    pub const DRIVER_FILE: &'static str = "simulate.m";
+
+    pub fn write_to(&self, directory: &Path) -> io::Result<()> {
+        std::fs::write(directory.join(Self::RHS_FILE), &self.rhs)?;
+        std::fs::write(directory.join(Self::DRIVER_FILE), &self.driver)
+    }
+}
+
+/// Renders a `model_rhs(t, y, p)` function file for `ode45` and a driver
+/// script over the metadata's time span. Running the driver writes the
+/// solution as CSV, with the same columns as the script from
+/// [`render_ode`](super::r4k::render_ode).
+///
+/// Octave finds functions by their file names, so extension files aren't
+/// included: they only need to be in the same directory.
+pub fn render_octave(model: &OdeModel) -> Result<OctaveFiles, TemplateError> {
+    let context = RenderContext::new(model, &[]);
+
+    Ok(OctaveFiles {
+        rhs: Octave::render_rhs(&context)?,
+        driver: Octave.render(&context)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transformations::test_models::{abc, named};
+    use crate::transformations::Registry;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        let octave = render_octave(&model).unwrap();
+
+        assert_eq!(octave.rhs, include_str!("fixtures/abc_rhs.m"));
+        assert_eq!(octave.driver, include_str!("fixtures/abc_simulate.m"));
+    }
+
+    #[test]
+    fn registry_renders_both_files() {
+        let model = abc();
+        let octave = render_octave(&model).unwrap();
+        let registry = Registry::default();
+
+        assert_eq!(
+            registry.render("octave", &model, &[]).unwrap(),
+            octave.driver
+        );
+        assert_eq!(
+            registry
+                .render_support_files("octave", &model, &[])
+                .unwrap(),
+            [(OctaveFiles::RHS_FILE.to_owned(), octave.rhs)]
+        );
+    }
+
+    #[test]
+    fn names_of_parameters_are_kept_clear() {
+        let octave = render_octave(&named("y", &["p", "t", "dy"])).unwrap();
+
+        assert!(octave.rhs.contains("\n  y_ = y(1);\n"));
+        assert!(octave
+            .rhs
+            .contains("\n  dy_ = p(1);\n  p_ = p(2);\n  t_ = p(3);\n"));
+    }
+}
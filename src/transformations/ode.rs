@@ -1,79 +1,332 @@
 use std::path::PathBuf;
 
-use minijinja::{context, Environment};
+use minijinja::context;
 
-use crate::{models::ode::OdeModel, Map, Model};
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::{ode::OdeModel, Argument, CompositionStyle, Equation};
 
-/*const ODE_TEMPLATE: &str = include_str!("../../templates/ode.txt.jinja");
+/// The backend of [`render_txt_with_equations`], called `text`.
+pub struct Text;
 
-pub fn render_ode(model: Model) -> String {
-    let env = Environment::new();
+impl Backend for Text {
+    fn name(&self) -> &str {
+        "text"
+    }
+
+    fn file_extension(&self) -> &str {
+        "txt"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        let model = context.model;
+
+        let values: Vec<(&str, String)> = model
+            .arguments
+            .values()
+            .filter_map(|argument| match argument {
+                Argument::Value { name, value } if !model.is_number(name) => {
+                    Some((name.as_str(), value.to_string()))
+                }
+                _ => None,
+            })
+            .collect();
+        let definitions: Vec<(&str, String)> = model
+            .arguments
+            .values()
+            .filter(|argument| matches!(argument, Argument::Composite { .. }))
+            .map(Argument::name)
+            .filter(|name| is_identifier(name))
+            .map(|name| (name, inline(model, name, &mut Vec::new()).0))
+            .collect();
+        let equations: Vec<(&str, String)> = model
+            .get_populations()
+            .map(|population| {
+                let terms: Vec<&Equation> = model
+                    .equations
+                    .iter()
+                    .filter(|eq| eq.operates_on.as_deref() == Some(population.name()))
+                    .collect();
+                let rhs = match terms.as_slice() {
+                    [only] if only.contribution != '-' => operand(
+                        model,
+                        &only.argument,
+                        Precedence::Comparison,
+                        &mut Vec::new(),
+                    ),
+                    terms => sum(
+                        model,
+                        terms
+                            .iter()
+                            .map(|eq| (eq.argument.as_str(), eq.contribution)),
+                        &mut Vec::new(),
+                    ),
+                };
+                (population.name(), rhs)
+            })
+            .collect();
+
+        let ctx = context! {
+            name => model.name,
+            start_time => model.metadata.start_time.to_string(),
+            delta_time => model.metadata.delta_time.to_string(),
+            end_time => model.metadata.end_time.to_string(),
+            values => values,
+            definitions => definitions,
+            equations => equations,
+            extensions => context.extensions_ending_with(""),
+        };
+
+        context.templates.render(&["ode.txt.jinja"], ctx)
+    }
+}
+
+/// Renders the model as plain text, with a `name = value` line for each
+/// value, a definition for each composite named like a variable and a
+/// `dX_dt = ...` line for each population. Other composites are written
+/// inline, and the constants standing for numbers as the numbers.
+///
+/// [`import_txt`](crate::import::txt::import_txt) reads this back.
+pub fn render_txt_with_equations(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    Text.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+fn is_identifier(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// How tightly an expression binds, to tell when it needs parentheses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    Comparison,
+    Sum,
+    Product,
+    Unary,
+    Power,
+    Atom,
+}
+
+/// The argument called `name` as an operand needing at least `context`.
+/// Composites not named like a variable are written out.
+fn operand<'a>(
+    model: &'a OdeModel,
+    name: &'a str,
+    context: Precedence,
+    stack: &mut Vec<&'a str>,
+) -> String {
+    let (text, precedence) = match model.arguments.get(name) {
+        Some(Argument::Value { value, .. }) if model.is_number(name) => {
+            (value.to_string(), Precedence::Atom)
+        }
+        Some(Argument::Composite { .. }) if !is_identifier(name) && !stack.contains(&name) => {
+            inline(model, name, stack)
+        }
+        _ => (name.to_owned(), Precedence::Atom),
+    };
+    if precedence >= context {
+        text
+    } else {
+        format!("({text})")
+    }
+}
 
-    let mut ctx = context! {
-        model => model,
+/// Like [`operand`], negated if `contribution` is `-`.
+fn signed<'a>(
+    model: &'a OdeModel,
+    name: &'a str,
+    contribution: char,
+    context: Precedence,
+    stack: &mut Vec<&'a str>,
+) -> String {
+    if contribution != '-' {
+        return operand(model, name, context, stack);
+    }
+    // `-b*c` reads as `(-b)*c`, which is the same in a sum
+    let inner = if context <= Precedence::Product {
+        Precedence::Product
+    } else {
+        Precedence::Power
     };
+    let text = format!("-{}", operand(model, name, inner, stack));
+    if Precedence::Unary >= context {
+        text
+    } else {
+        format!("({text})")
+    }
+}
+
+/// Terms joined by `+` and `-`, first negated if need be.
+fn sum<'a>(
+    model: &'a OdeModel,
+    terms: impl IntoIterator<Item = (&'a str, char)>,
+    stack: &mut Vec<&'a str>,
+) -> String {
+    let mut text = String::new();
+    for (i, (name, contribution)) in terms.into_iter().enumerate() {
+        if i == 0 {
+            text += &signed(model, name, contribution, Precedence::Product, stack);
+        } else {
+            text += if contribution == '-' { " - " } else { " + " };
+            text += &operand(model, name, Precedence::Product, stack);
+        }
+    }
+    text
+}
 
-    env.render_str(ODE_TEMPLATE, &mut ctx).unwrap()
-}*/
-
-const TXT_TEMPLATE: &str = include_str!("../../templates/ode.txt.jinja");
-
-pub fn render_txt_with_equations(model: &OdeModel, extension_lookup_paths: &[&PathBuf]) -> String {
-    let env = Environment::new();
-
-    let populations = model.get_populations().collect::<Vec<_>>();
-    let constants = model.get_constants().collect::<Vec<_>>();
-    let equations = model
-        .equations
-        .iter()
-        .cloned()
-        .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
-        .collect::<Map<_, _>>();
-
-    let extensions: Vec<String> = model
-        .extension_files
-        .iter()
-        .filter_map(|filename| {
-            let filename_as_path = PathBuf::from(filename);
-            let filename_as_path = &filename_as_path;
-            let full_path = extension_lookup_paths
-                .iter()
-                .find(|path| path.ends_with(filename))
-                .unwrap_or(&filename_as_path);
-
-            std::fs::read_to_string(full_path).ok()
-        })
-        .collect();
-
-    let mut ctx = context! {
-        model => model,
-        equations => equations,
-        populations => populations,
-        constants => constants,
-        extensions => extensions,
+/// The composition of the composite called `name`.
+fn inline<'a>(
+    model: &'a OdeModel,
+    name: &'a str,
+    stack: &mut Vec<&'a str>,
+) -> (String, Precedence) {
+    let Some(Argument::Composite {
+        operation,
+        style,
+        composition,
+        ..
+    }) = model.arguments.get(name)
+    else {
+        return (name.to_owned(), Precedence::Atom);
     };
 
-    env.render_str(TXT_TEMPLATE, &mut ctx).unwrap()
+    stack.push(name);
+    let mut join = |separator: &str, first: Precedence, rest: Precedence| {
+        composition
+            .iter()
+            .enumerate()
+            .map(|(i, component)| {
+                let context = if i == 0 { first } else { rest };
+                signed(
+                    model,
+                    &component.name,
+                    component.contribution,
+                    context,
+                    stack,
+                )
+            })
+            .collect::<Vec<_>>()
+            .join(separator)
+    };
+    let expression = match (style, operation.as_str()) {
+        (CompositionStyle::Prefixed, function) => (
+            format!(
+                "{function}({})",
+                join(", ", Precedence::Comparison, Precedence::Comparison)
+            ),
+            Precedence::Atom,
+        ),
+        (CompositionStyle::Infixed, "+") => (
+            sum(
+                model,
+                composition
+                    .iter()
+                    .map(|component| (component.name.as_str(), component.contribution)),
+                stack,
+            ),
+            Precedence::Sum,
+        ),
+        (CompositionStyle::Infixed, operation @ ("*" | "/" | "%")) => (
+            join(operation, Precedence::Unary, Precedence::Unary),
+            Precedence::Product,
+        ),
+        (CompositionStyle::Infixed, operation @ ("^" | "**")) => (
+            join(operation, Precedence::Atom, Precedence::Unary),
+            Precedence::Power,
+        ),
+        (CompositionStyle::Infixed, "-") => (
+            join(" - ", Precedence::Product, Precedence::Unary),
+            Precedence::Sum,
+        ),
+        (CompositionStyle::Infixed, operation) => (
+            join(&format!(" {operation} "), Precedence::Sum, Precedence::Sum),
+            Precedence::Comparison,
+        ),
+    };
+    stack.pop();
+    expression
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::models::ode::Metadata;
+    use crate::models::{Argument, Component};
+    use crate::Equation;
+
+    use super::*;
+
     #[test]
-    fn test_render_ode_abc_json() {
-        use super::*;
+    fn test_render_txt_abc() {
+        let mut model = OdeModel::new("_".into(), Metadata::import_defaults());
+
+        for (name, value) in [("A", 1.0), ("B", 2.0), ("C", 3.0)] {
+            model.insert_argument(Argument::Value {
+                name: name.into(),
+                value,
+            });
+        }
+
+        model.insert_argument(Argument::Composite {
+            name: "A*B".into(),
+            operation: "*".into(),
+            style: Default::default(),
+            composition: vec![
+                Component {
+                    name: "A".into(),
+                    contribution: '+',
+                },
+                Component {
+                    name: "B".into(),
+                    contribution: '+',
+                },
+            ],
+        });
+
+        model.insert_argument(Argument::Composite {
+            name: "A*B/C".into(),
+            operation: "/".into(),
+            style: Default::default(),
+            composition: vec![
+                Component {
+                    name: "A*B".into(),
+                    contribution: '+',
+                },
+                Component {
+                    name: "C".into(),
+                    contribution: '+',
+                },
+            ],
+        });
 
-        const ABC_JSON_STR: &str = include_str!("../../tests/fixtures/abc.json");
+        for (population, argument, contribution) in
+            [("A", "A*B", '+'), ("B", "A*B", '-'), ("C", "A*B/C", '+')]
+        {
+            model.insert_equation(Equation {
+                name: format!("d{population}/dt"),
+                operates_on: Some(population.into()),
+                argument: argument.into(),
+                contribution,
+            });
+        }
 
-        let model = serde_json::from_str::<Model>(ABC_JSON_STR).unwrap();
+        let txt = render_txt_with_equations(&model, &[]).unwrap();
 
-        let ode = render_ode(model);
+        const EXPECTED: &str = "@ name = _
+@ start_time = 0
+@ delta_time = 0.1
+@ end_time = 10
 
-        const EXPECTED: &str = "CoreModel:
+A = 1
+B = 2
+C = 3
 
-        - dA/dt =+ (A  * B ) +
-        - dB/dt =- (A  * B ) +
-        - dC/dt =+ (A  * B  / C ) +";
+dA_dt = A*B
+dB_dt = -A*B
+dC_dt = (A*B)/C
+";
 
-        assert_eq!(ode, EXPECTED);
+        assert_eq!(txt, EXPECTED);
     }
 }
@@ -0,0 +1,52 @@
+use std::path::PathBuf;
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_r`], called `r`.
+pub struct R;
+
+impl Backend for R {
+    fn name(&self) -> &str {
+        "r"
+    }
+
+    fn file_extension(&self) -> &str {
+        "R"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode.R.jinja", "ode-support.R"], ".R")
+    }
+}
+
+/// Renders an R script which solves the model with `deSolve::ode`. Running
+/// it writes the solution as CSV, with the same columns as the script from
+/// [`render_ode`](super::r4k::render_ode), and `--params "k=1 ..."`
+/// overrides the constants' values.
+///
+/// Only the `.R` files among the model's extension files are included.
+pub fn render_r(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    R.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transformations::test_models::abc;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        let r = render_r(&model, &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.R");
+
+        assert_eq!(r, EXPECTED);
+    }
+}
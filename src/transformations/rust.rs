@@ -0,0 +1,110 @@
+use std::path::PathBuf;
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_rust`], called `rust`.
+pub struct Rust;
+
+impl Backend for Rust {
+    fn name(&self) -> &str {
+        "rust"
+    }
+
+    fn file_extension(&self) -> &str {
+        "rs"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode.rs.jinja", "ode-support.rs"], ".rs")
+    }
+}
+
+/// Renders a Rust module with `State` and `Params` structs, whose defaults
+/// are the model's values, an `rhs` function and a fixed step RK4
+/// `simulate` over the metadata's time span. `odeir-macros` embeds it at
+/// compile time.
+///
+/// Only the `.rs` files among the model's extension files are included.
+pub fn render_rust(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    Rust.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::Command;
+
+    use crate::transformations::test_models::{
+        abc, assert_matches_reference, predator_prey, scratch_directory,
+    };
+    use crate::Map;
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        let rust = render_rust(&model, &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.rs");
+
+        assert_eq!(rust, EXPECTED);
+    }
+
+    #[test]
+    fn compiled_module_matches_reference() {
+        let model = predator_prey();
+        let directory = scratch_directory("rust");
+
+        std::fs::write(
+            directory.join("model.rs"),
+            render_rust(&model, &[]).unwrap(),
+        )
+        .unwrap();
+        let main = directory.join("main.rs");
+        std::fs::write(
+            &main,
+            r#"
+#[allow(dead_code)]
+mod model;
+
+fn main() {
+    let params = model::Params {
+        beta: 0.5,
+        ..Default::default()
+    };
+    for (t, state) in model::simulate(&params) {
+        let values = state.to_array().map(|value| value.to_string());
+        println!("{t},{}", values.join(","));
+    }
+}
+"#,
+        )
+        .unwrap();
+
+        let binary = directory.join("model");
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".into());
+        let compilation = Command::new(rustc)
+            .args(["--edition", "2021", "-D", "warnings", "-o"])
+            .arg(&binary)
+            .arg(&main)
+            .output()
+            .unwrap();
+        assert!(
+            compilation.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compilation.stderr)
+        );
+
+        let run = Command::new(&binary).output().unwrap();
+        assert!(run.status.success());
+        std::fs::remove_dir_all(&directory).unwrap();
+
+        let csv = String::from_utf8(run.stdout).unwrap();
+        assert_matches_reference(&model, &Map::from([("beta", 0.5)]), &csv, 1e-9);
+    }
+}
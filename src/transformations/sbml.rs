@@ -0,0 +1,808 @@
+//! SBML Level 3 Version 2 export, for tools such as COPASI and Tellurium.
+//!
+//! Populations become species in a single compartment of size 1, so their
+//! amounts and concentrations agree, and constants become parameters. A
+//! population's rate of change is the sum of the arguments of the equations
+//! operating on it, each negated if its contribution is `-`.
+
+use std::collections::BTreeSet;
+use std::fmt::{self, Write};
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::{ode::OdeModel, Argument, CompositionStyle, CoreModel, Equation};
+use crate::Map;
+
+pub(crate) const SBML_NAMESPACE: &str = "http://www.sbml.org/sbml/level3/version2/core";
+pub(crate) const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+pub(crate) const COMPARTMENT: &str = "default_compartment";
+
+/// The backend of [`render_sbml`], called `sbml`.
+pub struct Sbml;
+
+impl Backend for Sbml {
+    fn name(&self) -> &str {
+        "sbml"
+    }
+
+    fn file_extension(&self) -> &str {
+        "xml"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        render_sbml(context.model).map_err(|err| TemplateError {
+            file: self.name().to_owned(),
+            line: None,
+            message: err.to_string(),
+        })
+    }
+}
+
+/// A function which MathML has no equivalent for, such as one from an
+/// extension, so that SBML can't call it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnknownFunction(pub String);
+
+impl fmt::Display for UnknownFunction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "SBML has no function called `{}`", self.0)
+    }
+}
+
+impl std::error::Error for UnknownFunction {}
+
+/// Renders the model as an SBML document.
+///
+/// Equations sharing an argument become a reaction, whose rate is that
+/// argument, when they take from some populations (`-`) and give to others
+/// (`+`). Populations with any other equation get a rate rule instead, and
+/// so do the rest of the populations in that equation's reactions, since
+/// SBML doesn't let rules and reactions change the same species.
+///
+/// Fails if the model calls a function which MathML doesn't have.
+pub fn render_sbml(model: &OdeModel) -> Result<String, UnknownFunction> {
+    let populations: Vec<&Argument> = model.get_populations().collect();
+    let reactions = reactions(model);
+    let ids = Ids::new(model, &reactions);
+
+    let mut xml = Xml::default();
+    xml.out
+        .push_str("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    xml.open(
+        "sbml",
+        &[("xmlns", SBML_NAMESPACE), ("level", "3"), ("version", "2")],
+    );
+    let name = if model.name.is_empty() {
+        "model"
+    } else {
+        &model.name
+    };
+    xml.open("model", &[("id", &sid(name)), ("name", name)]);
+
+    xml.open("listOfCompartments", &[]);
+    xml.empty(
+        "compartment",
+        &[
+            ("id", COMPARTMENT),
+            ("spatialDimensions", "3"),
+            ("size", "1"),
+            ("constant", "true"),
+        ],
+    );
+    xml.close("listOfCompartments");
+
+    if !populations.is_empty() {
+        xml.open("listOfSpecies", &[]);
+        for population in &populations {
+            let Argument::Value { name, value } = population else {
+                continue;
+            };
+            xml.empty(
+                "species",
+                &[
+                    ("id", ids.argument(name)),
+                    ("name", name),
+                    ("compartment", COMPARTMENT),
+                    ("initialAmount", &value.to_string()),
+                    ("hasOnlySubstanceUnits", "true"),
+                    ("boundaryCondition", "false"),
+                    ("constant", "false"),
+                ],
+            );
+        }
+        xml.close("listOfSpecies");
+    }
+
+    let constants: Vec<&Argument> = model.get_constants().collect();
+    if !constants.is_empty() {
+        xml.open("listOfParameters", &[]);
+        for constant in constants {
+            let Argument::Value { name, value } = constant else {
+                continue;
+            };
+            xml.empty(
+                "parameter",
+                &[
+                    ("id", ids.argument(name)),
+                    ("name", name),
+                    ("value", &value.to_string()),
+                    ("constant", "true"),
+                ],
+            );
+        }
+        xml.close("listOfParameters");
+    }
+
+    let in_reaction = |population: &str| {
+        reactions
+            .values()
+            .flatten()
+            .any(|eq| eq.operates_on.as_deref() == Some(population))
+    };
+    let ruled: Vec<&str> = populations
+        .iter()
+        .map(|population| population.name())
+        .filter(|&population| !in_reaction(population))
+        .filter(|&population| {
+            model
+                .equations
+                .iter()
+                .any(|eq| eq.operates_on.as_deref() == Some(population))
+        })
+        .collect();
+
+    if !ruled.is_empty() {
+        xml.open("listOfRules", &[]);
+        for population in ruled {
+            let terms = model
+                .equations
+                .iter()
+                .filter(|eq| eq.operates_on.as_deref() == Some(population))
+                .map(|eq| {
+                    let term = mathml(model, &ids, &eq.argument, &mut Vec::new())?;
+                    Ok(if eq.contribution == '-' {
+                        MathMl::apply("minus", vec![term])
+                    } else {
+                        term
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            let rate = match <[MathMl; 1]>::try_from(terms) {
+                Ok([term]) => term,
+                Err(terms) => MathMl::apply("plus", terms),
+            };
+
+            xml.open("rateRule", &[("variable", ids.argument(population))]);
+            xml.math(&rate);
+            xml.close("rateRule");
+        }
+        xml.close("listOfRules");
+    }
+
+    if !reactions.is_empty() {
+        xml.open("listOfReactions", &[]);
+        for (argument, equations) in &reactions {
+            write_reaction(&mut xml, model, &ids, argument, equations)?;
+        }
+        xml.close("listOfReactions");
+    }
+
+    xml.close("model");
+    xml.close("sbml");
+    Ok(xml.out)
+}
+
+/// Equations grouped by their argument, for the groups which can be
+/// written as reactions.
+fn reactions(model: &CoreModel) -> Map<&str, Vec<&Equation>> {
+    let mut groups: Map<&str, Vec<&Equation>> = Map::new();
+    for equation in &model.equations {
+        if equation.operates_on.is_some() {
+            groups
+                .entry(equation.argument.as_str())
+                .or_default()
+                .push(equation);
+        }
+    }
+
+    let converts = |equations: &Vec<&Equation>| {
+        equations.iter().any(|eq| eq.contribution == '-')
+            && equations.iter().any(|eq| eq.contribution != '-')
+    };
+    let (mut reactions, mut rules): (Map<_, _>, Map<_, _>) = groups
+        .into_iter()
+        .partition(|(_, equations)| converts(equations));
+
+    // Populations changed by a rule can't take part in reactions, which may
+    // then turn more populations into ruled ones
+    loop {
+        let ruled = |population: Option<&str>| {
+            rules
+                .values()
+                .flatten()
+                .any(|eq: &&Equation| eq.operates_on.as_deref() == population)
+        };
+        let (keep, demote): (Map<_, _>, Map<_, _>) =
+            reactions.into_iter().partition(|(_, equations)| {
+                !equations
+                    .iter()
+                    .any(|eq: &&Equation| ruled(eq.operates_on.as_deref()))
+            });
+        reactions = keep;
+        if demote.is_empty() {
+            return reactions;
+        }
+        rules.extend(demote);
+    }
+}
+
+fn write_reaction(
+    xml: &mut Xml,
+    model: &CoreModel,
+    ids: &Ids,
+    argument: &str,
+    equations: &[&Equation],
+) -> Result<(), UnknownFunction> {
+    // Net stoichiometry of each population
+    let mut stoichiometries: Map<&str, i32> = Map::new();
+    for equation in equations {
+        let Some(population) = equation.operates_on.as_deref() else {
+            continue;
+        };
+        *stoichiometries.entry(population).or_default() +=
+            if equation.contribution == '-' { -1 } else { 1 };
+    }
+    let names = equations
+        .iter()
+        .map(|eq| eq.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    xml.open(
+        "reaction",
+        &[
+            ("id", ids.reaction(argument)),
+            ("name", &names),
+            ("reversible", "false"),
+        ],
+    );
+    for (list, sign) in [("listOfReactants", -1), ("listOfProducts", 1)] {
+        let species: Vec<(&str, i32)> = stoichiometries
+            .iter()
+            .filter(|(_, &n)| n.signum() == sign)
+            .map(|(&population, &n)| (population, n.abs()))
+            .collect();
+        if species.is_empty() {
+            continue;
+        }
+        xml.open(list, &[]);
+        for (population, n) in species {
+            xml.empty(
+                "speciesReference",
+                &[
+                    ("species", ids.argument(population)),
+                    ("stoichiometry", &n.to_string()),
+                    ("constant", "true"),
+                ],
+            );
+        }
+        xml.close(list);
+    }
+    xml.open("kineticLaw", &[]);
+    xml.math(&mathml(model, ids, argument, &mut Vec::new())?);
+    xml.close("kineticLaw");
+    xml.close("reaction");
+    Ok(())
+}
+
+/// The SBML identifier of every argument, and of the reaction of each
+/// argument which has one. A number tells apart names which make the same
+/// identifier, like `a-b` and `a_b`.
+struct Ids<'a> {
+    arguments: Map<&'a str, String>,
+    reactions: Map<&'a str, String>,
+}
+
+impl<'a> Ids<'a> {
+    fn new(model: &'a CoreModel, reactions: &Map<&'a str, Vec<&Equation>>) -> Self {
+        // Names which are identifiers already are kept
+        let (kept, renamed): (Vec<&str>, Vec<&str>) = model
+            .arguments
+            .keys()
+            .map(String::as_str)
+            .partition(|&name| sid(name) == name && name != COMPARTMENT);
+        let mut taken: BTreeSet<String> = kept.iter().map(|&name| name.to_owned()).collect();
+        taken.insert(COMPARTMENT.to_owned());
+        let mut unique = |base: String| {
+            let mut candidate = base.clone();
+            for suffix in 1.. {
+                if !taken.contains(&candidate) {
+                    break;
+                }
+                candidate = format!("{base}_{suffix}");
+            }
+            taken.insert(candidate.clone());
+            candidate
+        };
+
+        let mut arguments: Map<&str, String> =
+            kept.iter().map(|&name| (name, name.to_owned())).collect();
+        for name in renamed {
+            arguments.insert(name, unique(sid(name)));
+        }
+        let reactions = reactions
+            .keys()
+            .map(|&argument| {
+                let id = arguments
+                    .get(argument)
+                    .cloned()
+                    .unwrap_or_else(|| sid(argument));
+                (argument, unique(format!("reaction_{id}")))
+            })
+            .collect();
+        Self {
+            arguments,
+            reactions,
+        }
+    }
+
+    fn argument<'b>(&'b self, name: &'b str) -> &'b str {
+        self.arguments.get(name).map_or(name, String::as_str)
+    }
+
+    fn reaction<'b>(&'b self, argument: &'b str) -> &'b str {
+        self.reactions
+            .get(argument)
+            .map_or(argument, String::as_str)
+    }
+}
+
+/// Turns a name into an SBML identifier, replacing characters which aren't
+/// allowed with underscores.
+pub(crate) fn sid(name: &str) -> String {
+    let mut id: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !id.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        id.insert(0, '_');
+    }
+    id
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum MathMl {
+    Ci(String),
+    Cn(f64),
+    /// Applies a MathML operator, such as `plus`, to the arguments.
+    Apply(&'static str, Vec<MathMl>),
+    /// 1 if the condition holds, 0 otherwise.
+    Indicator(Box<MathMl>),
+}
+
+impl MathMl {
+    fn apply(operator: &'static str, arguments: Vec<MathMl>) -> Self {
+        Self::Apply(operator, arguments)
+    }
+
+    /// Folds binary `operator` over `arguments`, from the left.
+    fn fold_left(operator: &'static str, arguments: Vec<MathMl>) -> Self {
+        arguments
+            .into_iter()
+            .reduce(|acc, next| Self::apply(operator, vec![acc, next]))
+            .unwrap()
+    }
+}
+
+fn mathml<'a>(
+    model: &'a CoreModel,
+    ids: &Ids,
+    name: &'a str,
+    stack: &mut Vec<&'a str>,
+) -> Result<MathMl, UnknownFunction> {
+    let Some(Argument::Composite {
+        operation,
+        style,
+        composition,
+        ..
+    }) = model.arguments.get(name)
+    else {
+        return Ok(MathMl::Ci(ids.argument(name).to_owned()));
+    };
+    if stack.contains(&name) || composition.is_empty() {
+        return Ok(MathMl::Ci(ids.argument(name).to_owned()));
+    }
+
+    stack.push(name);
+    let operands = composition
+        .iter()
+        .map(|component| {
+            let operand = mathml(model, ids, &component.name, stack)?;
+            Ok(if component.contribution == '-' {
+                MathMl::apply("minus", vec![operand])
+            } else {
+                operand
+            })
+        })
+        .collect::<Result<Vec<MathMl>, _>>();
+    stack.pop();
+    let mut operands = operands?;
+
+    let relation = |operator| {
+        let pairs: Vec<MathMl> = operands
+            .windows(2)
+            .map(|pair| MathMl::apply(operator, pair.to_vec()))
+            .collect();
+        let condition = match <[MathMl; 1]>::try_from(pairs) {
+            Ok([pair]) => pair,
+            Err(pairs) => MathMl::apply("and", pairs),
+        };
+        MathMl::Indicator(Box::new(condition))
+    };
+
+    Ok(match (style, operation.as_str()) {
+        (_, _) if operands.len() == 1 && matches!(operation.as_str(), "+" | "*") => {
+            operands.pop().unwrap()
+        }
+        (CompositionStyle::Infixed, "+") => MathMl::apply("plus", operands),
+        (CompositionStyle::Infixed, "*") => MathMl::apply("times", operands),
+        (CompositionStyle::Infixed, "-") => MathMl::fold_left("minus", operands),
+        (CompositionStyle::Infixed, "/") => MathMl::fold_left("divide", operands),
+        (CompositionStyle::Infixed, "%") => {
+            // Python's modulo: a - b * floor(a / b)
+            operands
+                .into_iter()
+                .reduce(|a, b| {
+                    let quotient = MathMl::apply("divide", vec![a.clone(), b.clone()]);
+                    let floor = MathMl::apply("floor", vec![quotient]);
+                    MathMl::apply("minus", vec![a, MathMl::apply("times", vec![b, floor])])
+                })
+                .unwrap()
+        }
+        (CompositionStyle::Infixed, "**" | "^") | (CompositionStyle::Prefixed, "pow") => operands
+            .into_iter()
+            .rev()
+            .reduce(|exponent, base| MathMl::apply("power", vec![base, exponent]))
+            .unwrap(),
+        (CompositionStyle::Infixed, "==") => relation("eq"),
+        (CompositionStyle::Infixed, "!=") => relation("neq"),
+        (CompositionStyle::Infixed, "<") => relation("lt"),
+        (CompositionStyle::Infixed, "<=") => relation("leq"),
+        (CompositionStyle::Infixed, ">") => relation("gt"),
+        (CompositionStyle::Infixed, ">=") => relation("geq"),
+        (CompositionStyle::Prefixed, "min") => MathMl::apply("min", operands),
+        (CompositionStyle::Prefixed, "max") => MathMl::apply("max", operands),
+        (CompositionStyle::Prefixed, "abs") => MathMl::apply("abs", operands),
+        (CompositionStyle::Prefixed, "exp") => MathMl::apply("exp", operands),
+        (CompositionStyle::Prefixed, "log") => MathMl::apply("ln", operands),
+        (CompositionStyle::Prefixed, "sqrt") => MathMl::apply("root", operands),
+        (CompositionStyle::Prefixed, "sin") => MathMl::apply("sin", operands),
+        (CompositionStyle::Prefixed, "cos") => MathMl::apply("cos", operands),
+        (CompositionStyle::Prefixed, "tan") => MathMl::apply("tan", operands),
+        (CompositionStyle::Prefixed, "asin") => MathMl::apply("arcsin", operands),
+        (CompositionStyle::Prefixed, "acos") => MathMl::apply("arccos", operands),
+        (CompositionStyle::Prefixed, "atan") => MathMl::apply("arctan", operands),
+        (CompositionStyle::Prefixed, "sinh") => MathMl::apply("sinh", operands),
+        (CompositionStyle::Prefixed, "cosh") => MathMl::apply("cosh", operands),
+        (CompositionStyle::Prefixed, "tanh") => MathMl::apply("tanh", operands),
+        (CompositionStyle::Prefixed, "floor") => MathMl::apply("floor", operands),
+        (CompositionStyle::Prefixed, "ceil") => MathMl::apply("ceiling", operands),
+        (_, function) => return Err(UnknownFunction(function.to_owned())),
+    })
+}
+
+/// Writes indented XML, one element per line.
+#[derive(Default)]
+struct Xml {
+    out: String,
+    depth: usize,
+}
+
+impl Xml {
+    fn start(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        write!(self.out, "{:indent$}<{tag}", "", indent = self.depth * 2).unwrap();
+        for (name, value) in attributes {
+            write!(self.out, " {name}=\"{}\"", escape(value)).unwrap();
+        }
+    }
+
+    fn open(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.start(tag, attributes);
+        self.out.push_str(">\n");
+        self.depth += 1;
+    }
+
+    fn empty(&mut self, tag: &str, attributes: &[(&str, &str)]) {
+        self.start(tag, attributes);
+        self.out.push_str("/>\n");
+    }
+
+    fn close(&mut self, tag: &str) {
+        self.depth -= 1;
+        writeln!(self.out, "{:indent$}</{tag}>", "", indent = self.depth * 2).unwrap();
+    }
+
+    fn text(&mut self, tag: &str, text: &str) {
+        self.start(tag, &[]);
+        writeln!(self.out, "> {} </{tag}>", escape(text)).unwrap();
+    }
+
+    fn math(&mut self, math: &MathMl) {
+        self.open("math", &[("xmlns", MATHML_NAMESPACE)]);
+        self.mathml(math);
+        self.close("math");
+    }
+
+    fn mathml(&mut self, math: &MathMl) {
+        match math {
+            MathMl::Ci(name) => self.text("ci", name),
+            MathMl::Cn(value) => self.text("cn", &value.to_string()),
+            MathMl::Apply(operator, arguments) => {
+                self.open("apply", &[]);
+                self.empty(operator, &[]);
+                arguments.iter().for_each(|argument| self.mathml(argument));
+                self.close("apply");
+            }
+            MathMl::Indicator(condition) => {
+                self.open("piecewise", &[]);
+                self.open("piece", &[]);
+                self.mathml(&MathMl::Cn(1.0));
+                self.mathml(condition);
+                self.close("piece");
+                self.open("otherwise", &[]);
+                self.mathml(&MathMl::Cn(0.0));
+                self.close("otherwise");
+                self.close("piecewise");
+            }
+        }
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::models::CompositionStyle::{Infixed, Prefixed};
+    use crate::transformations::test_models::{arg, argument, composite, value};
+
+    use super::*;
+
+    fn equation(population: &str, argument: &str, contribution: char) -> Equation {
+        Equation {
+            name: format!("{argument} on {population}"),
+            operates_on: Some(population.into()),
+            argument: argument.into(),
+            contribution,
+        }
+    }
+
+    /// Susceptible, infected and recovered populations, plus a vaccinated
+    /// one whose growth isn't taken from anybody.
+    fn sirv() -> OdeModel {
+        let mut model = OdeModel::new("SIR & vaccines".into(), Metadata::default());
+        for (name, v) in [
+            ("S", 990.0),
+            ("I", 10.0),
+            ("R", 0.0),
+            ("V", 0.0),
+            ("beta", 0.0003),
+            ("gamma", 0.1),
+            ("nu", 2.0),
+        ] {
+            model.insert_argument(value(name, v));
+        }
+        model.insert_argument(composite(
+            "infection",
+            "*",
+            Infixed,
+            [arg("beta"), arg("S"), arg("I")],
+        ));
+        model.insert_argument(composite(
+            "recovery",
+            "*",
+            Infixed,
+            [arg("gamma"), arg("I")],
+        ));
+        model.insert_equation(equation("S", "infection", '-'));
+        model.insert_equation(equation("I", "infection", '+'));
+        model.insert_equation(equation("I", "recovery", '-'));
+        model.insert_equation(equation("R", "recovery", '+'));
+        model.insert_equation(equation("V", "nu", '+'));
+        model
+    }
+
+    fn children<'a, 'input>(
+        node: roxmltree::Node<'a, 'input>,
+        tag: &'a str,
+    ) -> impl Iterator<Item = roxmltree::Node<'a, 'input>> + 'a {
+        node.descendants().filter(move |n| n.has_tag_name(tag))
+    }
+
+    #[test]
+    fn species_parameters_reactions_and_rules() {
+        let sbml = render_sbml(&sirv()).unwrap();
+        let document = roxmltree::Document::parse(&sbml).unwrap();
+        let root = document.root_element();
+
+        assert_eq!(root.tag_name().namespace(), Some(SBML_NAMESPACE));
+        assert_eq!(root.attribute("level"), Some("3"));
+
+        let model = children(root, "model").next().unwrap();
+        assert_eq!(model.attribute("id"), Some("SIR___vaccines"));
+        assert_eq!(model.attribute("name"), Some("SIR & vaccines"));
+
+        let species: Vec<_> = children(root, "species")
+            .map(|s| {
+                (
+                    s.attribute("id").unwrap(),
+                    s.attribute("initialAmount").unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(species, [("I", "10"), ("R", "0"), ("S", "990"), ("V", "0")]);
+
+        let parameters: Vec<_> = children(root, "parameter")
+            .map(|p| p.attribute("id").unwrap())
+            .collect();
+        assert_eq!(parameters, ["beta", "gamma", "nu"]);
+
+        let reactions: Vec<_> = children(root, "reaction")
+            .map(|reaction| {
+                let references = |list| {
+                    children(reaction, list)
+                        .flat_map(|list| children(list, "speciesReference"))
+                        .map(|s| s.attribute("species").unwrap())
+                        .collect::<Vec<_>>()
+                };
+                (
+                    reaction.attribute("id").unwrap(),
+                    references("listOfReactants"),
+                    references("listOfProducts"),
+                )
+            })
+            .collect();
+        assert_eq!(
+            reactions,
+            [
+                ("reaction_infection", vec!["S"], vec!["I"]),
+                ("reaction_recovery", vec!["I"], vec!["R"]),
+            ]
+        );
+
+        // V only grows, so it needs a rule
+        let rules: Vec<_> = children(root, "rateRule")
+            .map(|rule| rule.attribute("variable").unwrap())
+            .collect();
+        assert_eq!(rules, ["V"]);
+    }
+
+    #[test]
+    fn rules_take_over_reactions_they_touch() {
+        let mut model = sirv();
+        // An extra term on I means it can't be changed by reactions
+        model.insert_equation(equation("I", "nu", '+'));
+
+        let sbml = render_sbml(&model).unwrap();
+        let document = roxmltree::Document::parse(&sbml).unwrap();
+        let root = document.root_element();
+
+        assert_eq!(children(root, "reaction").count(), 0);
+        let rules: Vec<_> = children(root, "rateRule")
+            .map(|rule| rule.attribute("variable").unwrap())
+            .collect();
+        assert_eq!(rules, ["I", "R", "S", "V"]);
+
+        // dI/dt = infection - recovery + nu
+        let rate = children(root, "rateRule").next().unwrap();
+        let terms: Vec<String> = children(rate, "math")
+            .next()
+            .unwrap()
+            .first_element_child()
+            .unwrap()
+            .children()
+            .filter(|n| n.is_element())
+            .map(|n| match n.tag_name().name() {
+                "ci" => n.text().unwrap().trim().to_owned(),
+                tag => tag.to_owned(),
+            })
+            .collect();
+        assert_eq!(terms, ["plus", "apply", "apply", "nu"]);
+    }
+
+    #[test]
+    fn mathml_of_composites() {
+        let mut model = CoreModel::new();
+        for name in ["a", "b", "c"] {
+            model.insert_argument(value(name, 1.0));
+        }
+        model.insert_argument(composite(
+            "a-b-c",
+            "-",
+            Infixed,
+            [arg("a"), argument("b", '-'), arg("c")],
+        ));
+        model.insert_argument(composite(
+            "a^b^c",
+            "**",
+            Infixed,
+            [arg("a"), arg("b"), arg("c")],
+        ));
+        model.insert_argument(composite(
+            "a<b<c",
+            "<",
+            Infixed,
+            [arg("a"), arg("b"), arg("c")],
+        ));
+
+        let ci = |name: &str| MathMl::Ci(name.into());
+        let ids = Ids::new(&model, &Map::new());
+        let mathml = |name| mathml(&model, &ids, name, &mut Vec::new()).unwrap();
+
+        assert_eq!(
+            mathml("a-b-c"),
+            MathMl::apply(
+                "minus",
+                vec![
+                    MathMl::apply(
+                        "minus",
+                        vec![ci("a"), MathMl::apply("minus", vec![ci("b")])]
+                    ),
+                    ci("c")
+                ]
+            )
+        );
+        assert_eq!(
+            mathml("a^b^c"),
+            MathMl::apply(
+                "power",
+                vec![ci("a"), MathMl::apply("power", vec![ci("b"), ci("c")])]
+            )
+        );
+        assert_eq!(
+            mathml("a<b<c"),
+            MathMl::Indicator(Box::new(MathMl::apply(
+                "and",
+                vec![
+                    MathMl::apply("lt", vec![ci("a"), ci("b")]),
+                    MathMl::apply("lt", vec![ci("b"), ci("c")])
+                ]
+            )))
+        );
+    }
+
+    #[test]
+    fn identifiers() {
+        assert_eq!(sid("beta"), "beta");
+        assert_eq!(sid("A+B"), "A_B");
+        assert_eq!(sid("2x"), "_2x");
+    }
+
+    #[test]
+    fn identifiers_are_told_apart() {
+        let mut model = sirv();
+        for name in ["a-b", "a_b", COMPARTMENT] {
+            model.insert_argument(value(name, 1.0));
+        }
+        let reactions = reactions(&model);
+        let ids = Ids::new(&model, &reactions);
+
+        assert_eq!(ids.argument("a_b"), "a_b");
+        assert_eq!(ids.argument("a-b"), "a_b_1");
+        assert_eq!(ids.argument(COMPARTMENT), "default_compartment_1");
+        assert_eq!(ids.reaction("infection"), "reaction_infection");
+    }
+
+    #[test]
+    fn functions_without_mathml_are_errors() {
+        let mut model = sirv();
+        model.insert_argument(composite("erf(I)", "erf", Prefixed, [arg("I")]));
+        model.insert_equation(equation("V", "erf(I)", '+'));
+
+        assert_eq!(render_sbml(&model), Err(UnknownFunction("erf".into())));
+    }
+}
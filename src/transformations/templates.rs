@@ -0,0 +1,358 @@
+//! The templates of the backends, which can be replaced by name without
+//! rebuilding the crate.
+//!
+//! Each backend renders a list of templates, such as `ode.py.jinja` followed
+//! by `ode-support.py`, and writes their outputs one after the other. Any of
+//! them can be swapped for a template of the same name, and the templates
+//! can `include` or `import` each other by name.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+use minijinja::value::Value;
+use minijinja::{Environment, ErrorKind, Source};
+
+use super::filters;
+use crate::Map;
+
+/// Names and sources of the templates embedded in the crate.
+const BUILTIN: [(&str, &str); 20] = [
+    ("macros.jinja", include_str!("../../templates/macros.jinja")),
+    ("ode.py.jinja", include_str!("../../templates/ode.py.jinja")),
+    (
+        "ode-support.py",
+        include_str!("../../templates/ode-support.py"),
+    ),
+    (
+        "ode.txt.jinja",
+        include_str!("../../templates/ode.txt.jinja"),
+    ),
+    ("ode.jl.jinja", include_str!("../../templates/ode.jl.jinja")),
+    (
+        "ode-support.jl",
+        include_str!("../../templates/ode-support.jl"),
+    ),
+    ("ode.R.jinja", include_str!("../../templates/ode.R.jinja")),
+    (
+        "ode-support.R",
+        include_str!("../../templates/ode-support.R"),
+    ),
+    ("ode.rs.jinja", include_str!("../../templates/ode.rs.jinja")),
+    (
+        "ode-support.rs",
+        include_str!("../../templates/ode-support.rs"),
+    ),
+    ("ode.ts.jinja", include_str!("../../templates/ode.ts.jinja")),
+    (
+        "ode-support.ts",
+        include_str!("../../templates/ode-support.ts"),
+    ),
+    ("ode.c.jinja", include_str!("../../templates/ode.c.jinja")),
+    (
+        "ode-support.c",
+        include_str!("../../templates/ode-support.c"),
+    ),
+    (
+        "ode.tex.jinja",
+        include_str!("../../templates/ode.tex.jinja"),
+    ),
+    (
+        "xpp.ode.jinja",
+        include_str!("../../templates/xpp.ode.jinja"),
+    ),
+    (
+        "ode-rhs.m.jinja",
+        include_str!("../../templates/ode-rhs.m.jinja"),
+    ),
+    (
+        "ode-driver.m.jinja",
+        include_str!("../../templates/ode-driver.m.jinja"),
+    ),
+    ("ca.py.jinja", include_str!("../../templates/ca.py.jinja")),
+    (
+        "ca-support.py",
+        include_str!("../../templates/ca-support.py"),
+    ),
+];
+
+/// Templates replacing the built-in ones with the same names, or adding
+/// new ones for the others to include.
+#[derive(Debug, Clone, Default)]
+pub struct Templates {
+    /// Sources by name, with the file each was read from.
+    overrides: Map<String, (String, Option<PathBuf>)>,
+    /// Every template parsed, built on first use.
+    compiled: OnceLock<Compiled>,
+}
+
+#[derive(Debug, Clone)]
+struct Compiled {
+    environment: Environment<'static>,
+    /// The templates which failed to parse, by name. They only fail the
+    /// backends which use them.
+    broken: Map<String, TemplateError>,
+}
+
+/// Only the built-in templates.
+pub(crate) static BUILTIN_TEMPLATES: Templates = Templates::new();
+
+impl Templates {
+    pub const fn new() -> Self {
+        Self {
+            overrides: Map::new(),
+            compiled: OnceLock::new(),
+        }
+    }
+
+    /// Reads every file in `directory` as a template named after the file.
+    pub fn from_directory(directory: impl AsRef<Path>) -> io::Result<Self> {
+        let mut templates = Self::new();
+        for entry in std::fs::read_dir(directory)? {
+            let path = entry?.path();
+            if !path.is_file() {
+                continue;
+            }
+            let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+                continue;
+            };
+            let source = std::fs::read_to_string(&path)?;
+            templates
+                .overrides
+                .insert(name.to_owned(), (source, Some(path)));
+        }
+        Ok(templates)
+    }
+
+    /// Adds a template called `name`, replacing any other.
+    pub fn insert(&mut self, name: impl Into<String>, source: impl Into<String>) {
+        self.overrides.insert(name.into(), (source.into(), None));
+        self.compiled = OnceLock::new();
+    }
+
+    /// The names of the templates embedded in the crate.
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN.iter().map(|&(name, _)| name)
+    }
+
+    /// Renders the templates called `names` one after the other, with the
+    /// fields of `ctx` as their variables.
+    pub fn render(
+        &self,
+        names: &[&str],
+        ctx: impl serde::Serialize,
+    ) -> Result<String, TemplateError> {
+        let ctx = Value::from_serializable(&ctx);
+        let Compiled {
+            environment,
+            broken,
+        } = self.compiled.get_or_init(|| self.compile());
+
+        // Unlike the context, globals are seen by imported templates too, so
+        // the macros of `macros.jinja` can use `model` and `equations`. The
+        // environment's templates are shared, so copying it is cheap
+        let mut env = environment.clone();
+        for key in ctx.try_iter().into_iter().flatten() {
+            if let (Some(name), Ok(value)) = (key.as_str(), ctx.get_item(&key)) {
+                // Read once rather than by every expression
+                if name == "model" {
+                    if let Ok(model) = filters::ParsedModel::new(&value) {
+                        env.add_global(filters::MODEL, Value::from_object(model));
+                    }
+                }
+                env.add_global(name.to_owned(), value);
+            }
+        }
+
+        let mut output = String::new();
+        for (i, name) in names.iter().enumerate() {
+            let rendered = env
+                .get_template(name)
+                .and_then(|template| template.render(&ctx))
+                .map_err(|err| match err.kind() {
+                    ErrorKind::TemplateNotFound => {
+                        // Either `name` or one it includes
+                        match missing_template(&err).and_then(|missing| broken.get(&missing)) {
+                            Some(broken) => broken.clone(),
+                            None => self.error(err),
+                        }
+                    }
+                    _ => self.error(err),
+                })?;
+            output.push_str(&rendered);
+
+            // minijinja drops the last newline of every template, which only
+            // the last one should lose
+            let is_last = i + 1 == names.len();
+            if !is_last && self.source(name).is_some_and(|s| s.ends_with('\n')) {
+                output.push('\n');
+            }
+        }
+        Ok(output)
+    }
+
+    fn compile(&self) -> Compiled {
+        let mut source = Source::new();
+        let mut broken = Map::new();
+        let builtins = BUILTIN
+            .iter()
+            .filter(|(name, _)| !self.overrides.contains_key(*name))
+            .map(|&(name, template)| (name, template));
+        let overrides = self
+            .overrides
+            .iter()
+            .map(|(name, (template, _))| (name.as_str(), template.as_str()));
+        for (name, template) in builtins.chain(overrides) {
+            if let Err(err) = source.add_template(name, template) {
+                broken.insert(name.to_owned(), self.error(err));
+            }
+        }
+
+        let mut environment = Environment::new();
+        environment.set_source(source);
+        filters::add_to(&mut environment);
+        Compiled {
+            environment,
+            broken,
+        }
+    }
+
+    fn source(&self, name: &str) -> Option<&str> {
+        match self.overrides.get(name) {
+            Some((source, _)) => Some(source),
+            None => BUILTIN
+                .iter()
+                .find(|&&(builtin, _)| builtin == name)
+                .map(|&(_, source)| source),
+        }
+    }
+
+    fn error(&self, err: minijinja::Error) -> TemplateError {
+        let name = err.name().unwrap_or_default();
+        let file = match self.overrides.get(name) {
+            Some((_, Some(path))) => path.display().to_string(),
+            _ => name.to_owned(),
+        };
+
+        // The message without the location minijinja appends
+        let message = err.to_string();
+        let location = format!(" (in {name}:{})", err.line().unwrap_or(0));
+        let message = message.strip_suffix(&location).unwrap_or(&message);
+
+        TemplateError {
+            file,
+            line: err.line(),
+            message: message.to_owned(),
+        }
+    }
+}
+
+/// A template which failed to parse or render.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// The path of the template if it was read from a directory, otherwise
+    /// its name.
+    pub file: String,
+    /// Starting from 1.
+    pub line: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for TemplateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{line}: {}", self.file, self.message),
+            None => write!(f, "{}: {}", self.file, self.message),
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// The name of the template a `TemplateNotFound` error is about, which
+/// minijinja only gives in its message, quoted after `template`.
+fn missing_template(err: &minijinja::Error) -> Option<String> {
+    let message = err.to_string();
+    let start = message.find("template \"")? + "template ".len();
+    serde_json::Deserializer::from_str(&message[start..])
+        .into_iter()
+        .next()?
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use minijinja::context;
+
+    use super::*;
+    use crate::transformations::test_models::scratch_directory;
+
+    #[test]
+    fn overrides() {
+        let mut templates = Templates::new();
+        templates.insert("ode-support.py", "# {{ name }} ends here\n");
+        templates.insert("header", "# {{ name }}\n");
+        templates.insert("ode.py.jinja", "{% include \"header\" %}\nimport numpy\n");
+
+        let rendered = templates
+            .render(
+                &["ode.py.jinja", "ode-support.py"],
+                context! { name => "model" },
+            )
+            .unwrap();
+
+        assert_eq!(rendered, "# model\nimport numpy\n# model ends here");
+
+        let ctx = Map::from([("name", "other")]);
+        let rendered = templates.render(&["header"], ctx).unwrap();
+        assert_eq!(rendered, "# other");
+    }
+
+    #[test]
+    fn errors_have_file_and_line() {
+        let directory = scratch_directory("template-errors");
+        std::fs::write(directory.join("ode.txt.jinja"), "fine\n{{ oops(1) }}\n").unwrap();
+
+        let templates = Templates::from_directory(&directory).unwrap();
+        let err = templates
+            .render(&["ode.txt.jinja"], context! {})
+            .unwrap_err();
+
+        assert_eq!(
+            err.file,
+            directory.join("ode.txt.jinja").display().to_string()
+        );
+        assert_eq!(err.line, Some(2));
+        assert!(err.to_string().starts_with(&format!("{}:2: ", err.file)));
+
+        let mut templates = Templates::new();
+        templates.insert("ode.py.jinja", "{% for x in %}");
+        let err = templates
+            .render(&["ode.py.jinja"], context! {})
+            .unwrap_err();
+        assert_eq!((err.file.as_str(), err.line), ("ode.py.jinja", Some(1)));
+        assert!(err.message.starts_with("syntax error"), "{}", err.message);
+    }
+
+    #[test]
+    fn only_missing_templates_blame_broken_ones() {
+        let mut templates = Templates::new();
+        templates.insert("ode.py.jinja", "{% include \"header\" %}");
+        templates.insert("header", "{% if %}");
+        // "e" is in the message of every missing template
+        templates.insert("e", "{% if %}");
+        templates.insert("ode.txt.jinja", "{% include \"nowhere\" %}");
+
+        let err = templates
+            .render(&["ode.py.jinja"], context! {})
+            .unwrap_err();
+        assert_eq!(err.file, "header");
+
+        let err = templates
+            .render(&["ode.txt.jinja"], context! {})
+            .unwrap_err();
+        assert_eq!(err.file, "ode.txt.jinja");
+        assert!(err.message.contains("\"nowhere\""), "{}", err.message);
+    }
+}
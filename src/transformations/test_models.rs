@@ -0,0 +1,325 @@
+//! Models shared by the tests of the backends, and a reference solution to
+//! check what generated programs print against.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use crate::models::ode::{Metadata, OdeModel};
+use crate::models::{Argument, Component, CompositionStyle};
+use crate::{Equation, Map};
+
+pub fn value(name: impl Into<String>, value: f64) -> Argument {
+    Argument::Value {
+        name: name.into(),
+        value,
+    }
+}
+
+pub fn composite(
+    name: impl Into<String>,
+    operation: impl Into<String>,
+    style: CompositionStyle,
+    composition: impl IntoIterator<Item = Component>,
+) -> Argument {
+    Argument::Composite {
+        name: name.into(),
+        operation: operation.into(),
+        style,
+        composition: composition.into_iter().collect(),
+    }
+}
+
+pub fn argument(name: impl Into<String>, contribution: char) -> Component {
+    Component {
+        name: name.into(),
+        contribution,
+    }
+}
+
+/// Shorthand for positive arguments
+pub fn arg(name: impl Into<String>) -> Component {
+    argument(name, '+')
+}
+
+pub fn equation(
+    name: impl Into<String>,
+    operates_on: impl Into<String>,
+    composition: Component,
+) -> Equation {
+    Equation {
+        name: name.into(),
+        operates_on: Some(operates_on.into()),
+        argument: composition.name,
+        contribution: composition.contribution,
+    }
+}
+
+/// A small model going through comparisons, powers, modulo and prefixed
+/// functions, which the snapshot of every backend is rendered from.
+pub fn abc() -> OdeModel {
+    let mut model = OdeModel::new(
+        "_".into(),
+        Metadata {
+            start_time: 0.0,
+            delta_time: 0.1,
+            end_time: 10.0,
+            ..Default::default()
+        },
+    );
+    let infixed = CompositionStyle::Infixed;
+
+    model.insert_argument(value("A", 10_f64));
+    model.insert_argument(value("B", 20_f64));
+    model.insert_argument(value("k", 0.5));
+
+    model.insert_argument(composite("A+B", "+", infixed, [arg("A"), arg("B")]));
+    model.insert_equation(equation("dA/dt", "A", arg("A+B")));
+
+    model.insert_argument(composite("A%B", "%", infixed, [arg("A"), arg("B")]));
+    model.insert_argument(composite(
+        "k<A<B",
+        "<",
+        infixed,
+        [arg("k"), arg("A"), arg("B")],
+    ));
+    model.insert_argument(composite(
+        "max(A%B, k<A<B)",
+        "max",
+        CompositionStyle::Prefixed,
+        [arg("A%B"), arg("k<A<B")],
+    ));
+    model.insert_argument(composite(
+        "B**k-max",
+        "**",
+        infixed,
+        [arg("B"), arg("k"), argument("max(A%B, k<A<B)", '-')],
+    ));
+    model.insert_equation(equation("dB/dt", "B", arg("B**k-max")));
+
+    model
+}
+
+/// Lotka-Volterra, which stays bounded for the whole time span
+pub fn predator_prey() -> OdeModel {
+    let mut model = OdeModel::new(
+        "Predator-prey".into(),
+        Metadata {
+            start_time: 0.0,
+            delta_time: 0.01,
+            end_time: 20.0,
+            ..Default::default()
+        },
+    );
+    let infixed = CompositionStyle::Infixed;
+
+    for (name, initial) in [
+        ("prey", 10.0),
+        ("predators", 5.0),
+        ("alpha", 1.1),
+        ("beta", 0.4),
+        ("gamma", 0.4),
+        ("delta", 0.1),
+    ] {
+        model.insert_argument(value(name, initial));
+    }
+
+    model.insert_argument(composite(
+        "alpha*prey",
+        "*",
+        infixed,
+        [arg("alpha"), arg("prey")],
+    ));
+    model.insert_argument(composite(
+        "beta*prey*predators",
+        "*",
+        infixed,
+        [arg("beta"), arg("prey"), arg("predators")],
+    ));
+    model.insert_argument(composite(
+        "dprey",
+        "+",
+        infixed,
+        [arg("alpha*prey"), argument("beta*prey*predators", '-')],
+    ));
+    model.insert_equation(equation("dprey/dt", "prey", arg("dprey")));
+
+    model.insert_argument(composite(
+        "delta*prey*predators",
+        "*",
+        infixed,
+        [arg("delta"), arg("prey"), arg("predators")],
+    ));
+    model.insert_argument(composite(
+        "gamma*predators",
+        "*",
+        infixed,
+        [arg("gamma"), arg("predators")],
+    ));
+    // Predators have one equation for births and another for deaths
+    model.insert_equation(equation("births", "predators", arg("delta*prey*predators")));
+    model.insert_equation(equation(
+        "deaths",
+        "predators",
+        argument("gamma*predators", '-'),
+    ));
+
+    model
+}
+
+/// A population called `population` growing at the rate of the first of
+/// `constants` and decaying by each of the others, for names which the
+/// generated programs also use for their own variables.
+pub fn named(population: &str, constants: &[&str]) -> OdeModel {
+    let mut model = OdeModel::new(
+        "Named".into(),
+        Metadata {
+            start_time: 0.0,
+            delta_time: 0.1,
+            end_time: 1.0,
+            ..Default::default()
+        },
+    );
+    let (rate, decays) = constants.split_first().unwrap();
+
+    model.insert_argument(value(population, 1.0));
+    model.insert_argument(value(*rate, 0.5));
+    let growth = format!("{rate}*{population}");
+    model.insert_argument(composite(
+        growth.as_str(),
+        "*",
+        CompositionStyle::Infixed,
+        [arg(*rate), arg(population)],
+    ));
+    model.insert_equation(equation("growth", population, arg(growth)));
+
+    for &decay in decays {
+        model.insert_argument(value(decay, 0.1));
+        model.insert_equation(equation(
+            format!("{decay} decay"),
+            population,
+            argument(decay, '-'),
+        ));
+    }
+
+    model
+}
+
+/// The RK4 integration of the generated programs, with the model evaluated
+/// in Rust. Each row holds the populations at one step.
+pub fn reference(model: &OdeModel, overrides: &Map<&str, f64>) -> Vec<Vec<f64>> {
+    let populations: Vec<&str> = model.get_populations().map(|arg| arg.name()).collect();
+
+    let rhs = |y: &[f64]| -> Vec<f64> {
+        let bindings = |name: &str| {
+            populations
+                .iter()
+                .position(|&population| population == name)
+                .map(|i| y[i])
+                .or_else(|| overrides.get(name).copied())
+        };
+        populations
+            .iter()
+            .map(|&population| {
+                model
+                    .equations
+                    .iter()
+                    .filter(|eq| eq.operates_on.as_deref() == Some(population))
+                    .map(|eq| {
+                        let value = model.evaluate(&eq.argument, &bindings).unwrap();
+                        if eq.contribution == '-' {
+                            -value
+                        } else {
+                            value
+                        }
+                    })
+                    .sum()
+            })
+            .collect()
+    };
+    let shifted = |y: &[f64], k: &[f64], h: f64| -> Vec<f64> {
+        y.iter().zip(k).map(|(y, k)| y + h * k).collect()
+    };
+
+    let Metadata {
+        start_time,
+        delta_time: h,
+        end_time,
+        ..
+    } = model.metadata;
+    let steps = ((end_time - start_time) / h).round() as usize;
+
+    let mut y: Vec<f64> = model
+        .get_populations()
+        .map(|arg| match arg {
+            Argument::Value { value, .. } => *value,
+            _ => unreachable!(),
+        })
+        .collect();
+    let mut rows = vec![y.clone()];
+
+    for _ in 0..steps {
+        let k1 = rhs(&y);
+        let k2 = rhs(&shifted(&y, &k1, h / 2.0));
+        let k3 = rhs(&shifted(&y, &k2, h / 2.0));
+        let k4 = rhs(&shifted(&y, &k3, h));
+
+        for i in 0..y.len() {
+            y[i] += h / 6.0 * (k1[i] + 2.0 * k2[i] + 2.0 * k3[i] + k4[i]);
+        }
+        rows.push(y.clone());
+    }
+
+    rows
+}
+
+/// Checks CSV rows of `t` and the populations against [`reference`],
+/// allowing for `tolerance` on every value.
+pub fn assert_matches_reference(
+    model: &OdeModel,
+    overrides: &Map<&str, f64>,
+    csv: &str,
+    tolerance: f64,
+) {
+    let expected = reference(model, overrides);
+    let rows: Vec<Vec<f64>> = csv
+        .lines()
+        .map(|line| line.split(',').map(|v| v.parse().unwrap()).collect())
+        .collect();
+    assert_eq!(rows.len(), expected.len());
+
+    for (step, (row, expected)) in rows.iter().zip(&expected).enumerate() {
+        let t = model.metadata.start_time + step as f64 * model.metadata.delta_time;
+        assert!((row[0] - t).abs() < 1e-4, "t = {} at step {step}", row[0]);
+
+        for (value, expected) in row[1..].iter().zip(expected) {
+            assert!(
+                (value - expected).abs() <= tolerance + 1e-9 * expected.abs(),
+                "{value} != {expected} at t = {t}"
+            );
+        }
+    }
+}
+
+/// A fresh directory for the files of a test called `name`.
+pub fn scratch_directory(name: &str) -> PathBuf {
+    let directory = std::env::temp_dir().join(format!("odeir-{name}-{}", std::process::id()));
+    std::fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Runs `command`, failing when its program can't be found unless
+/// `ODEIR_SKIP_MISSING_TOOLS` is set, in which case the test should be
+/// skipped on `None`.
+pub fn run(command: &mut Command) -> Option<Output> {
+    match command.output() {
+        Ok(output) => Some(output),
+        Err(err) if std::env::var_os("ODEIR_SKIP_MISSING_TOOLS").is_some() => {
+            eprintln!("skipping: cannot run {:?}: {err}", command.get_program());
+            None
+        }
+        Err(err) => panic!(
+            "cannot run {:?}: {err}, set ODEIR_SKIP_MISSING_TOOLS to skip this test",
+            command.get_program()
+        ),
+    }
+}
@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::ode::OdeModel;
+
+/// The backend of [`render_typescript`], called `typescript`.
+pub struct TypeScript;
+
+impl Backend for TypeScript {
+    fn name(&self) -> &str {
+        "typescript"
+    }
+
+    fn file_extension(&self) -> &str {
+        "ts"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        context.render_templates(&["ode.ts.jinja", "ode-support.ts"], ".ts")
+    }
+}
+
+/// Renders a TypeScript ES module exporting typed `initialValues`,
+/// `constants` and `rhs`, along with a fixed step RK4 `simulate()` over the
+/// metadata's time span and `toCsv()`, for previewing trajectories in the
+/// browser.
+///
+/// Only the `.ts` files among the model's extension files are included.
+pub fn render_typescript(
+    model: &OdeModel,
+    extension_lookup_paths: &[&PathBuf],
+) -> Result<String, TemplateError> {
+    TypeScript.render(&RenderContext::new(model, extension_lookup_paths))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::transformations::test_models::{abc, named};
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        let typescript = render_typescript(&model, &[]).unwrap();
+
+        const EXPECTED: &str = include_str!("fixtures/abc_ode.ts");
+
+        assert_eq!(typescript, EXPECTED);
+    }
+
+    #[test]
+    fn names_of_parameters_are_kept_clear() {
+        let model = named("state", &["params", "t"]);
+
+        let typescript = render_typescript(&model, &[]).unwrap();
+
+        assert!(typescript.contains("\n  const { state_ } = state;\n"));
+        assert!(typescript.contains("\n  const { params_, t_ } = params;\n"));
+    }
+}
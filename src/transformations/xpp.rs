@@ -0,0 +1,160 @@
+//! XPPAUT `.ode` export, for the bifurcation and phase plane tools of XPP
+//! and AUTO.
+
+use std::collections::BTreeSet;
+
+use minijinja::context;
+
+use super::templates::{Templates, BUILTIN_TEMPLATES};
+use super::{Backend, RenderContext, TemplateError};
+use crate::models::{ode::OdeModel, Argument, CoreModel};
+use crate::Map;
+
+/// XPP only reads this many characters of a name.
+const MAX_NAME_LENGTH: usize = 9;
+
+/// Names which XPP gives a meaning of its own, in lowercase.
+const RESERVED: [&str; 31] = [
+    "t", "pi", "if", "then", "else", "sin", "cos", "tan", "asin", "acos", "atan", "sinh", "cosh",
+    "tanh", "exp", "ln", "log", "log10", "sqrt", "abs", "max", "min", "mod", "flr", "ceil", "heav",
+    "sign", "ran", "par", "init", "pymod",
+];
+
+/// The backend of [`render_xpp`], called `xpp`.
+pub struct Xpp;
+
+impl Backend for Xpp {
+    fn name(&self) -> &str {
+        "xpp"
+    }
+
+    fn file_extension(&self) -> &str {
+        "ode"
+    }
+
+    fn render(&self, context: &RenderContext) -> Result<String, TemplateError> {
+        render_with(context.model, context.templates)
+    }
+}
+
+/// Renders the model as an XPPAUT `.ode` file, integrated over the
+/// metadata's time span.
+///
+/// XPP names are case insensitive, short and alphanumeric, so values are
+/// renamed where they aren't, for example `growth_rate` becomes `growth_ra`
+/// and `T` becomes `T1`. The constants that imports add for numbers are
+/// written as the numbers.
+pub fn render_xpp(model: &OdeModel) -> Result<String, TemplateError> {
+    render_with(model, &BUILTIN_TEMPLATES)
+}
+
+fn render_with(model: &OdeModel, templates: &Templates) -> Result<String, TemplateError> {
+    let populations = model.get_populations().collect::<Vec<_>>();
+    let constants = model
+        .get_constants()
+        .filter(|arg| !model.is_number(arg.name()))
+        .collect::<Vec<_>>();
+    let equations = model
+        .equations
+        .iter()
+        .cloned()
+        .filter_map(|eq| Some((eq.operates_on.clone()?, eq)))
+        .collect::<Map<_, _>>();
+    let uses_modulo = model.arguments.values().any(|arg| {
+        matches!(
+            arg,
+            Argument::Composite { operation, .. } if operation == "%"
+        )
+    });
+
+    let ctx = context! {
+        model => model,
+        equations => equations,
+        populations => populations,
+        constants => constants,
+        uses_modulo => uses_modulo,
+        total => model.metadata.end_time - model.metadata.start_time,
+    };
+
+    templates.render(&["xpp.ode.jinja"], ctx)
+}
+
+/// How each value is written in XPP.
+pub(super) fn names(model: &CoreModel) -> Map<&str, String> {
+    let mut names = Map::new();
+    let mut taken: BTreeSet<String> = RESERVED.iter().map(|name| name.to_string()).collect();
+
+    for argument in model.arguments.values() {
+        let Argument::Value { name, value } = argument else {
+            continue;
+        };
+        if model.is_number(name) {
+            names.insert(name.as_str(), value.to_string());
+            continue;
+        }
+
+        let mut base: String = name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+            .take(MAX_NAME_LENGTH)
+            .collect();
+        if !base.starts_with(|c: char| c.is_ascii_alphabetic()) {
+            base.insert(0, 'v');
+            base.truncate(MAX_NAME_LENGTH);
+        }
+
+        let mut candidate = base.clone();
+        for suffix in 1.. {
+            if !taken.contains(&candidate.to_lowercase()) {
+                break;
+            }
+            let suffix = suffix.to_string();
+            let mut shortened = base.clone();
+            shortened.truncate(MAX_NAME_LENGTH - suffix.len());
+            candidate = shortened + &suffix;
+        }
+
+        taken.insert(candidate.to_lowercase());
+        names.insert(name.as_str(), candidate);
+    }
+
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::models::ode::Metadata;
+    use crate::transformations::test_models::{abc, value};
+
+    use super::*;
+
+    #[test]
+    fn render_simple() {
+        let model = abc();
+
+        assert_eq!(
+            render_xpp(&model).unwrap(),
+            include_str!("fixtures/abc.ode")
+        );
+    }
+
+    #[test]
+    fn names_are_valid_in_xpp() {
+        let mut model = OdeModel::new("names".into(), Metadata::default());
+        for name in ["growth_rate", "growth_rates", "T", "t", "2x", "c2"] {
+            model.insert_argument(value(name, 2.0));
+        }
+        model.insert_number(3.0);
+
+        let names = names(&model);
+
+        assert_eq!(names["growth_rate"], "growth_ra");
+        assert_eq!(names["growth_rates"], "growth_r1");
+        assert_eq!(names["T"], "T1");
+        assert_eq!(names["t"], "t2");
+        assert_eq!(names["2x"], "v2x");
+        assert_eq!(names["c2"], "c2");
+        // Added by imports for the number 3
+        assert_eq!(names["c3"], "3");
+    }
+}
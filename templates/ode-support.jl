@@ -0,0 +1,64 @@
+
+
+function simulation_output_to_csv(solution, io::IO)
+    println(io, "t,", join(VARIABLE_NAMES, ","))
+
+    for (t, u) in zip(solution.t, solution.u)
+        println(io, t, ",", join((@sprintf("%.4f", value) for value in u), ","))
+    end
+end
+
+
+function update_constants_with_params(p, params)
+    updated = copy(p)
+
+    for (name, value) in params
+        index = findfirst(==(name), CONSTANT_NAMES)
+        if index !== nothing
+            updated[index] = value
+        end
+    end
+
+    return updated
+end
+
+
+function parse_params(params::AbstractString)
+    return [
+        (name, parse(Float64, value))
+        for (name, value) in (split(param, "=") for param in split(params))
+    ]
+end
+
+
+function main(args=ARGS)
+    output = nothing
+    params = ""
+
+    i = 1
+    while i <= length(args)
+        if args[i] in ("-o", "--output")
+            output = args[i+1]
+            i += 2
+        elseif args[i] == "--params"
+            params = args[i+1]
+            i += 2
+        else
+            error("unknown argument $(args[i])")
+        end
+    end
+
+    problem = ODEProblem(f!, u0, tspan, update_constants_with_params(p, parse_params(params)))
+    solution = solve(problem; saveat=tspan[1]:dt:tspan[2])
+
+    if output === nothing
+        simulation_output_to_csv(solution, stdout)
+    else
+        open(io -> simulation_output_to_csv(solution, io), output, "w")
+    end
+end
+
+
+if abspath(PROGRAM_FILE) == @__FILE__
+    main()
+end